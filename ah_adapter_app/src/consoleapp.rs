@@ -8,6 +8,8 @@ use ah_system_adapter::{
     ArrowheadCloud, ArrowheadProvider, InterfaceEntry, NoEntryTag, RegisterServiceInput,
     RequestOrchestrationInput, ServiceDefinitionEntry, ServiceRequirements,
 };
+#[cfg(feature = "json-schema")]
+use ah_system_adapter::{ArrowheadService, EntryTag, Orchestration};
 
 use clap::{ArgEnum, Args, Parser, Subcommand};
 use regex::Regex;
@@ -106,6 +108,17 @@ impl ConsoleApp {
                     let settings = controller.get_settings()?;
                     serde_table::print_object("Settings", &settings, column_width)?;
                 }
+                #[cfg(feature = "json-schema")]
+                ShowCommand::Schema { data } => {
+                    let schema = match data {
+                        StoredData::Orchestrations => schemars::schema_for!(Orchestration),
+                        StoredData::Services => schemars::schema_for!(ArrowheadService<EntryTag>),
+                        StoredData::Settings => schemars::schema_for!(Settings),
+                    };
+                    let schema = serde_json::to_string_pretty(&schema)
+                        .map_err(|err| Error::PrintError(format!("{}", err)))?;
+                    println!("{}", schema);
+                }
             },
             Unregister(UnregisterCommand::Service { name }) => {
                 controller.unregister_service(&name)?;
@@ -178,6 +191,22 @@ enum ShowCommand {
 
     /// Show the settings
     Settings,
+
+    /// Show the JSON Schema of the files stored by the application
+    #[cfg(feature = "json-schema")]
+    Schema {
+        /// The stored data described by the schema
+        #[clap(arg_enum)]
+        data: StoredData,
+    },
+}
+
+#[cfg(feature = "json-schema")]
+#[derive(ArgEnum, Clone)]
+enum StoredData {
+    Orchestrations,
+    Services,
+    Settings,
 }
 
 #[derive(Subcommand)]
@@ -276,15 +305,15 @@ struct RequestOrchestrationIdCommand {
 
 #[derive(Args)]
 struct SetSettingsCommand {
-    /// Address of Service Registry core system
+    /// Address of Service Registry core system (comma separated addresses of redundant instances)
     #[clap(long)]
     service_registry_address: Option<String>,
 
-    /// Address of Authorization core system
+    /// Address of Authorization core system (comma separated addresses of redundant instances)
     #[clap(long)]
     authorization_address: Option<String>,
 
-    /// Address of Orchestrator core system
+    /// Address of Orchestrator core system (comma separated addresses of redundant instances)
     #[clap(long)]
     orchestrator_address: Option<String>,
 
@@ -409,6 +438,7 @@ impl TryInto<RequestOrchestrationInput> for RequestOrchestrationCommand {
                             .parse()
                             .map_err(|e| Error::CommandError(format!("Error during parsing field 'system-port' of argument 'preferred-provider': {}", e)))?,
                         authentication_info: pp.get("authentication-info").map(String::clone),
+                        metadata: None,
                     },
                 }])
             }
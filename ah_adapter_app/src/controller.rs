@@ -2,8 +2,8 @@ use crate::datastore::{self, DataStore};
 use crate::settings::Settings;
 
 use ah_system_adapter::{
-    ArrowheadService, ArrowheadSystem, ArrowheadSystemAdapter, EntryTag, NoEntryTag, Orchestration,
-    RegisterServiceInput, RequestOrchestrationInput,
+    ArrowheadCore, ArrowheadService, ArrowheadSystem, ArrowheadSystemAdapter, CoreSystemEndpoints,
+    EntryTag, NoEntryTag, Orchestration, RegisterServiceInput, RequestOrchestrationInput,
 };
 
 use std::error;
@@ -73,8 +73,9 @@ impl Controller {
     pub fn unregister_service(&self, name: &str) -> Result<()> {
         let system_adapter = self.get_system_adapter()?;
 
-        let service_definition_entry = &self.data_store.get_services(name)?[0].1.service_definition;
-        system_adapter.unregister_service(service_definition_entry.get_service_definition())?;
+        // The stored entry is unregistered exactly, other entries of the same service definition
+        // (e.g. with another service URI) are kept
+        system_adapter.unregister_service_entry(&self.data_store.get_services(name)?[0].1)?;
 
         self.data_store.delete_service(name)?;
 
@@ -135,20 +136,30 @@ impl Controller {
         Ok(self.data_store.get_orchestrations(name_regex)?)
     }
 
-    fn get_system_adapter(&self) -> Result<ArrowheadSystemAdapter> {
+    fn get_system_adapter(&self) -> Result<Box<dyn ArrowheadCore>> {
         let settings = self.get_settings()?;
         let err_not_set =
             |missing| Error::SettingsError(format!("The value '{}' is not set", missing));
-        Ok(ArrowheadSystemAdapter::new(
-            &settings
-                .service_registry_address
-                .ok_or_else(|| err_not_set("serviceRegistryAddress"))?,
-            &settings
-                .authorization_address
-                .ok_or_else(|| err_not_set("authorizationAddress"))?,
-            &settings
-                .orchestrator_address
-                .ok_or_else(|| err_not_set("orchestratorAddress"))?,
+        // Redundant core system instances are given as comma separated addresses
+        let endpoints = |addresses: String| {
+            CoreSystemEndpoints::new(&addresses.split(',').map(str::trim).collect::<Vec<_>>())
+        };
+        Ok(Box::new(ArrowheadSystemAdapter::with_endpoints(
+            endpoints(
+                settings
+                    .service_registry_address
+                    .ok_or_else(|| err_not_set("serviceRegistryAddress"))?,
+            )?,
+            endpoints(
+                settings
+                    .authorization_address
+                    .ok_or_else(|| err_not_set("authorizationAddress"))?,
+            )?,
+            endpoints(
+                settings
+                    .orchestrator_address
+                    .ok_or_else(|| err_not_set("orchestratorAddress"))?,
+            )?,
             ArrowheadSystem {
                 entry_tag: NoEntryTag {},
                 system_name: settings
@@ -161,7 +172,8 @@ impl Controller {
                     .system_port
                     .ok_or_else(|| err_not_set("systemPort"))?,
                 authentication_info: settings.system_authentication_info,
+                metadata: None,
             },
-        )?)
+        )))
     }
 }
@@ -3,13 +3,17 @@ mod controller;
 mod datastore;
 mod serde_table;
 mod settings;
+mod telemetry;
 
 use consoleapp::ConsoleApp;
 use controller::Controller;
 use datastore::DataStore;
+use telemetry::Telemetry;
 
 fn main() {
+    let telemetry = Telemetry::init();
     let data_store = DataStore::new().unwrap();
     let controller = Controller { data_store };
     ConsoleApp::run(&controller);
+    telemetry.report();
 }
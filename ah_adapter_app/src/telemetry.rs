@@ -0,0 +1,72 @@
+#[cfg(feature = "metrics")]
+use metrics_util::debugging::{DebugValue, DebuggingRecorder, Snapshotter};
+use tracing_subscriber::filter::LevelFilter;
+
+use std::env;
+
+pub struct Telemetry {
+    #[cfg(feature = "metrics")]
+    snapshotter: Option<Snapshotter>,
+}
+
+impl Telemetry {
+    // Tracing is enabled by the AH_LOG environment variable holding the maximum level, request
+    // bodies are logged only if AH_LOG_BODIES is set to true. With the metrics feature, the
+    // metrics collected during the command are printed if AH_METRICS is set to true.
+    pub fn init() -> Self {
+        if let Some(level) = env::var("AH_LOG")
+            .ok()
+            .and_then(|level| level.parse::<LevelFilter>().ok())
+        {
+            tracing_subscriber::fmt()
+                .with_max_level(level)
+                .with_writer(std::io::stderr)
+                .init();
+        }
+        ah_system_adapter::set_body_logging(Self::is_enabled("AH_LOG_BODIES"));
+
+        #[cfg(feature = "metrics")]
+        {
+            let snapshotter = if Self::is_enabled("AH_METRICS") {
+                let recorder = DebuggingRecorder::new();
+                let snapshotter = recorder.snapshotter();
+                recorder.install().ok().map(|_| snapshotter)
+            } else {
+                None
+            };
+            Self { snapshotter }
+        }
+        #[cfg(not(feature = "metrics"))]
+        Self {}
+    }
+
+    pub fn report(&self) {
+        #[cfg(feature = "metrics")]
+        if let Some(snapshotter) = &self.snapshotter {
+            for (composite_key, _, _, value) in snapshotter.snapshot().into_vec() {
+                let key = composite_key.key();
+                let labels: Vec<String> = key
+                    .labels()
+                    .map(|l| format!("{}={}", l.key(), l.value()))
+                    .collect();
+                let value = match value {
+                    DebugValue::Counter(count) => format!("{}", count),
+                    DebugValue::Gauge(gauge) => format!("{}", gauge),
+                    DebugValue::Histogram(samples) => {
+                        let sum: f64 = samples.iter().map(|s| s.into_inner()).sum();
+                        format!(
+                            "count={} mean={:.6}",
+                            samples.len(),
+                            sum / samples.len().max(1) as f64
+                        )
+                    }
+                };
+                eprintln!("{}{{{}}} {}", key.name(), labels.join(","), value);
+            }
+        }
+    }
+
+    fn is_enabled(variable: &str) -> bool {
+        env::var(variable).is_ok_and(|value| value.eq_ignore_ascii_case("true"))
+    }
+}
@@ -0,0 +1,295 @@
+use aes::Aes256;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use cbc::cipher::block_padding::Pkcs7;
+use cbc::cipher::{BlockDecryptMut, KeyIvInit};
+use hmac::{Hmac, Mac};
+use rsa::pkcs1v15::{Signature, VerifyingKey};
+use rsa::pkcs8::{DecodePrivateKey, DecodePublicKey};
+use rsa::signature::Verifier;
+use rsa::{Oaep, RsaPrivateKey, RsaPublicKey};
+use serde::Deserialize;
+use sha2::{Sha256, Sha512};
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const ISSUER: &str = "Authorization";
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub(crate) enum TokenError {
+    Malformed,
+    InvalidSignature,
+    Expired,
+    WrongIssuer,
+}
+
+#[derive(Deserialize, PartialEq, Clone, Debug)]
+pub(crate) struct TokenClaims {
+    pub iss: String,
+    pub exp: Option<u64>,
+    pub cid: String,
+    pub sid: String,
+    pub iid: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Header {
+    alg: String,
+    enc: Option<String>,
+}
+
+// Verifies the access tokens generated by the Authorization core system. The tokens are JWS
+// (RS512) signed by the Authorization, nested in JWE (RSA-OAEP-256, A256CBC-HS512) encrypted
+// with the public key of the provider.
+pub(crate) struct TokenVerifier {
+    provider_private_key: RsaPrivateKey,
+    authorization_public_key: VerifyingKey<Sha512>,
+}
+
+impl TokenVerifier {
+    // The private key is PKCS#8 PEM, the public key is the base64 encoded DER returned by the
+    // publickey endpoint of the Authorization (optionally as a JSON string).
+    pub(crate) fn new(
+        provider_private_key_pem: &str,
+        authorization_public_key: &str,
+    ) -> Option<Self> {
+        let public_key_der = STANDARD
+            .decode(authorization_public_key.trim().trim_matches('"'))
+            .ok()?;
+        Some(Self {
+            provider_private_key: RsaPrivateKey::from_pkcs8_pem(provider_private_key_pem).ok()?,
+            authorization_public_key: VerifyingKey::new(
+                RsaPublicKey::from_public_key_der(&public_key_der).ok()?,
+            ),
+        })
+    }
+
+    pub(crate) fn verify(&self, token: &str) -> Result<TokenClaims, TokenError> {
+        let jws = self.decrypt(token)?;
+        let claims: TokenClaims = self.verify_signature(&jws)?;
+        if claims.iss != ISSUER {
+            return Err(TokenError::WrongIssuer);
+        }
+        if let Some(exp) = claims.exp {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_err(|_| TokenError::Expired)?
+                .as_secs();
+            if exp <= now {
+                return Err(TokenError::Expired);
+            }
+        }
+        Ok(claims)
+    }
+
+    fn decrypt(&self, token: &str) -> Result<String, TokenError> {
+        let parts: Vec<&str> = token.split('.').collect();
+        let [header, encrypted_key, iv, ciphertext, tag] = parts[..] else {
+            return Err(TokenError::Malformed);
+        };
+        let decoded_header: Header =
+            serde_json::from_slice(&decode(header)?).map_err(|_| TokenError::Malformed)?;
+        if decoded_header.alg != "RSA-OAEP-256"
+            || decoded_header.enc.as_deref() != Some("A256CBC-HS512")
+        {
+            return Err(TokenError::Malformed);
+        }
+        let content_key = self
+            .provider_private_key
+            .decrypt(Oaep::new::<Sha256>(), &decode(encrypted_key)?)
+            .map_err(|_| TokenError::InvalidSignature)?;
+        if content_key.len() != 64 {
+            return Err(TokenError::Malformed);
+        }
+        let (mac_key, encryption_key) = content_key.split_at(32);
+        let (iv, ciphertext) = (decode(iv)?, decode(ciphertext)?);
+
+        // The authentication tag is the first half of the HMAC over the additional
+        // authenticated data (the encoded header), the IV, the ciphertext and the AAD bit length.
+        // A shorter tag would be checked against fewer bytes, so only the full half is accepted.
+        let tag = decode(tag)?;
+        if tag.len() != 32 {
+            return Err(TokenError::InvalidSignature);
+        }
+        let mut mac = Hmac::<Sha512>::new_from_slice(mac_key).map_err(|_| TokenError::Malformed)?;
+        mac.update(header.as_bytes());
+        mac.update(&iv);
+        mac.update(&ciphertext);
+        mac.update(&((header.len() as u64) * 8).to_be_bytes());
+        mac.verify_truncated_left(&tag)
+            .map_err(|_| TokenError::InvalidSignature)?;
+
+        let plaintext = cbc::Decryptor::<Aes256>::new_from_slices(encryption_key, &iv)
+            .map_err(|_| TokenError::Malformed)?
+            .decrypt_padded_vec_mut::<Pkcs7>(&ciphertext)
+            .map_err(|_| TokenError::Malformed)?;
+        String::from_utf8(plaintext).map_err(|_| TokenError::Malformed)
+    }
+
+    fn verify_signature(&self, jws: &str) -> Result<TokenClaims, TokenError> {
+        let parts: Vec<&str> = jws.split('.').collect();
+        let [header, payload, signature] = parts[..] else {
+            return Err(TokenError::Malformed);
+        };
+        let decoded_header: Header =
+            serde_json::from_slice(&decode(header)?).map_err(|_| TokenError::Malformed)?;
+        if decoded_header.alg != "RS512" {
+            return Err(TokenError::Malformed);
+        }
+        let signature = Signature::try_from(decode(signature)?.as_slice())
+            .map_err(|_| TokenError::Malformed)?;
+        self.authorization_public_key
+            .verify(format!("{}.{}", header, payload).as_bytes(), &signature)
+            .map_err(|_| TokenError::InvalidSignature)?;
+        serde_json::from_slice(&decode(payload)?).map_err(|_| TokenError::Malformed)
+    }
+}
+
+fn decode(part: &str) -> Result<Vec<u8>, TokenError> {
+    URL_SAFE_NO_PAD
+        .decode(part)
+        .map_err(|_| TokenError::Malformed)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use cbc::cipher::BlockEncryptMut;
+    use rsa::pkcs1v15::SigningKey;
+    use rsa::pkcs8::EncodePublicKey;
+    use rsa::signature::{SignatureEncoding, Signer};
+    use serde_json::{json, Value};
+
+    pub(crate) fn key(name: &str) -> String {
+        std::fs::read_to_string(format!(
+            "{}/fixtures/keys/{}.pem",
+            env!("CARGO_MANIFEST_DIR"),
+            name
+        ))
+        .unwrap()
+    }
+
+    pub(crate) fn public_key(name: &str) -> String {
+        let public_key = RsaPrivateKey::from_pkcs8_pem(&key(name))
+            .unwrap()
+            .to_public_key()
+            .to_public_key_der()
+            .unwrap();
+        STANDARD.encode(public_key.as_bytes())
+    }
+
+    // Generates a token the same way as the Authorization core system.
+    pub(crate) fn token(claims: Value, signer: &str, recipient: &str) -> String {
+        let encode = |bytes: &[u8]| URL_SAFE_NO_PAD.encode(bytes);
+        let signing_key =
+            SigningKey::<Sha512>::new(RsaPrivateKey::from_pkcs8_pem(&key(signer)).unwrap());
+        let signing_input = format!(
+            "{}.{}",
+            encode(json!({ "alg": "RS512" }).to_string().as_bytes()),
+            encode(claims.to_string().as_bytes())
+        );
+        let signature = signing_key.sign(signing_input.as_bytes()).to_bytes();
+        let jws = format!("{}.{}", signing_input, encode(&signature));
+
+        let content_key: Vec<u8> = (0..64).collect();
+        let iv = [7u8; 16];
+        let recipient_key = RsaPrivateKey::from_pkcs8_pem(&key(recipient))
+            .unwrap()
+            .to_public_key();
+        let encrypted_key = recipient_key
+            .encrypt(&mut rand::thread_rng(), Oaep::new::<Sha256>(), &content_key)
+            .unwrap();
+        let header = encode(
+            json!({ "alg": "RSA-OAEP-256", "enc": "A256CBC-HS512", "cty": "JWT" })
+                .to_string()
+                .as_bytes(),
+        );
+        let ciphertext = cbc::Encryptor::<Aes256>::new_from_slices(&content_key[32..], &iv)
+            .unwrap()
+            .encrypt_padded_vec_mut::<Pkcs7>(jws.as_bytes());
+        let mut mac = Hmac::<Sha512>::new_from_slice(&content_key[..32]).unwrap();
+        mac.update(header.as_bytes());
+        mac.update(&iv);
+        mac.update(&ciphertext);
+        mac.update(&((header.len() as u64) * 8).to_be_bytes());
+        let tag = mac.finalize().into_bytes();
+
+        format!(
+            "{}.{}.{}.{}.{}",
+            header,
+            encode(&encrypted_key),
+            encode(&iv),
+            encode(&ciphertext),
+            encode(&tag[..32])
+        )
+    }
+
+    fn verifier() -> TokenVerifier {
+        TokenVerifier::new(&key("provider"), &public_key("authorization")).unwrap()
+    }
+
+    #[test]
+    fn verify_token() {
+        let claims = json!({
+            "iss": "Authorization",
+            "cid": "consumer",
+            "sid": "temperature",
+            "iid": "HTTP-SECURE-JSON"
+        });
+
+        assert_eq!(
+            verifier().verify(&token(claims, "authorization", "provider")),
+            Ok(TokenClaims {
+                iss: "Authorization".to_owned(),
+                exp: None,
+                cid: "consumer".to_owned(),
+                sid: "temperature".to_owned(),
+                iid: Some("HTTP-SECURE-JSON".to_owned()),
+            })
+        );
+    }
+
+    #[test]
+    fn reject_invalid_tokens() {
+        let claims = json!({ "iss": "Authorization", "cid": "consumer", "sid": "temperature" });
+        let verifier = verifier();
+
+        assert_eq!(
+            verifier.verify(&token(claims.clone(), "provider", "provider")),
+            Err(TokenError::InvalidSignature)
+        );
+        assert_eq!(
+            verifier.verify(&token(claims.clone(), "authorization", "authorization")),
+            Err(TokenError::InvalidSignature)
+        );
+        assert_eq!(
+            verifier.verify(&token(
+                json!({ "iss": "Authorization", "exp": 1, "cid": "consumer", "sid": "temperature" }),
+                "authorization",
+                "provider"
+            )),
+            Err(TokenError::Expired)
+        );
+        assert_eq!(verifier.verify("token"), Err(TokenError::Malformed));
+    }
+
+    #[test]
+    fn reject_truncated_tag() {
+        let claims = json!({ "iss": "Authorization", "cid": "consumer", "sid": "temperature" });
+        let token = token(claims, "authorization", "provider");
+        let (token, tag) = token.rsplit_once('.').unwrap();
+        let tag = URL_SAFE_NO_PAD.decode(tag).unwrap();
+        let verifier = verifier();
+
+        for length in [0, 1, 16, 31] {
+            assert_eq!(
+                verifier.verify(&format!(
+                    "{}.{}",
+                    token,
+                    URL_SAFE_NO_PAD.encode(&tag[..length])
+                )),
+                Err(TokenError::InvalidSignature)
+            );
+        }
+    }
+}
@@ -0,0 +1,566 @@
+use crate::dtos::{
+    ArrowheadSystem, EntryTag, NoEntryTag, Orchestration, PageRequest, ServiceQueryForm,
+};
+use crate::error::{Error, Result};
+use crate::instrumentation::{self, SendObserved, CHOREOGRAPHER};
+
+use reqwest::blocking::Client;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChoreographerPlan<T> {
+    #[serde(flatten)]
+    pub entry_tag: T,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_action_name: Option<String>,
+    pub actions: Vec<ChoreographerAction<T>>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChoreographerAction<T> {
+    #[serde(flatten)]
+    pub entry_tag: T,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_action_name: Option<String>,
+    pub first_step_names: Vec<String>,
+    pub steps: Vec<ChoreographerStep<T>>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChoreographerStep<T> {
+    #[serde(flatten)]
+    pub entry_tag: T,
+    pub name: String,
+    pub service_definition: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_version: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_version: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sr_template: Option<ServiceQueryForm>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub static_parameters: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quantity: Option<u32>,
+    #[serde(default)]
+    pub next_step_names: Vec<String>,
+}
+
+#[derive(Deserialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChoreographerPlanList {
+    pub data: Vec<ChoreographerPlan<EntryTag>>,
+    pub count: u32,
+}
+
+#[derive(Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChoreographerRunPlanRequest {
+    pub plan_id: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quantity: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_inter_cloud: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub choose_optimal_executor: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notify_uri: Option<String>,
+}
+
+#[derive(Deserialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChoreographerRunPlanResponse {
+    pub plan_id: u32,
+    pub session_id: Option<u32>,
+    pub quantity: Option<u32>,
+    pub status: ChoreographerSessionStatus,
+    #[serde(default)]
+    pub error_messages: Vec<String>,
+    #[serde(default)]
+    pub need_inter_cloud: bool,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ChoreographerSessionStatus {
+    Initiated,
+    Running,
+    Aborted,
+    Done,
+}
+
+#[derive(Deserialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChoreographerSession {
+    #[serde(flatten)]
+    pub entry_tag: EntryTag,
+    pub plan_id: u32,
+    pub plan_name: String,
+    pub status: ChoreographerSessionStatus,
+    pub quantity: u32,
+    pub execution_number: u32,
+    pub notify_uri: Option<String>,
+}
+
+#[derive(Deserialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChoreographerSessionList {
+    pub data: Vec<ChoreographerSession>,
+    pub count: u32,
+}
+
+#[derive(Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChoreographerExecutorForm {
+    pub system: ArrowheadSystem<NoEntryTag>,
+    pub base_uri: String,
+    pub service_definition_name: String,
+    pub min_version: u32,
+    pub max_version: u32,
+}
+
+#[derive(Deserialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChoreographerExecutorEntry {
+    #[serde(flatten)]
+    pub entry_tag: EntryTag,
+    pub name: String,
+    pub address: String,
+    pub port: u32,
+    pub base_uri: String,
+    #[serde(default)]
+    pub service_definitions: Vec<ChoreographerExecutorServiceDefinition>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChoreographerExecutorServiceDefinition {
+    pub service_definition_name: String,
+    pub min_version: u32,
+    pub max_version: u32,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChoreographerExecuteStepRequest {
+    pub session_id: u32,
+    pub session_step_id: u32,
+    pub main_orchestration_result: Orchestration,
+    #[serde(default)]
+    pub precondition_orchestration_results: Vec<Orchestration>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quantity: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub static_parameters: Option<HashMap<String, String>>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChoreographerAbortStepRequest {
+    pub session_id: u32,
+    pub session_step_id: u32,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ChoreographerExecutedStepStatus {
+    Success,
+    Error,
+    FatalError,
+    Aborted,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChoreographerExecutedStepResult {
+    pub session_id: u32,
+    pub session_step_id: u32,
+    pub status: ChoreographerExecutedStepStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exception: Option<String>,
+}
+
+pub struct ChoreographerAdapter {
+    pub choreographer_address: Url,
+}
+
+impl ChoreographerAdapter {
+    pub fn new(choreographer_address: &str) -> Result<Self> {
+        Ok(ChoreographerAdapter {
+            choreographer_address: choreographer_address.try_into()?,
+        })
+    }
+
+    pub fn echo_choreographer(&self) -> Result<()> {
+        instrumentation::observe(CHOREOGRAPHER, "echo_choreographer", || {
+            let client = Client::new();
+            client
+                .get(self.choreographer_address.join("echo")?)
+                .send_observed()?
+                .error_for_status()?;
+            Ok(())
+        })
+    }
+
+    pub fn get_plans(&self, page_request: &PageRequest) -> Result<ChoreographerPlanList> {
+        instrumentation::observe(CHOREOGRAPHER, "get_plans", || {
+            let client = Client::new();
+            let mut url = self.choreographer_address.join("mgmt/plan")?;
+            page_request.append_query_pairs(&mut url);
+            let response = client.get(url).send_observed()?;
+            if response.status().is_client_error() {
+                Err(Error::ArrowheadError(response.json()?))
+            } else {
+                Ok(response.json()?)
+            }
+        })
+    }
+
+    pub fn get_plan(&self, id: u32) -> Result<ChoreographerPlan<EntryTag>> {
+        instrumentation::observe(CHOREOGRAPHER, "get_plan", || {
+            let client = Client::new();
+            let response = client
+                .get(
+                    self.choreographer_address
+                        .join(&format!("mgmt/plan/{}", id))?,
+                )
+                .send_observed()?;
+            if response.status().is_client_error() {
+                Err(Error::ArrowheadError(response.json()?))
+            } else {
+                Ok(response.json()?)
+            }
+        })
+    }
+
+    pub fn add_plans(&self, plans: &[ChoreographerPlan<NoEntryTag>]) -> Result<()> {
+        instrumentation::observe(CHOREOGRAPHER, "add_plans", || {
+            let client = Client::new();
+            let response = client
+                .post(self.choreographer_address.join("mgmt/plan")?)
+                .json(&plans)
+                .send_observed()?;
+            if response.status().is_client_error() {
+                Err(Error::ArrowheadError(response.json()?))
+            } else {
+                response.error_for_status()?;
+                Ok(())
+            }
+        })
+    }
+
+    pub fn remove_plan(&self, id: u32) -> Result<()> {
+        instrumentation::observe(CHOREOGRAPHER, "remove_plan", || {
+            let client = Client::new();
+            let response = client
+                .delete(
+                    self.choreographer_address
+                        .join(&format!("mgmt/plan/{}", id))?,
+                )
+                .send_observed()?;
+            if response.status().is_client_error() {
+                Err(Error::ArrowheadError(response.json()?))
+            } else {
+                response.error_for_status()?;
+                Ok(())
+            }
+        })
+    }
+
+    pub fn start_sessions(
+        &self,
+        requests: &[ChoreographerRunPlanRequest],
+    ) -> Result<Vec<ChoreographerRunPlanResponse>> {
+        instrumentation::observe(CHOREOGRAPHER, "start_sessions", || {
+            let client = Client::new();
+            let response = client
+                .post(self.choreographer_address.join("mgmt/session/start")?)
+                .json(&requests)
+                .send_observed()?;
+            if response.status().is_client_error() {
+                Err(Error::ArrowheadError(response.json()?))
+            } else {
+                Ok(response.json()?)
+            }
+        })
+    }
+
+    pub fn get_sessions(&self, page_request: &PageRequest) -> Result<ChoreographerSessionList> {
+        instrumentation::observe(CHOREOGRAPHER, "get_sessions", || {
+            let client = Client::new();
+            let mut url = self.choreographer_address.join("mgmt/session")?;
+            page_request.append_query_pairs(&mut url);
+            let response = client.get(url).send_observed()?;
+            if response.status().is_client_error() {
+                Err(Error::ArrowheadError(response.json()?))
+            } else {
+                Ok(response.json()?)
+            }
+        })
+    }
+
+    pub fn get_session(&self, id: u32) -> Result<ChoreographerSession> {
+        instrumentation::observe(CHOREOGRAPHER, "get_session", || {
+            let client = Client::new();
+            let response = client
+                .get(
+                    self.choreographer_address
+                        .join(&format!("mgmt/session/{}", id))?,
+                )
+                .send_observed()?;
+            if response.status().is_client_error() {
+                Err(Error::ArrowheadError(response.json()?))
+            } else {
+                Ok(response.json()?)
+            }
+        })
+    }
+
+    pub fn register_executor(
+        &self,
+        executor_form: &ChoreographerExecutorForm,
+    ) -> Result<ChoreographerExecutorEntry> {
+        instrumentation::observe(CHOREOGRAPHER, "register_executor", || {
+            let client = Client::new();
+            let response = client
+                .post(self.choreographer_address.join("executor")?)
+                .json(executor_form)
+                .send_observed()?;
+            if response.status().is_client_error() {
+                Err(Error::ArrowheadError(response.json()?))
+            } else {
+                Ok(response.json()?)
+            }
+        })
+    }
+
+    pub fn unregister_executor(&self, address: &str, port: u32, base_uri: &str) -> Result<()> {
+        instrumentation::observe(CHOREOGRAPHER, "unregister_executor", || {
+            let client = Client::new();
+            let mut url = self.choreographer_address.join("executor")?;
+            url.query_pairs_mut().extend_pairs(&[
+                ("address", address),
+                ("port", &port.to_string()),
+                ("base_uri", base_uri),
+            ]);
+            let response = client.delete(url).send_observed()?;
+            if response.status().is_client_error() {
+                Err(Error::ArrowheadError(response.json()?))
+            } else {
+                Ok(())
+            }
+        })
+    }
+
+    pub fn notify_step_done(&self, step_result: &ChoreographerExecutedStepResult) -> Result<()> {
+        instrumentation::observe(CHOREOGRAPHER, "notify_step_done", || {
+            let client = Client::new();
+            let response = client
+                .post(self.choreographer_address.join("session/notifyStepDone")?)
+                .json(step_result)
+                .send_observed()?;
+            if response.status().is_client_error() {
+                Err(Error::ArrowheadError(response.json()?))
+            } else {
+                response.error_for_status()?;
+                Ok(())
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Matcher;
+    use serde_json::json;
+
+    #[test]
+    fn get_plan() {
+        let mock = mockito::mock("GET", "/mgmt/plan/1")
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                  "id": 1,
+                  "name": "plan",
+                  "firstActionName": "action",
+                  "actions": [
+                    {
+                      "id": 2,
+                      "name": "action",
+                      "firstStepNames": ["step"],
+                      "steps": [
+                        {
+                          "id": 3,
+                          "name": "step",
+                          "serviceDefinition": "move",
+                          "minVersion": 1,
+                          "maxVersion": 2,
+                          "quantity": 1,
+                          "nextStepNames": [],
+                          "createdAt": "string",
+                          "updatedAt": "string"
+                        }
+                      ],
+                      "createdAt": "string",
+                      "updatedAt": "string"
+                    }
+                  ],
+                  "createdAt": "string",
+                  "updatedAt": "string"
+                })
+                .to_string(),
+            )
+            .create();
+        let adapter = ChoreographerAdapter::new(&mockito::server_url()).unwrap();
+        let result = adapter.get_plan(1);
+        let entry_tag = |id| EntryTag {
+            id,
+            created_at: "string".to_owned(),
+            updated_at: "string".to_owned(),
+        };
+        let expected_plan = ChoreographerPlan {
+            entry_tag: entry_tag(1),
+            name: "plan".to_owned(),
+            first_action_name: Some("action".to_owned()),
+            actions: vec![ChoreographerAction {
+                entry_tag: entry_tag(2),
+                name: "action".to_owned(),
+                next_action_name: None,
+                first_step_names: vec!["step".to_owned()],
+                steps: vec![ChoreographerStep {
+                    entry_tag: entry_tag(3),
+                    name: "step".to_owned(),
+                    service_definition: "move".to_owned(),
+                    min_version: Some(1),
+                    max_version: Some(2),
+                    sr_template: None,
+                    static_parameters: None,
+                    quantity: Some(1),
+                    next_step_names: vec![],
+                }],
+            }],
+        };
+        assert!(matches!(result, Ok(plan) if plan == expected_plan));
+        mock.assert();
+    }
+
+    #[test]
+    fn get_plans_paged() {
+        let mock = mockito::mock("GET", "/mgmt/plan")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("page".into(), "0".into()),
+                Matcher::UrlEncoded("item_per_page".into(), "10".into()),
+            ]))
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "data": [], "count": 0 }).to_string())
+            .create();
+        let adapter = ChoreographerAdapter::new(&mockito::server_url()).unwrap();
+        let result = adapter.get_plans(&PageRequest {
+            page: Some(0),
+            item_per_page: Some(10),
+            ..Default::default()
+        });
+
+        assert!(matches!(result, Ok(plans) if plans.count == 0));
+        mock.assert();
+    }
+
+    #[test]
+    fn start_sessions() {
+        let mock = mockito::mock("POST", "/mgmt/session/start")
+            .match_body(Matcher::Json(json!([
+                { "planId": 1, "quantity": 2, "notifyUri": "http://notify" }
+            ])))
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!([
+                  {
+                    "planId": 1,
+                    "sessionId": 5,
+                    "quantity": 2,
+                    "status": "INITIATED",
+                    "errorMessages": [],
+                    "needInterCloud": false
+                  }
+                ])
+                .to_string(),
+            )
+            .create();
+        let adapter = ChoreographerAdapter::new(&mockito::server_url()).unwrap();
+        let result = adapter.start_sessions(&[ChoreographerRunPlanRequest {
+            plan_id: 1,
+            quantity: Some(2),
+            allow_inter_cloud: None,
+            choose_optimal_executor: None,
+            notify_uri: Some("http://notify".to_owned()),
+        }]);
+
+        assert!(matches!(
+            result.as_deref(),
+            Ok([ChoreographerRunPlanResponse {
+                session_id: Some(5),
+                status: ChoreographerSessionStatus::Initiated,
+                ..
+            }])
+        ));
+        mock.assert();
+    }
+
+    #[test]
+    fn get_session_arrowhead_error() {
+        let mock = mockito::mock("GET", "/mgmt/session/7")
+            .with_status(400)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                  "errorMessage": "string",
+                  "errorCode": 400,
+                  "exceptionType": "INVALID_PARAMETER",
+                  "origin": "string"
+                })
+                .to_string(),
+            )
+            .create();
+        let adapter = ChoreographerAdapter::new(&mockito::server_url()).unwrap();
+        let result = adapter.get_session(7);
+
+        assert!(
+            matches!(result, Err(Error::ArrowheadError(exception)) if exception.exception_type == "INVALID_PARAMETER")
+        );
+        mock.assert();
+    }
+
+    #[test]
+    fn notify_step_done() {
+        let mock = mockito::mock("POST", "/session/notifyStepDone")
+            .match_body(Matcher::Json(json!({
+                "sessionId": 1,
+                "sessionStepId": 2,
+                "status": "FATAL_ERROR",
+                "message": "string"
+            })))
+            .create();
+        let adapter = ChoreographerAdapter::new(&mockito::server_url()).unwrap();
+        let result = adapter.notify_step_done(&ChoreographerExecutedStepResult {
+            session_id: 1,
+            session_step_id: 2,
+            status: ChoreographerExecutedStepStatus::FatalError,
+            message: Some("string".to_owned()),
+            exception: None,
+        });
+
+        assert!(result.is_ok());
+        mock.assert();
+    }
+}
@@ -0,0 +1,185 @@
+use serde::Serialize;
+
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+#[derive(Serialize, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+#[derive(PartialEq, Clone)]
+pub struct CircuitBreakerConfig {
+    pub failure_threshold: u32,
+    pub cool_down: Duration,
+}
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            cool_down: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Serialize, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CircuitBreakerStatus {
+    pub state: CircuitState,
+    pub consecutive_failures: u32,
+}
+
+struct BreakerState {
+    state: CircuitState,
+    consecutive_failures: u32,
+    opened_at: Instant,
+}
+
+pub(crate) struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    state: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    pub(crate) fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(BreakerState {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                opened_at: Instant::now(),
+            }),
+        }
+    }
+
+    pub(crate) fn status(&self) -> CircuitBreakerStatus {
+        let state = self.lock_state();
+        CircuitBreakerStatus {
+            state: state.state,
+            consecutive_failures: state.consecutive_failures,
+        }
+    }
+
+    // Requests pass while the circuit is closed. Once the cool-down of an open circuit has
+    // elapsed, the circuit becomes half-open and the calling thread runs the probe, closing the
+    // circuit on success and opening it again on failure, including a panic of the probe. Other
+    // threads are rejected meanwhile.
+    pub(crate) fn allow_request<F: FnOnce() -> bool>(&self, probe: F) -> bool {
+        {
+            let mut state = self.lock_state();
+            match state.state {
+                CircuitState::Closed => return true,
+                CircuitState::HalfOpen => return false,
+                CircuitState::Open if state.opened_at.elapsed() < self.config.cool_down => {
+                    return false
+                }
+                CircuitState::Open => state.state = CircuitState::HalfOpen,
+            }
+        }
+
+        let mut outcome = ProbeOutcome {
+            circuit_breaker: self,
+            healthy: false,
+        };
+        outcome.healthy = probe();
+        outcome.healthy
+    }
+
+    pub(crate) fn report_success(&self) {
+        let mut state = self.lock_state();
+        state.state = CircuitState::Closed;
+        state.consecutive_failures = 0;
+    }
+
+    pub(crate) fn report_failure(&self) {
+        let mut state = self.lock_state();
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= self.config.failure_threshold {
+            state.state = CircuitState::Open;
+            state.opened_at = Instant::now();
+        }
+    }
+
+    fn lock_state(&self) -> MutexGuard<'_, BreakerState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+// Reports the outcome of the half-open probe when dropped, so a probe which panics leaves the
+// circuit open instead of half-open for good.
+struct ProbeOutcome<'a> {
+    circuit_breaker: &'a CircuitBreaker,
+    healthy: bool,
+}
+
+impl Drop for ProbeOutcome<'_> {
+    fn drop(&mut self) {
+        let mut state = self.circuit_breaker.lock_state();
+        if self.healthy {
+            state.state = CircuitState::Closed;
+            state.consecutive_failures = 0;
+        } else {
+            state.state = CircuitState::Open;
+            state.opened_at = Instant::now();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn circuit_breaker(cool_down: Duration) -> CircuitBreaker {
+        CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 2,
+            cool_down,
+        })
+    }
+
+    #[test]
+    fn open_after_threshold() {
+        let circuit_breaker = circuit_breaker(Duration::from_secs(60));
+
+        circuit_breaker.report_failure();
+        assert!(circuit_breaker.allow_request(|| unreachable!()));
+        circuit_breaker.report_failure();
+        assert_eq!(circuit_breaker.status().state, CircuitState::Open);
+        assert!(!circuit_breaker.allow_request(|| unreachable!()));
+    }
+
+    #[test]
+    fn probe_when_half_open() {
+        let circuit_breaker = circuit_breaker(Duration::ZERO);
+        circuit_breaker.report_failure();
+        circuit_breaker.report_failure();
+
+        assert!(!circuit_breaker.allow_request(|| false));
+        assert_eq!(circuit_breaker.status().state, CircuitState::Open);
+        assert!(circuit_breaker.allow_request(|| true));
+        assert_eq!(
+            circuit_breaker.status(),
+            CircuitBreakerStatus {
+                state: CircuitState::Closed,
+                consecutive_failures: 0
+            }
+        );
+    }
+
+    #[test]
+    fn reopen_when_probe_panics() {
+        let circuit_breaker = circuit_breaker(Duration::ZERO);
+        circuit_breaker.report_failure();
+        circuit_breaker.report_failure();
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            circuit_breaker.allow_request(|| panic!("the probe panics"))
+        }));
+        assert!(result.is_err());
+        assert_eq!(circuit_breaker.status().state, CircuitState::Open);
+        assert!(circuit_breaker.allow_request(|| true));
+        assert_eq!(circuit_breaker.status().state, CircuitState::Closed);
+    }
+}
@@ -0,0 +1,844 @@
+use crate::error::{Error, Result};
+use crate::instrumentation;
+use crate::random;
+use crate::transport::{self, Transport};
+
+use percent_encoding::percent_decode_str;
+use reqwest::blocking::{Request, Response};
+use reqwest::{Method, Url};
+
+use std::io::ErrorKind;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicU16, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+const CONFIRMABLE: u8 = 0;
+const NON_CONFIRMABLE: u8 = 1;
+const ACKNOWLEDGEMENT: u8 = 2;
+const RESET: u8 = 3;
+
+const EMPTY: u8 = 0x00;
+const GET: u8 = 0x01;
+const POST: u8 = 0x02;
+const PUT: u8 = 0x03;
+const DELETE: u8 = 0x04;
+
+const URI_PATH: u16 = 11;
+const CONTENT_FORMAT: u16 = 12;
+const URI_QUERY: u16 = 15;
+const BLOCK2: u16 = 23;
+const PROXY_URI: u16 = 35;
+
+const APPLICATION_JSON: u8 = 50;
+const PAYLOAD_MARKER: u8 = 0xff;
+const MAX_DATAGRAM_SIZE: usize = 65_535;
+const DEFAULT_PORT: u16 = 5683;
+
+#[derive(Clone)]
+pub struct CoapConfig {
+    // The forward CoAP-to-HTTP proxy receiving the requests to http(s) addresses, which are sent
+    // in the Proxy-Uri option.
+    pub proxy: Option<(String, u16)>,
+    pub ack_timeout: Duration,
+    pub max_retransmit: u32,
+}
+
+impl Default for CoapConfig {
+    fn default() -> Self {
+        Self {
+            proxy: None,
+            ack_timeout: Duration::from_secs(2),
+            max_retransmit: 4,
+        }
+    }
+}
+
+impl CoapConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_proxy(mut self, host: &str, port: u16) -> Self {
+        self.proxy = Some((host.to_owned(), port));
+        self
+    }
+
+    pub fn with_retransmission(mut self, ack_timeout: Duration, max_retransmit: u32) -> Self {
+        self.ack_timeout = ack_timeout;
+        self.max_retransmit = max_retransmit;
+        self
+    }
+}
+
+// The options of a message by their number, in any order.
+type CoapOptions = Vec<(u16, Vec<u8>)>;
+
+// A CoAP message (RFC 7252), limited to what the adapter and its tests need.
+#[derive(Clone, Debug, PartialEq)]
+struct CoapMessage {
+    message_type: u8,
+    code: u8,
+    message_id: u16,
+    token: Vec<u8>,
+    options: CoapOptions,
+    payload: Vec<u8>,
+}
+
+impl CoapMessage {
+    fn empty(message_type: u8, message_id: u16) -> Self {
+        Self {
+            message_type,
+            code: EMPTY,
+            message_id,
+            token: Vec::new(),
+            options: Vec::new(),
+            payload: Vec::new(),
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = vec![
+            0x40 | self.message_type << 4 | self.token.len() as u8,
+            self.code,
+        ];
+        bytes.extend_from_slice(&self.message_id.to_be_bytes());
+        bytes.extend_from_slice(&self.token);
+
+        let mut options: Vec<&(u16, Vec<u8>)> = self.options.iter().collect();
+        options.sort_by_key(|(number, _)| *number);
+        let mut previous = 0;
+        for (number, value) in options {
+            let (delta, delta_extension) = Self::option_nibble(number - previous);
+            let (length, length_extension) = Self::option_nibble(value.len() as u16);
+            bytes.push(delta << 4 | length);
+            bytes.extend(delta_extension);
+            bytes.extend(length_extension);
+            bytes.extend_from_slice(value);
+            previous = *number;
+        }
+
+        if !self.payload.is_empty() {
+            bytes.push(PAYLOAD_MARKER);
+            bytes.extend_from_slice(&self.payload);
+        }
+        bytes
+    }
+
+    fn option_nibble(value: u16) -> (u8, Vec<u8>) {
+        match value {
+            0..=12 => (value as u8, Vec::new()),
+            13..=268 => (13, vec![(value - 13) as u8]),
+            _ => (14, (value - 269).to_be_bytes().to_vec()),
+        }
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        let invalid =
+            |reason: &str| Error::TransportError(format!("Invalid CoAP message: {}", reason));
+        if bytes.len() < 4 || bytes[0] >> 6 != 1 {
+            return Err(invalid("unsupported header"));
+        }
+        let token_length = (bytes[0] & 0x0f) as usize;
+        if token_length > 8 || bytes.len() < 4 + token_length {
+            return Err(invalid("invalid token length"));
+        }
+        let mut message = Self {
+            message_type: bytes[0] >> 4 & 0x03,
+            code: bytes[1],
+            message_id: u16::from_be_bytes([bytes[2], bytes[3]]),
+            token: bytes[4..4 + token_length].to_vec(),
+            options: Vec::new(),
+            payload: Vec::new(),
+        };
+
+        let mut rest = &bytes[4 + token_length..];
+        let mut number = 0u16;
+        while let Some((&first, tail)) = rest.split_first() {
+            if first == PAYLOAD_MARKER {
+                if tail.is_empty() {
+                    return Err(invalid("empty payload after the payload marker"));
+                }
+                message.payload = tail.to_vec();
+                break;
+            }
+            rest = tail;
+            let delta = Self::read_option_nibble(first >> 4, &mut rest)
+                .ok_or_else(|| invalid("invalid option delta"))?;
+            let length = Self::read_option_nibble(first & 0x0f, &mut rest)
+                .ok_or_else(|| invalid("invalid option length"))? as usize;
+            if rest.len() < length {
+                return Err(invalid("truncated option"));
+            }
+            number = number
+                .checked_add(delta)
+                .ok_or_else(|| invalid("invalid option delta"))?;
+            message.options.push((number, rest[..length].to_vec()));
+            rest = &rest[length..];
+        }
+        Ok(message)
+    }
+
+    fn read_option_nibble(nibble: u8, rest: &mut &[u8]) -> Option<u16> {
+        let (value, consumed) = match nibble {
+            0..=12 => (nibble as u16, 0),
+            13 => (*rest.first()? as u16 + 13, 1),
+            14 => (
+                u16::from_be_bytes([*rest.first()?, *rest.get(1)?]).checked_add(269)?,
+                2,
+            ),
+            _ => return None,
+        };
+        *rest = &rest[consumed..];
+        Some(value)
+    }
+
+    fn option(&self, number: u16) -> Option<&[u8]> {
+        self.options
+            .iter()
+            .find(|(n, _)| *n == number)
+            .map(|(_, value)| value.as_slice())
+    }
+
+    // The block number, the more flag and the size exponent of a Block2 option (RFC 7959).
+    fn block2(&self) -> Option<(u32, bool, u8)> {
+        let value = self
+            .option(BLOCK2)?
+            .iter()
+            .fold(0u32, |value, byte| value << 8 | *byte as u32);
+        Some((value >> 4, value & 0x08 != 0, (value & 0x07) as u8))
+    }
+
+    // The response code as the equivalent HTTP status. The success codes have no HTTP
+    // equivalent but 2.01 (Created), the others are read as their digits, e.g. 4.04 as 404.
+    fn http_status(&self) -> u16 {
+        match (self.code >> 5, self.code & 0x1f) {
+            (2, 1) => 201,
+            (2, _) => 200,
+            (class, detail) => class as u16 * 100 + detail as u16,
+        }
+    }
+}
+
+fn uint_option(value: u32) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let skip = bytes.iter().take_while(|byte| **byte == 0).count();
+    bytes[skip..].to_vec()
+}
+
+fn block2_option(number: u32, size_exponent: u8) -> Vec<u8> {
+    uint_option(number << 4 | size_exponent as u32)
+}
+
+// Timeouts too long for an Instant are cut to a deadline which is never reached in practice.
+fn deadline_after(timeout: Duration) -> Instant {
+    let now = Instant::now();
+    now.checked_add(timeout)
+        .unwrap_or_else(|| now + Duration::from_secs(100 * 365 * 24 * 60 * 60))
+}
+
+// Carries the requests to the core systems over CoAP, for constrained consumers. Requests to
+// coap:// addresses are sent to the host of the address with its path and query as Uri-Path and
+// Uri-Query options; requests to http(s) addresses are sent to the forward CoAP-to-HTTP proxy
+// of the configuration in the Proxy-Uri option. Requests are confirmable messages with JSON
+// payloads, retransmitted with exponential back-off, and large responses are reassembled from
+// their blocks. Every request is sent from its own socket.
+pub struct CoapTransport {
+    config: CoapConfig,
+    message_id: AtomicU16,
+    token: AtomicU64,
+}
+
+impl CoapTransport {
+    pub fn new(config: CoapConfig) -> Self {
+        let seed = random::random_u64();
+        Self {
+            config,
+            message_id: AtomicU16::new(seed as u16),
+            token: AtomicU64::new(seed),
+        }
+    }
+
+    // The server receiving the request and the options locating the endpoint on it.
+    fn locate(&self, url: &Url) -> Result<(SocketAddr, CoapOptions)> {
+        let (host, port, options) = match url.scheme() {
+            "coap" => {
+                let host = url.host_str().ok_or_else(|| {
+                    Error::ConfigError(format!("The CoAP address '{}' has no host", url))
+                })?;
+                let mut options = Vec::new();
+                for segment in url.path_segments().into_iter().flatten() {
+                    if !segment.is_empty() {
+                        let segment = percent_decode_str(segment).collect::<Vec<_>>();
+                        options.push((URI_PATH, segment));
+                    }
+                }
+                options
+                    .extend(url.query_pairs().map(|(key, value)| {
+                        (URI_QUERY, format!("{}={}", key, value).into_bytes())
+                    }));
+                (host, url.port().unwrap_or(DEFAULT_PORT), options)
+            }
+            "http" | "https" => {
+                let (host, port) = self.config.proxy.as_ref().ok_or_else(|| {
+                    Error::ConfigError(format!(
+                        "The address '{}' needs a CoAP-to-HTTP proxy",
+                        instrumentation::redact_url(url)
+                    ))
+                })?;
+                let options = vec![(PROXY_URI, url.as_str().as_bytes().to_vec())];
+                (host.as_str(), *port, options)
+            }
+            scheme => {
+                return Err(Error::ConfigError(format!(
+                    "The '{}' scheme is not supported by the CoAP transport",
+                    scheme
+                )))
+            }
+        };
+        let server = (host, port)
+            .to_socket_addrs()
+            .ok()
+            .and_then(|mut addresses| addresses.next())
+            .ok_or_else(|| {
+                Error::TransportError(format!(
+                    "Could not resolve the CoAP server '{}:{}'",
+                    host, port
+                ))
+            })?;
+        Ok((server, options))
+    }
+
+    // Sends a confirmable request until it is acknowledged, and waits for its response, which is
+    // either piggybacked on the acknowledgement or sent separately.
+    fn exchange(
+        &self,
+        socket: &UdpSocket,
+        request: &CoapMessage,
+        location: &str,
+    ) -> Result<CoapMessage> {
+        let io_error = |e: std::io::Error| {
+            Error::TransportError(format!("CoAP request to {}: {}", location, e))
+        };
+        let encoded = request.encode();
+        let mut timeout = self.config.ack_timeout;
+        for _ in 0..=self.config.max_retransmit {
+            socket.send(&encoded).map_err(io_error)?;
+            let mut deadline = deadline_after(timeout);
+            let mut acknowledged = false;
+            while let Some(message) = Self::receive(socket, deadline).map_err(io_error)? {
+                let same_exchange = message.message_id == request.message_id;
+                match message.message_type {
+                    RESET if same_exchange => {
+                        return Err(Error::TransportError(format!(
+                            "CoAP request to {} was reset",
+                            location
+                        )))
+                    }
+                    ACKNOWLEDGEMENT if same_exchange && message.code == EMPTY => {
+                        acknowledged = true;
+                        deadline = deadline_after(self.max_transmit_wait());
+                    }
+                    ACKNOWLEDGEMENT if same_exchange && message.token == request.token => {
+                        return Ok(message)
+                    }
+                    CONFIRMABLE | NON_CONFIRMABLE
+                        if message.token == request.token && message.code != EMPTY =>
+                    {
+                        if message.message_type == CONFIRMABLE {
+                            let ack = CoapMessage::empty(ACKNOWLEDGEMENT, message.message_id);
+                            socket.send(&ack.encode()).map_err(io_error)?;
+                        }
+                        return Ok(message);
+                    }
+                    _ => {}
+                }
+            }
+            if acknowledged {
+                return Err(Error::TransportError(format!(
+                    "No response from {} after the acknowledgement",
+                    location
+                )));
+            }
+            timeout = timeout.saturating_mul(2);
+        }
+        Err(Error::TransportError(format!(
+            "No response from {} after {} retransmissions",
+            location, self.config.max_retransmit
+        )))
+    }
+
+    fn receive(socket: &UdpSocket, deadline: Instant) -> std::io::Result<Option<CoapMessage>> {
+        let mut buffer = vec![0u8; MAX_DATAGRAM_SIZE];
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(None);
+            }
+            socket.set_read_timeout(Some(remaining))?;
+            match socket.recv(&mut buffer) {
+                // Datagrams which are not CoAP messages are ignored.
+                Ok(length) => {
+                    if let Ok(message) = CoapMessage::decode(&buffer[..length]) {
+                        return Ok(Some(message));
+                    }
+                }
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    return Ok(None)
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    // MAX_TRANSMIT_WAIT of RFC 7252, the longest a separate response is waited for. It saturates
+    // instead of overflowing with large retransmission settings.
+    fn max_transmit_wait(&self) -> Duration {
+        let factor = self
+            .config
+            .max_retransmit
+            .checked_add(1)
+            .and_then(|exponent| 2u32.checked_pow(exponent))
+            .map_or(u32::MAX, |power| power - 1);
+        self.config.ack_timeout.saturating_mul(factor)
+    }
+}
+
+impl Transport for CoapTransport {
+    // The response has the status of the equivalent HTTP response, e.g. 2.05 (Content) is 200 and
+    // 4.04 (Not Found) is 404, and the payload of every block.
+    fn execute(&self, request: Request) -> Result<Response> {
+        let code = match *request.method() {
+            Method::GET => GET,
+            Method::POST => POST,
+            Method::PUT => PUT,
+            Method::DELETE => DELETE,
+            ref method => {
+                return Err(Error::TransportError(format!(
+                    "The {} method is not supported by CoAP",
+                    method
+                )))
+            }
+        };
+        let location = instrumentation::redact_url(request.url());
+        let (server, mut options) = self.locate(request.url())?;
+        let payload = transport::request_body(&request)?.to_vec();
+        if !payload.is_empty() {
+            options.push((CONTENT_FORMAT, vec![APPLICATION_JSON]));
+        }
+
+        let local = if server.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+        let socket = UdpSocket::bind(local)
+            .and_then(|socket| socket.connect(server).map(|_| socket))
+            .map_err(|e| Error::TransportError(format!("Could not open the CoAP socket: {}", e)))?;
+        let mut request = CoapMessage {
+            message_type: CONFIRMABLE,
+            code,
+            message_id: self.message_id.fetch_add(1, Ordering::Relaxed),
+            token: self
+                .token
+                .fetch_add(1, Ordering::Relaxed)
+                .to_be_bytes()
+                .to_vec(),
+            options,
+            payload,
+        };
+        let mut body = Vec::new();
+        loop {
+            let response = self.exchange(&socket, &request, &location)?;
+            body.extend_from_slice(&response.payload);
+            match response.block2() {
+                Some((number, true, size_exponent)) if response.code >> 5 == 2 => {
+                    request.message_id = self.message_id.fetch_add(1, Ordering::Relaxed);
+                    request.options.retain(|(n, _)| *n != BLOCK2);
+                    request
+                        .options
+                        .push((BLOCK2, block2_option(number + 1, size_exponent)));
+                    request.payload.clear();
+                }
+                _ => {
+                    let content_type = match response.option(CONTENT_FORMAT) {
+                        Some([APPLICATION_JSON]) => "application/json",
+                        _ => "text/plain",
+                    };
+                    return transport::response(response.http_status(), content_type, body);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dtos::{
+        ArrowheadSystem, InterfaceEntry, NoEntryTag, OrchestrationFlagKey, RegisterServiceInput,
+        RequestOrchestrationInput, ServiceDefinitionEntry, ServiceQueryForm, ServiceRequirements,
+    };
+    use crate::mock_core::MockCore;
+    use crate::ArrowheadSystemAdapter;
+
+    use std::collections::HashMap;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[derive(Clone, Copy, Default)]
+    struct Behavior {
+        // Ignores the first transmission of every request.
+        drop_first_transmission: bool,
+        // Acknowledges the requests first and sends their responses separately.
+        separate_responses: bool,
+    }
+
+    const BLOCK_SIZE: usize = 256;
+
+    // A stand-in for a CoAP-to-HTTP proxy in front of the mock core. Requests with Uri-Path
+    // options are forwarded to the mock core, requests with a Proxy-Uri to the given URL. Returns
+    // the port of the server and the number of separate responses acknowledged by the client.
+    fn start_server(core: &MockCore, behavior: Behavior) -> (u16, Arc<AtomicUsize>) {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = socket.local_addr().unwrap().port();
+        let base_url = Url::parse(core.base_url()).unwrap();
+        let acknowledged = Arc::new(AtomicUsize::new(0));
+        let counter = acknowledged.clone();
+        thread::spawn(move || {
+            let mut buffer = vec![0u8; MAX_DATAGRAM_SIZE];
+            let mut seen = Vec::new();
+            let mut bodies: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
+            let mut next_message_id = 0u16;
+            while let Ok((length, client)) = socket.recv_from(&mut buffer) {
+                let request = CoapMessage::decode(&buffer[..length]).unwrap();
+                if request.message_type == ACKNOWLEDGEMENT {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                    continue;
+                }
+                if behavior.drop_first_transmission && !seen.contains(&request.message_id) {
+                    seen.push(request.message_id);
+                    continue;
+                }
+
+                let (code, body) = match request.block2() {
+                    Some((number, _, _)) if number > 0 => (0x45, bodies[&request.token].clone()),
+                    _ => forward(&base_url, &request),
+                };
+                let mut response = CoapMessage {
+                    message_type: ACKNOWLEDGEMENT,
+                    code,
+                    message_id: request.message_id,
+                    token: request.token.clone(),
+                    options: Vec::new(),
+                    payload: body.clone(),
+                };
+                if body.len() > BLOCK_SIZE {
+                    let number = request.block2().map_or(0, |(number, _, _)| number) as usize;
+                    let end = body.len().min((number + 1) * BLOCK_SIZE);
+                    response.payload = body[number * BLOCK_SIZE..end].to_vec();
+                    let more = if end < body.len() { 0x08 } else { 0 };
+                    // Size exponent 4 stands for blocks of 256 bytes.
+                    response.options = vec![(BLOCK2, uint_option((number as u32) << 4 | more | 4))];
+                    bodies.insert(request.token.clone(), body);
+                }
+                if behavior.separate_responses {
+                    let ack = CoapMessage::empty(ACKNOWLEDGEMENT, request.message_id);
+                    socket.send_to(&ack.encode(), client).unwrap();
+                    response.message_type = CONFIRMABLE;
+                    response.message_id = next_message_id;
+                    next_message_id += 1;
+                }
+                socket.send_to(&response.encode(), client).unwrap();
+            }
+        });
+        (port, acknowledged)
+    }
+
+    fn forward(base_url: &Url, request: &CoapMessage) -> (u8, Vec<u8>) {
+        let url = match request.option(PROXY_URI) {
+            Some(proxy_uri) => Url::parse(std::str::from_utf8(proxy_uri).unwrap()).unwrap(),
+            None => {
+                let option_values = |number: u16| {
+                    request
+                        .options
+                        .iter()
+                        .filter(move |(n, _)| *n == number)
+                        .map(|(_, value)| String::from_utf8(value.clone()).unwrap())
+                };
+                let mut url = base_url
+                    .join(&option_values(URI_PATH).collect::<Vec<_>>().join("/"))
+                    .unwrap();
+                let query = option_values(URI_QUERY).collect::<Vec<_>>().join("&");
+                if !query.is_empty() {
+                    url.set_query(Some(&query));
+                }
+                url
+            }
+        };
+        let method = match request.code {
+            GET => reqwest::Method::GET,
+            POST => reqwest::Method::POST,
+            DELETE => reqwest::Method::DELETE,
+            code => panic!("unexpected method {}", code),
+        };
+        let mut builder = reqwest::blocking::Client::new().request(method, url);
+        if !request.payload.is_empty() {
+            builder = builder
+                .header("Content-Type", "application/json")
+                .body(request.payload.clone());
+        }
+        let response = builder.send().unwrap();
+        let status = response.status().as_u16();
+        let code = match status {
+            200 => 0x45,
+            201 => 0x41,
+            status => (((status / 100) << 5) | (status % 100)) as u8,
+        };
+        (code, response.bytes().unwrap().to_vec())
+    }
+
+    fn system(system_name: &str, port: u32) -> ArrowheadSystem<NoEntryTag> {
+        ArrowheadSystem {
+            entry_tag: NoEntryTag {},
+            system_name: system_name.to_owned(),
+            address: "127.0.0.1".to_owned(),
+            port,
+            authentication_info: None,
+            metadata: None,
+        }
+    }
+
+    fn requirements(service_definition: &str) -> ServiceRequirements {
+        ServiceRequirements {
+            service_definition_requirement: service_definition.to_owned(),
+            interface_requirements: None,
+            security_requirements: None,
+            metadata_requirements: None,
+            version_requirement: None,
+            max_version_requirement: None,
+            min_version_requirement: None,
+        }
+    }
+
+    fn register_input(service_definition: &str) -> RegisterServiceInput {
+        RegisterServiceInput {
+            service_definition: ServiceDefinitionEntry::Value(service_definition.to_owned()),
+            service_uri: format!("/{}", service_definition),
+            end_of_validity: None,
+            secure: None,
+            metadata: None,
+            version: Some(1),
+            interfaces: vec![InterfaceEntry::Value("HTTP-INSECURE-JSON".to_owned())],
+        }
+    }
+
+    fn config() -> CoapConfig {
+        CoapConfig::new().with_retransmission(Duration::from_millis(100), 3)
+    }
+
+    // An adapter reaching the core systems on the CoAP server with the given port.
+    fn adapter(
+        port: u16,
+        config: CoapConfig,
+        client_system: ArrowheadSystem<NoEntryTag>,
+    ) -> ArrowheadSystemAdapter {
+        let address = |system: &str| format!("coap://127.0.0.1:{}/{}/", port, system);
+        ArrowheadSystemAdapter::new(
+            &address("serviceregistry"),
+            &address("authorization"),
+            &address("orchestrator"),
+            client_system,
+        )
+        .unwrap()
+        .with_transport(CoapTransport::new(config))
+    }
+
+    #[test]
+    fn message_encoding() {
+        let message = CoapMessage {
+            message_type: CONFIRMABLE,
+            code: POST,
+            message_id: 0x1234,
+            token: vec![1, 2, 3, 4],
+            options: vec![
+                (URI_QUERY, b"port=8080".to_vec()),
+                (URI_PATH, b"serviceregistry".to_vec()),
+                (URI_PATH, b"unregister".to_vec()),
+                (PROXY_URI, vec![b'x'; 300]),
+                (CONTENT_FORMAT, vec![APPLICATION_JSON]),
+            ],
+            payload: b"{}".to_vec(),
+        };
+        let encoded = message.encode();
+        assert_eq!(&encoded[..4], &[0x44, POST, 0x12, 0x34]);
+
+        let decoded = CoapMessage::decode(&encoded).unwrap();
+        assert_eq!(decoded.options[0], (URI_PATH, b"serviceregistry".to_vec()));
+        assert_eq!(decoded.options[1], (URI_PATH, b"unregister".to_vec()));
+        assert_eq!(decoded.option(PROXY_URI).map(<[u8]>::len), Some(300));
+        assert_eq!(decoded.payload, b"{}");
+        assert!(matches!(
+            CoapMessage::decode(&encoded[..encoded.len() - 10]),
+            Err(Error::TransportError(_))
+        ));
+        assert_eq!(uint_option(0), Vec::<u8>::new());
+        assert_eq!(block2_option(20, 6), vec![0x01, 0x46]);
+    }
+
+    #[test]
+    fn core_services_over_coap() {
+        let core = MockCore::start().unwrap();
+        core.set_public_key("coap-key");
+        let (port, _) = start_server(&core, Behavior::default());
+        let provider = adapter(port, config(), system("provider", 8080));
+        let consumer = adapter(port, config(), system("consumer", 0));
+
+        assert!(consumer.echo_service_registry().is_ok());
+        assert!(consumer.echo_authorization().is_ok());
+        assert!(consumer.echo_orchestrator().is_ok());
+        assert!(matches!(consumer.get_public_key(), Ok(key) if key == "coap-key"));
+
+        let registered = provider
+            .register_service(register_input("temperature"))
+            .unwrap();
+        assert!(registered.provider_system.system_name == "provider");
+        assert!(matches!(
+            provider.register_service(register_input("temperature")),
+            Err(Error::ArrowheadError(e)) if e.exception_type == "INVALID_PARAMETER"
+        ));
+        let form = |service_definition: &str| ServiceQueryForm {
+            service_requirements: requirements(service_definition),
+            ping_providers: None,
+        };
+        assert!(matches!(
+            consumer.query_service(&form("temperature")),
+            Ok(list) if list.service_query_data.len() == 1
+        ));
+        let results = consumer
+            .query_services(&[form("temperature"), form("humidity")])
+            .unwrap();
+        assert!(results[0].1.service_query_data.len() == 1);
+        assert!(results[1].1.service_query_data.is_empty());
+
+        // The orchestration response is larger than a block.
+        let response = consumer
+            .request_orchestration(RequestOrchestrationInput {
+                requested_service: requirements("temperature"),
+                preferred_providers: None,
+                orchestration_flags: Some(HashMap::from([(
+                    OrchestrationFlagKey::OverrideStore,
+                    true,
+                )])),
+            })
+            .unwrap();
+        assert!(
+            response.response.len() == 1 && response.response[0].provider.system_name == "provider"
+        );
+
+        assert!(provider.unregister_service_entry(&registered).is_ok());
+        assert!(core.services().is_empty());
+        assert!(matches!(
+            provider.unregister_service("temperature"),
+            Err(Error::ArrowheadError(_))
+        ));
+        let registered = provider
+            .register_service(register_input("humidity"))
+            .unwrap();
+        assert!(provider
+            .delete_service_by_id(registered.entry_tag.id)
+            .is_ok());
+        assert!(core.services().is_empty());
+    }
+
+    #[test]
+    fn forward_proxy() {
+        let core = MockCore::start().unwrap();
+        let (port, _) = start_server(&core, Behavior::default());
+        let provider = ArrowheadSystemAdapter::new(
+            &core.service_registry_address(),
+            &core.authorization_address(),
+            &core.orchestrator_address(),
+            system("provider", 8080),
+        )
+        .unwrap()
+        .with_transport(CoapTransport::new(config().with_proxy("127.0.0.1", port)));
+
+        assert!(provider.echo_authorization().is_ok());
+        provider
+            .register_service(register_input("temperature"))
+            .unwrap();
+        assert!(provider.unregister_service("temperature").is_ok());
+        assert!(core.services().is_empty());
+
+        let without_proxy = ArrowheadSystemAdapter::new(
+            &core.service_registry_address(),
+            &core.authorization_address(),
+            &core.orchestrator_address(),
+            system("provider", 8080),
+        )
+        .unwrap()
+        .with_transport(CoapTransport::new(config()));
+        assert!(matches!(
+            without_proxy.echo_authorization(),
+            Err(Error::ConfigError(_))
+        ));
+    }
+
+    #[test]
+    fn retransmission_and_separate_responses() {
+        let core = MockCore::start().unwrap();
+        let (port, acknowledged) = start_server(
+            &core,
+            Behavior {
+                drop_first_transmission: true,
+                separate_responses: true,
+            },
+        );
+        let provider = adapter(port, config(), system("provider", 8080));
+
+        assert!(provider.echo_service_registry().is_ok());
+        assert!(provider
+            .register_service(register_input("temperature"))
+            .is_ok());
+        // The registered entry is sent in two blocks.
+        assert_eq!(acknowledged.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn no_response() {
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = silent.local_addr().unwrap().port();
+        let consumer = adapter(
+            port,
+            CoapConfig::new().with_retransmission(Duration::from_millis(20), 2),
+            system("consumer", 0),
+        );
+
+        assert!(matches!(
+            consumer.echo_orchestrator(),
+            Err(Error::TransportError(message)) if message.contains("after 2 retransmissions")
+        ));
+    }
+
+    #[test]
+    fn max_transmit_wait_saturates() {
+        let transport = |ack_timeout: Duration, max_retransmit: u32| {
+            CoapTransport::new(CoapConfig::new().with_retransmission(ack_timeout, max_retransmit))
+        };
+
+        assert_eq!(
+            transport(Duration::from_secs(2), 4).max_transmit_wait(),
+            Duration::from_secs(62)
+        );
+        assert_eq!(
+            transport(Duration::from_secs(2), 40).max_transmit_wait(),
+            Duration::from_secs(2) * u32::MAX
+        );
+        assert_eq!(
+            transport(Duration::MAX, u32::MAX).max_transmit_wait(),
+            Duration::MAX
+        );
+        assert!(deadline_after(Duration::MAX) > Instant::now());
+    }
+}
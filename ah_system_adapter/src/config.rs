@@ -0,0 +1,849 @@
+// Loads the configuration of the adapter from the sources used by the Java application systems
+// of Arrowhead: application.properties files and environment variables, or TOML and YAML files
+// with the same keys. Every source is flattened to dotted keys (`[server.ssl]` `enabled = true`
+// in TOML is `server.ssl.enabled`, the first item of `list` is `list[0]`), and the sources added
+// later override the earlier ones. TOML and YAML are parsed with the `toml` and `yaml` features.
+
+use crate::dtos::{ArrowheadSystem, NoEntryTag};
+use crate::endpoints::CoreSystemEndpoints;
+use crate::error::{Error, Result};
+use crate::ArrowheadSystemAdapter;
+
+#[cfg(feature = "ssl")]
+use openssl::pkcs12::Pkcs12;
+use reqwest::blocking::Client;
+#[cfg(feature = "ssl")]
+use reqwest::{Certificate, Identity};
+#[cfg(feature = "yaml")]
+use serde::Deserialize;
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+const SERVICE_REGISTRY_ADDRESS: &str = "sr_address";
+const SERVICE_REGISTRY_PORT: &str = "sr_port";
+const AUTHORIZATION_ADDRESS: &str = "authorization_address";
+const AUTHORIZATION_PORT: &str = "authorization_port";
+const ORCHESTRATOR_ADDRESS: &str = "orchestrator_address";
+const ORCHESTRATOR_PORT: &str = "orchestrator_port";
+const SSL_ENABLED: &str = "server.ssl.enabled";
+const SSL_KEY_STORE: &str = "server.ssl.key-store";
+const SSL_KEY_STORE_TYPE: &str = "server.ssl.key-store-type";
+const SSL_KEY_STORE_PASSWORD: &str = "server.ssl.key-store-password";
+const SSL_KEY_PASSWORD: &str = "server.ssl.key-password";
+const SSL_TRUST_STORE: &str = "server.ssl.trust-store";
+const SSL_TRUST_STORE_TYPE: &str = "server.ssl.trust-store-type";
+const SSL_TRUST_STORE_PASSWORD: &str = "server.ssl.trust-store-password";
+const SYSTEM_NAME: &str = "application_system_name";
+const SYSTEM_ADDRESS: &str = "server.address";
+const SYSTEM_PORT: &str = "server.port";
+
+const KEYS: &[&str] = &[
+    SERVICE_REGISTRY_ADDRESS,
+    SERVICE_REGISTRY_PORT,
+    AUTHORIZATION_ADDRESS,
+    AUTHORIZATION_PORT,
+    ORCHESTRATOR_ADDRESS,
+    ORCHESTRATOR_PORT,
+    SSL_ENABLED,
+    SSL_KEY_STORE,
+    SSL_KEY_STORE_TYPE,
+    SSL_KEY_STORE_PASSWORD,
+    SSL_KEY_PASSWORD,
+    SSL_TRUST_STORE,
+    SSL_TRUST_STORE_TYPE,
+    SSL_TRUST_STORE_PASSWORD,
+    SYSTEM_NAME,
+    SYSTEM_ADDRESS,
+    SYSTEM_PORT,
+];
+
+// The default ports of the core systems in Arrowhead 4.x
+const DEFAULT_SERVICE_REGISTRY_PORT: u32 = 8443;
+const DEFAULT_AUTHORIZATION_PORT: u32 = 8445;
+const DEFAULT_ORCHESTRATOR_PORT: u32 = 8441;
+
+#[derive(Default, Clone, Debug)]
+pub struct AdapterConfig {
+    properties: HashMap<String, String>,
+}
+
+impl AdapterConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Loads the file, then the environment variables overriding it.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::new().with_file(path)?.with_environment())
+    }
+
+    // The format is selected by the extension: .properties, .toml, .yaml or .yml
+    pub fn with_file(self, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)
+            .map_err(|e| Error::ConfigError(format!("{}: {}", path.display(), e)))?;
+        let properties = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("properties") => parse_properties(&source),
+            #[cfg(feature = "toml")]
+            Some("toml") => parse_toml(&source),
+            #[cfg(not(feature = "toml"))]
+            Some("toml") => Err("TOML files are read with the `toml` feature".to_owned()),
+            #[cfg(feature = "yaml")]
+            Some("yaml") | Some("yml") => parse_yaml(&source),
+            #[cfg(not(feature = "yaml"))]
+            Some("yaml") | Some("yml") => {
+                Err("YAML files are read with the `yaml` feature".to_owned())
+            }
+            _ => {
+                Err("unknown format, the extension must be .properties, .toml or .yaml".to_owned())
+            }
+        }
+        .map_err(|message| Error::ConfigError(format!("{}: {}", path.display(), message)))?;
+        Ok(self.with_entries(properties))
+    }
+
+    pub fn with_properties(self, source: &str) -> Result<Self> {
+        Ok(self.with_entries(parse_properties(source).map_err(Error::ConfigError)?))
+    }
+
+    #[cfg(feature = "toml")]
+    pub fn with_toml(self, source: &str) -> Result<Self> {
+        Ok(self.with_entries(parse_toml(source).map_err(Error::ConfigError)?))
+    }
+
+    #[cfg(feature = "yaml")]
+    pub fn with_yaml(self, source: &str) -> Result<Self> {
+        Ok(self.with_entries(parse_yaml(source).map_err(Error::ConfigError)?))
+    }
+
+    pub fn with_environment(self) -> Self {
+        self.with_variables(env::vars())
+    }
+
+    // Reads the keys from variables named like in Spring Boot: upper case, with `_` in place of
+    // `.` and `-` (e.g. SERVER_SSL_ENABLED for server.ssl.enabled).
+    pub fn with_variables(self, variables: impl IntoIterator<Item = (String, String)>) -> Self {
+        let variables: HashMap<String, String> = variables.into_iter().collect();
+        let properties: Vec<(String, String)> = KEYS
+            .iter()
+            .filter_map(|key| {
+                let name = key.to_uppercase().replace(['.', '-'], "_");
+                variables
+                    .get(&name)
+                    .map(|value| ((*key).to_owned(), value.clone()))
+            })
+            .collect();
+        self.with_entries(properties)
+    }
+
+    pub fn with_property(mut self, key: &str, value: &str) -> Self {
+        self.properties.insert(key.to_owned(), value.to_owned());
+        self
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.properties.get(key).map(String::as_str)
+    }
+
+    pub fn client_system(&self) -> Result<ArrowheadSystem<NoEntryTag>> {
+        Ok(ArrowheadSystem {
+            entry_tag: NoEntryTag {},
+            system_name: self.required(SYSTEM_NAME)?.to_owned(),
+            address: self.required(SYSTEM_ADDRESS)?.to_owned(),
+            port: self.port(SYSTEM_PORT, None)?,
+            authentication_info: None,
+            metadata: None,
+        })
+    }
+
+    // Builds the adapter. The Authorization and the Orchestrator default to the address of the
+    // Service Registry, and the ports to the default ports of the core systems. Several
+    // instances of a core system are given as comma separated addresses. With SSL enabled, the
+    // core systems are called over HTTPS with the client certificate of the key store, trusting
+    // the certificates of the trust store.
+    pub fn build(&self) -> Result<ArrowheadSystemAdapter> {
+        let client = match self.get(SSL_ENABLED).map(str::trim) {
+            None | Some("false") => None,
+            Some("true") => Some(self.ssl_client()?),
+            Some(value) => {
+                return Err(Error::ConfigError(format!(
+                    "The value '{}' of '{}' is not a boolean",
+                    value, SSL_ENABLED
+                )))
+            }
+        };
+        let scheme = if client.is_some() { "https" } else { "http" };
+        let service_registry_address = self.required(SERVICE_REGISTRY_ADDRESS)?;
+        let endpoints =
+            |address_key, port_key, default_port, path| -> Result<CoreSystemEndpoints> {
+                let port = self.port(port_key, Some(default_port))?;
+                let addresses: Vec<String> = self
+                    .get(address_key)
+                    .unwrap_or(service_registry_address)
+                    .split(',')
+                    .map(|address| format!("{}://{}:{}/{}/", scheme, address.trim(), port, path))
+                    .collect();
+                CoreSystemEndpoints::new(&addresses.iter().map(String::as_str).collect::<Vec<_>>())
+            };
+
+        let adapter = ArrowheadSystemAdapter::with_endpoints(
+            endpoints(
+                SERVICE_REGISTRY_ADDRESS,
+                SERVICE_REGISTRY_PORT,
+                DEFAULT_SERVICE_REGISTRY_PORT,
+                "serviceregistry",
+            )?,
+            endpoints(
+                AUTHORIZATION_ADDRESS,
+                AUTHORIZATION_PORT,
+                DEFAULT_AUTHORIZATION_PORT,
+                "authorization",
+            )?,
+            endpoints(
+                ORCHESTRATOR_ADDRESS,
+                ORCHESTRATOR_PORT,
+                DEFAULT_ORCHESTRATOR_PORT,
+                "orchestrator",
+            )?,
+            self.client_system()?,
+        );
+        Ok(match client {
+            Some(client) => adapter.with_client(client),
+            None => adapter,
+        })
+    }
+
+    // The key store holds the certificate and the private key of the client system, the trust
+    // store the certificates of the cloud, both in PKCS#12 format like in the Java systems.
+    #[cfg(feature = "ssl")]
+    fn ssl_client(&self) -> Result<Client> {
+        let key_store_password = self.required(SSL_KEY_STORE_PASSWORD)?;
+        if self
+            .get(SSL_KEY_PASSWORD)
+            .is_some_and(|key_password| key_password.trim() != key_store_password)
+        {
+            return Err(Error::ConfigError(format!(
+                "The value of '{}' must be the same as '{}' in a PKCS#12 key store",
+                SSL_KEY_PASSWORD, SSL_KEY_STORE_PASSWORD
+            )));
+        }
+        let (key_store_path, key_store) = self.store(SSL_KEY_STORE, SSL_KEY_STORE_TYPE)?;
+        let identity = Identity::from_pkcs12_der(&key_store, key_store_password).map_err(|e| {
+            Error::ConfigError(format!(
+                "The key store '{}' cannot be read: {}",
+                key_store_path, e
+            ))
+        })?;
+
+        let trust_store_password = self.required(SSL_TRUST_STORE_PASSWORD)?;
+        let (trust_store_path, trust_store) = self.store(SSL_TRUST_STORE, SSL_TRUST_STORE_TYPE)?;
+        let unreadable = |e: openssl::error::ErrorStack| {
+            Error::ConfigError(format!(
+                "The trust store '{}' cannot be read: {}",
+                trust_store_path, e
+            ))
+        };
+        let trust_store = Pkcs12::from_der(&trust_store)
+            .and_then(|pkcs12| pkcs12.parse2(trust_store_password))
+            .map_err(unreadable)?;
+        let trusted_certificates: Vec<_> = trust_store
+            .cert
+            .into_iter()
+            .chain(trust_store.ca.into_iter().flatten())
+            .collect();
+        if trusted_certificates.is_empty() {
+            return Err(Error::ConfigError(format!(
+                "The trust store '{}' holds no certificate",
+                trust_store_path
+            )));
+        }
+
+        let mut builder = Client::builder().identity(identity);
+        for certificate in trusted_certificates {
+            builder = builder.add_root_certificate(
+                certificate
+                    .to_der()
+                    .map_err(unreadable)
+                    .and_then(|der| Ok(Certificate::from_der(&der)?))?,
+            );
+        }
+        Ok(builder.build()?)
+    }
+
+    #[cfg(not(feature = "ssl"))]
+    fn ssl_client(&self) -> Result<Client> {
+        Err(Error::ConfigError(format!(
+            "The key store and the trust store of '{}' are read with the `ssl` feature",
+            SSL_ENABLED
+        )))
+    }
+
+    // Reads a PKCS#12 store from the file named by the key, a `file:` prefix is allowed.
+    #[cfg(feature = "ssl")]
+    fn store(&self, key: &str, type_key: &str) -> Result<(String, Vec<u8>)> {
+        if let Some(store_type) = self
+            .get(type_key)
+            .map(str::trim)
+            .filter(|store_type| !store_type.eq_ignore_ascii_case("PKCS12"))
+        {
+            return Err(Error::ConfigError(format!(
+                "The value '{}' of '{}' is not supported, only PKCS12 stores are read",
+                store_type, type_key
+            )));
+        }
+        let path = self.required(key)?;
+        let path = path.strip_prefix("file:").unwrap_or(path);
+        let store = fs::read(path)
+            .map_err(|e| Error::ConfigError(format!("The value '{}' of '{}': {}", path, key, e)))?;
+        Ok((path.to_owned(), store))
+    }
+
+    fn with_entries(mut self, entries: Vec<(String, String)>) -> Self {
+        self.properties.extend(entries);
+        self
+    }
+
+    fn required(&self, key: &str) -> Result<&str> {
+        self.get(key)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .ok_or_else(|| Error::ConfigError(format!("The value '{}' is not set", key)))
+    }
+
+    fn port(&self, key: &str, default: Option<u32>) -> Result<u32> {
+        match (self.get(key), default) {
+            (Some(port), _) => port.trim().parse().map_err(|_| {
+                Error::ConfigError(format!("The value '{}' of '{}' is not a port", port, key))
+            }),
+            (None, Some(default)) => Ok(default),
+            (None, None) => Err(Error::ConfigError(format!(
+                "The value '{}' is not set",
+                key
+            ))),
+        }
+    }
+}
+
+type ParseResult<T> = std::result::Result<T, String>;
+
+fn line_error(line: usize, message: &str) -> String {
+    format!("line {}: {}", line + 1, message)
+}
+
+// The format of java.util.Properties: `key=value`, `key: value` or `key value` lines, `#` and
+// `!` comments, lines continued by a trailing backslash, and backslash escapes.
+fn parse_properties(source: &str) -> ParseResult<Vec<(String, String)>> {
+    let mut properties = vec![];
+    let mut lines = source.lines().enumerate();
+    while let Some((number, line)) = lines.next() {
+        let mut line = line.trim_start().to_owned();
+        if line.is_empty() || line.starts_with('#') || line.starts_with('!') {
+            continue;
+        }
+        while line.chars().rev().take_while(|c| *c == '\\').count() % 2 == 1 {
+            line.pop();
+            match lines.next() {
+                Some((_, next)) => line.push_str(next.trim_start()),
+                None => break,
+            }
+        }
+
+        let mut separator = line.len();
+        let mut escaped = false;
+        for (i, c) in line.char_indices() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '=' | ':' | ' ' | '\t' | '\x0c' => {
+                    separator = i;
+                    break;
+                }
+                _ => {}
+            }
+        }
+        let (key, rest) = line.split_at(separator);
+        let rest = rest.trim_start_matches([' ', '\t', '\x0c']);
+        let value = rest
+            .strip_prefix(['=', ':'])
+            .unwrap_or(rest)
+            .trim_start_matches([' ', '\t', '\x0c']);
+        properties.push((
+            unescape_properties(key).map_err(|e| line_error(number, &e))?,
+            unescape_properties(value).map_err(|e| line_error(number, &e))?,
+        ));
+    }
+    Ok(properties)
+}
+
+fn unescape_properties(escaped: &str) -> ParseResult<String> {
+    let mut unescaped = String::new();
+    let mut chars = escaped.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('f') => unescaped.push('\x0c'),
+            Some('u') => unescaped.push(unicode_escape(&mut chars, 4)?),
+            Some(c) => unescaped.push(c),
+            None => {}
+        }
+    }
+    Ok(unescaped)
+}
+
+fn unicode_escape(chars: &mut std::str::Chars, digits: usize) -> ParseResult<char> {
+    let code: String = chars.take(digits).collect();
+    u32::from_str_radix(&code, 16)
+        .ok()
+        .filter(|_| code.len() == digits)
+        .and_then(char::from_u32)
+        .ok_or_else(|| format!("invalid unicode escape '{}'", code))
+}
+
+#[cfg(feature = "toml")]
+// TOML and YAML documents are flattened like Spring Boot flattens YAML: the keys of nested tables
+// and mappings are joined by `.`, and the items of arrays and sequences are indexed as `key[0]`.
+fn parse_toml(source: &str) -> ParseResult<Vec<(String, String)>> {
+    let table: toml::Table = source.parse().map_err(|e: toml::de::Error| e.to_string())?;
+    let mut properties = vec![];
+    flatten_toml(String::new(), toml::Value::Table(table), &mut properties);
+    Ok(properties)
+}
+
+#[cfg(feature = "toml")]
+fn flatten_toml(key: String, value: toml::Value, properties: &mut Vec<(String, String)>) {
+    match value {
+        toml::Value::Table(table) => {
+            for (name, value) in table {
+                flatten_toml(child_key(&key, &name), value, properties);
+            }
+        }
+        toml::Value::Array(items) => {
+            for (index, value) in items.into_iter().enumerate() {
+                flatten_toml(format!("{}[{}]", key, index), value, properties);
+            }
+        }
+        toml::Value::String(value) => properties.push((key, value)),
+        toml::Value::Integer(value) => properties.push((key, value.to_string())),
+        toml::Value::Float(value) => properties.push((key, value.to_string())),
+        toml::Value::Boolean(value) => properties.push((key, value.to_string())),
+        toml::Value::Datetime(value) => properties.push((key, value.to_string())),
+    }
+}
+
+#[cfg(feature = "yaml")]
+// Every document of the stream is flattened, the later documents override the earlier ones. A
+// null value leaves the key unset.
+fn parse_yaml(source: &str) -> ParseResult<Vec<(String, String)>> {
+    let mut properties = vec![];
+    for document in serde_yaml::Deserializer::from_str(source) {
+        match serde_yaml::Value::deserialize(document).map_err(|e| e.to_string())? {
+            value @ serde_yaml::Value::Mapping(_) => {
+                flatten_yaml(String::new(), value, &mut properties)?
+            }
+            serde_yaml::Value::Null => {}
+            _ => return Err("the document is not a mapping".to_owned()),
+        }
+    }
+    Ok(properties)
+}
+
+#[cfg(feature = "yaml")]
+fn flatten_yaml(
+    key: String,
+    value: serde_yaml::Value,
+    properties: &mut Vec<(String, String)>,
+) -> ParseResult<()> {
+    match value {
+        serde_yaml::Value::Mapping(mapping) => {
+            for (name, value) in mapping {
+                let name = yaml_scalar(name)
+                    .ok_or_else(|| format!("the keys of '{}' are not scalars", key))?;
+                flatten_yaml(child_key(&key, &name), value, properties)?;
+            }
+        }
+        serde_yaml::Value::Sequence(items) => {
+            for (index, value) in items.into_iter().enumerate() {
+                flatten_yaml(format!("{}[{}]", key, index), value, properties)?;
+            }
+        }
+        serde_yaml::Value::Tagged(tagged) => flatten_yaml(key, tagged.value, properties)?,
+        serde_yaml::Value::Null => {}
+        value => properties.extend(yaml_scalar(value).map(|value| (key, value))),
+    }
+    Ok(())
+}
+
+#[cfg(feature = "yaml")]
+fn yaml_scalar(value: serde_yaml::Value) -> Option<String> {
+    match value {
+        serde_yaml::Value::String(value) => Some(value),
+        serde_yaml::Value::Number(value) => Some(value.to_string()),
+        serde_yaml::Value::Bool(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(any(feature = "toml", feature = "yaml"))]
+fn child_key(parent: &str, name: &str) -> String {
+    if parent.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{}.{}", parent, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(file_name: &str) -> String {
+        format!(
+            "{}/fixtures/config/{}",
+            env!("CARGO_MANIFEST_DIR"),
+            file_name
+        )
+    }
+
+    fn addresses(endpoints: &CoreSystemEndpoints) -> Vec<&str> {
+        endpoints
+            .addresses()
+            .iter()
+            .map(|url| url.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn parse_properties_syntax() {
+        let properties = parse_properties(
+            "# comment\n\
+             ! comment\n\
+             \n\
+             a=1\n\
+             b : 2\n\
+             c 3\n\
+             \td=  4 \n\
+             e=first \\\n    second\n\
+             f\\ g=\\u00e9\\t\\\\\n\
+             h\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            properties,
+            [
+                ("a", "1"),
+                ("b", "2"),
+                ("c", "3"),
+                ("d", "4 "),
+                ("e", "first second"),
+                ("f g", "\u{e9}\t\\"),
+                ("h", ""),
+            ]
+            .map(|(key, value)| (key.to_owned(), value.to_owned()))
+        );
+        assert!(parse_properties("a=\\u00zz").is_err());
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn parse_toml_syntax() {
+        let properties = parse_toml(
+            "# comment\n\
+             a = \"x\\ty\" # comment\n\
+             b.c = 'C:\\path'\n\
+             list = [1, \"two\"]\n\
+             [server.\"ssl\"]\n\
+             enabled = true\n\
+             port = 8_443\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            properties,
+            [
+                ("a", "x\ty"),
+                ("b.c", "C:\\path"),
+                ("list[0]", "1"),
+                ("list[1]", "two"),
+                ("server.ssl.enabled", "true"),
+                ("server.ssl.port", "8443"),
+            ]
+            .map(|(key, value)| (key.to_owned(), value.to_owned()))
+        );
+        assert!(parse_toml("a = text").is_err());
+        assert!(parse_toml("a = \"unterminated").is_err());
+        assert!(parse_toml("a = 1\na = 2").is_err());
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn parse_yaml_syntax() {
+        let properties = parse_yaml(
+            "---\n\
+             # comment\n\
+             a: plain text # comment\n\
+             server:\n  \
+               ssl:\n    \
+                 enabled: false\n  \
+               port: \"8080\"\n\
+             b.c: 'it''s'\n\
+             d: ~\n\
+             list:\n  \
+               - 1\n  \
+               - name: x\n\
+             ---\n\
+             a: overridden\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            properties,
+            [
+                ("a", "plain text"),
+                ("server.ssl.enabled", "false"),
+                ("server.port", "8080"),
+                ("b.c", "it's"),
+                ("list[0]", "1"),
+                ("list[1].name", "x"),
+                ("a", "overridden"),
+            ]
+            .map(|(key, value)| (key.to_owned(), value.to_owned()))
+        );
+        assert!(parse_yaml("a: [1, 2").is_err());
+        assert!(parse_yaml("- 1\n").is_err());
+        assert!(parse_yaml("a: 1\na: 2\n").is_err());
+    }
+
+    #[cfg(all(feature = "toml", feature = "yaml"))]
+    #[test]
+    fn same_config_in_every_format() {
+        let configs: Vec<AdapterConfig> = [
+            "application.properties",
+            "application.toml",
+            "application.yaml",
+        ]
+        .iter()
+        .map(|file_name| AdapterConfig::new().with_file(fixture(file_name)).unwrap())
+        .collect();
+
+        for config in &configs {
+            assert_eq!(config.properties, configs[0].properties);
+        }
+        assert!(AdapterConfig::new()
+            .with_file(fixture("application.json"))
+            .is_err());
+    }
+
+    #[test]
+    fn build_adapter() {
+        let adapter = AdapterConfig::new()
+            .with_file(fixture("application.properties"))
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            addresses(&adapter.service_registry),
+            ["http://192.168.0.2:8443/serviceregistry/"]
+        );
+        assert_eq!(
+            addresses(&adapter.authorization),
+            ["http://192.168.0.2:8445/authorization/"]
+        );
+        assert_eq!(
+            addresses(&adapter.orchestrator),
+            [
+                "http://192.168.0.3:9441/orchestrator/",
+                "http://192.168.0.4:9441/orchestrator/"
+            ]
+        );
+        assert_eq!(adapter.client_system.system_name, "thermometer");
+        assert_eq!(adapter.client_system.address, "192.168.0.10");
+        assert_eq!(adapter.client_system.port, 8080);
+    }
+
+    #[test]
+    fn environment_overrides_file() {
+        let config = AdapterConfig::new()
+            .with_file(fixture("application.properties"))
+            .unwrap()
+            .with_variables([
+                ("SR_ADDRESS".to_owned(), "10.0.0.1".to_owned()),
+                ("SERVER_SSL_ENABLED".to_owned(), "false".to_owned()),
+                ("SERVER_PORT".to_owned(), "9090".to_owned()),
+                ("UNKNOWN_KEY".to_owned(), "value".to_owned()),
+            ]);
+        let adapter = config.build().unwrap();
+
+        assert_eq!(
+            addresses(&adapter.service_registry),
+            ["http://10.0.0.1:8443/serviceregistry/"]
+        );
+        assert_eq!(adapter.client_system.port, 9090);
+        assert_eq!(config.get("unknown.key"), None);
+        assert_eq!(config.get("UNKNOWN_KEY"), None);
+    }
+
+    #[test]
+    fn invalid_config() {
+        let config = AdapterConfig::new()
+            .with_properties("sr_address=localhost\nserver.address=localhost\nserver.port=8080")
+            .unwrap();
+
+        assert!(matches!(
+            config.build(),
+            Err(Error::ConfigError(message)) if message.contains(SYSTEM_NAME)
+        ));
+        let config = config.with_property(SYSTEM_NAME, "thermometer");
+        assert!(config.build().is_ok());
+        assert!(matches!(
+            config.clone().with_property(SYSTEM_PORT, "http").build(),
+            Err(Error::ConfigError(message)) if message.contains(SYSTEM_PORT)
+        ));
+        assert!(matches!(
+            config.clone().with_property(SSL_ENABLED, "yes").build(),
+            Err(Error::ConfigError(message)) if message.contains(SSL_ENABLED)
+        ));
+        // The key store and the trust store are required with SSL enabled.
+        assert!(matches!(
+            config.with_property(SSL_ENABLED, "true").build(),
+            Err(Error::ConfigError(_))
+        ));
+    }
+
+    #[cfg(feature = "ssl")]
+    mod ssl {
+        use super::*;
+        use crate::test_certificates::{issue, Issued};
+        use openssl::ssl::{SslAcceptor, SslMethod, SslVerifyMode};
+        use openssl::stack::Stack;
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
+        use std::path::PathBuf;
+        use std::thread;
+
+        // Answers the echo requests of the clients with a certificate issued by the cloud.
+        fn serve_echo(cloud: &Issued) -> u16 {
+            let server = issue("serviceregistry.testcloud.aitia.arrowhead.eu", Some(cloud));
+            let mut acceptor =
+                SslAcceptor::mozilla_intermediate_v5(SslMethod::tls_server()).unwrap();
+            acceptor.set_certificate(&server.certificate).unwrap();
+            acceptor.set_private_key(&server.key).unwrap();
+            acceptor
+                .cert_store_mut()
+                .add_cert(cloud.certificate.clone())
+                .unwrap();
+            acceptor.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+            let acceptor = acceptor.build();
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let port = listener.local_addr().unwrap().port();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let Ok(mut stream) = acceptor.accept(stream.unwrap()) else {
+                        continue;
+                    };
+                    let mut head = Vec::new();
+                    let mut byte = [0u8];
+                    while !head.ends_with(b"\r\n\r\n") && stream.read(&mut byte).unwrap_or(0) == 1 {
+                        head.push(byte[0]);
+                    }
+                    let _ = stream.write_all(
+                        b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    );
+                }
+            });
+            port
+        }
+
+        fn write_store(name: &str, builder: openssl::pkcs12::Pkcs12Builder) -> PathBuf {
+            let directory = std::env::temp_dir().join(format!("ah_config_{}", std::process::id()));
+            std::fs::create_dir_all(&directory).unwrap();
+            let path = directory.join(name);
+            std::fs::write(&path, builder.build2("123456").unwrap().to_der().unwrap()).unwrap();
+            path
+        }
+
+        fn ssl_config(cloud: &Issued, client: &Issued, port: u16) -> AdapterConfig {
+            let mut key_store = Pkcs12::builder();
+            key_store.pkey(&client.key).cert(&client.certificate);
+            let mut trusted = Stack::new().unwrap();
+            trusted.push(cloud.certificate.clone()).unwrap();
+            let mut trust_store = Pkcs12::builder();
+            trust_store.ca(trusted);
+
+            AdapterConfig::new()
+                .with_properties(&format!(
+                    "sr_address=127.0.0.1\n\
+                     sr_port={}\n\
+                     application_system_name=thermometer\n\
+                     server.address=127.0.0.1\n\
+                     server.port=8080\n\
+                     server.ssl.enabled=true\n\
+                     server.ssl.key-store-type=PKCS12\n\
+                     server.ssl.key-store=file:{}\n\
+                     server.ssl.key-store-password=123456\n\
+                     server.ssl.key-password=123456\n\
+                     server.ssl.trust-store={}\n\
+                     server.ssl.trust-store-password=123456\n",
+                    port,
+                    write_store("thermometer.p12", key_store).display(),
+                    write_store("truststore.p12", trust_store).display()
+                ))
+                .unwrap()
+        }
+
+        #[test]
+        fn call_with_client_certificate() {
+            let cloud = issue("testcloud.aitia.arrowhead.eu", None);
+            let client = issue("thermometer.testcloud.aitia.arrowhead.eu", Some(&cloud));
+            let port = serve_echo(&cloud);
+            let config = ssl_config(&cloud, &client, port);
+            let adapter = config.build().unwrap();
+
+            assert_eq!(
+                addresses(&adapter.service_registry),
+                [format!("https://127.0.0.1:{}/serviceregistry/", port).as_str()]
+            );
+            assert!(adapter.echo_service_registry().is_ok());
+            // Without the client certificate the handshake fails.
+            assert!(ArrowheadSystemAdapter::new(
+                &format!("https://127.0.0.1:{}/serviceregistry/", port),
+                "http://dontcare",
+                "http://dontcare",
+                config.client_system().unwrap(),
+            )
+            .unwrap()
+            .echo_service_registry()
+            .is_err());
+        }
+
+        #[test]
+        fn invalid_stores() {
+            let cloud = issue("testcloud.aitia.arrowhead.eu", None);
+            let client = issue("thermometer.testcloud.aitia.arrowhead.eu", Some(&cloud));
+            let config = ssl_config(&cloud, &client, 8443);
+
+            for (key, value) in [
+                (SSL_KEY_STORE_PASSWORD, "654321"),
+                (SSL_KEY_PASSWORD, "654321"),
+                (SSL_KEY_STORE_TYPE, "JKS"),
+                (SSL_TRUST_STORE, "missing.p12"),
+                (SSL_TRUST_STORE_PASSWORD, "654321"),
+            ] {
+                assert!(matches!(
+                    config.clone().with_property(key, value).build(),
+                    Err(Error::ConfigError(_))
+                ));
+            }
+        }
+    }
+}
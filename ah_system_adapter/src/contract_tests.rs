@@ -0,0 +1,546 @@
+// Contract tests against the payloads of the core systems in the fixtures directory. Every
+// endpoint has a folder per Arrowhead version holding the accepted request, the response and the
+// error body, each of them optional, and the query string of the accepted request when the
+// endpoint takes query parameters. The requests sent by the adapter must match the recorded
+// requests and query strings exactly, and the recorded responses must be accepted with unknown fields added and
+// with the optional fields left out.
+
+use crate::dtos::{
+    ArrowheadServerException, ArrowheadService, ArrowheadSystem, ArrowheadVersion, EntryTag,
+    NoEntryTag, OrchestrationResponse, RegisterServiceInput, RequestOrchestrationInput,
+    ServiceQueryForm, ServiceQueryFormList, ServiceQueryList, ServiceQueryResultList,
+};
+use crate::error::{Error, Result};
+use crate::ArrowheadSystemAdapter;
+
+use mockito::Matcher;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const UNKNOWN_FIELD: &str = "unknownField";
+
+struct Contract {
+    endpoint: &'static str,
+    method: &'static str,
+    path: &'static str,
+    status: usize,
+    versions: &'static [ArrowheadVersion],
+    call: fn(&ArrowheadSystemAdapter, Option<Value>) -> Result<()>,
+    response: fn(Value) -> serde_json::Result<()>,
+    optional_fields: &'static [&'static str],
+}
+
+const ALL_VERSIONS: &[ArrowheadVersion] = &[ArrowheadVersion::V4_4, ArrowheadVersion::V4_6];
+
+const SERVICE_OPTIONAL_FIELDS: &[&str] = &[
+    "authenticationInfo",
+    "endOfValidity",
+    "secure",
+    "metadata",
+    "version",
+];
+
+const CONTRACTS: &[Contract] = &[
+    Contract {
+        endpoint: "service_registry/query",
+        method: "POST",
+        path: "serviceregistry/query",
+        status: 200,
+        versions: ALL_VERSIONS,
+        call: |adapter, request| {
+            adapter
+                .query_service(&from_request::<ServiceQueryForm>(request))
+                .map(drop)
+        },
+        response: deserialize::<ServiceQueryList>,
+        optional_fields: SERVICE_OPTIONAL_FIELDS,
+    },
+    Contract {
+        endpoint: "service_registry/query_multi",
+        method: "POST",
+        path: "serviceregistry/query/multi",
+        status: 200,
+        versions: &[ArrowheadVersion::V4_6],
+        call: |adapter, request| {
+            adapter
+                .query_service_multi(&from_request::<ServiceQueryFormList>(request))
+                .map(drop)
+        },
+        response: deserialize::<ServiceQueryResultList>,
+        optional_fields: SERVICE_OPTIONAL_FIELDS,
+    },
+    Contract {
+        endpoint: "service_registry/register",
+        method: "POST",
+        path: "serviceregistry/register",
+        status: 201,
+        versions: ALL_VERSIONS,
+        call: |adapter, request| {
+            adapter
+                .register_service(from_request::<RegisterServiceInput>(request))
+                .map(drop)
+        },
+        response: deserialize::<ArrowheadService<EntryTag>>,
+        optional_fields: SERVICE_OPTIONAL_FIELDS,
+    },
+    Contract {
+        endpoint: "service_registry/unregister",
+        method: "DELETE",
+        path: "serviceregistry/unregister",
+        status: 200,
+        versions: ALL_VERSIONS,
+        call: |adapter, _| adapter.unregister_service("temperature"),
+        response: deserialize::<Value>,
+        optional_fields: &[],
+    },
+    Contract {
+        endpoint: "service_registry/unregister_entry",
+        method: "DELETE",
+        path: "serviceregistry/unregister",
+        status: 200,
+        versions: ALL_VERSIONS,
+        call: |adapter, _| {
+            // The fields of the entry used for unregistering are the same in every version.
+            let service = fixture(
+                ArrowheadVersion::V4_4,
+                "service_registry/register",
+                "response.json",
+            );
+            adapter.unregister_service_entry(&from_request::<ArrowheadService<EntryTag>>(service))
+        },
+        response: deserialize::<Value>,
+        optional_fields: &[],
+    },
+    Contract {
+        endpoint: "service_registry/mgmt_delete",
+        method: "DELETE",
+        path: "serviceregistry/mgmt/42",
+        status: 200,
+        versions: ALL_VERSIONS,
+        call: |adapter, _| adapter.delete_service_by_id(42),
+        response: deserialize::<Value>,
+        optional_fields: &[],
+    },
+    Contract {
+        endpoint: "authorization/publickey",
+        method: "GET",
+        path: "authorization/publickey",
+        status: 200,
+        versions: ALL_VERSIONS,
+        call: |adapter, _| adapter.get_public_key().map(drop),
+        response: deserialize::<String>,
+        optional_fields: &[],
+    },
+    Contract {
+        endpoint: "orchestrator/orchestration",
+        method: "POST",
+        path: "orchestrator/orchestration",
+        status: 200,
+        versions: ALL_VERSIONS,
+        call: |adapter, request| {
+            adapter
+                .request_orchestration(from_request::<RequestOrchestrationInput>(request))
+                .map(drop)
+        },
+        response: deserialize::<OrchestrationResponse>,
+        optional_fields: &["authenticationInfo", "authorizationTokens"],
+    },
+    Contract {
+        endpoint: "orchestrator/orchestration_by_id",
+        method: "GET",
+        path: "orchestrator/orchestration/42",
+        status: 200,
+        versions: ALL_VERSIONS,
+        call: |adapter, _| adapter.request_orchestration_by_id(42).map(drop),
+        response: deserialize::<OrchestrationResponse>,
+        optional_fields: &["authenticationInfo", "authorizationTokens"],
+    },
+];
+
+fn version_folder(version: ArrowheadVersion) -> &'static str {
+    match version {
+        ArrowheadVersion::V4_4 => "4.4",
+        ArrowheadVersion::V4_6 => "4.6",
+    }
+}
+
+fn fixture_path(version: ArrowheadVersion, endpoint: &str, file: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("fixtures")
+        .join(version_folder(version))
+        .join(endpoint)
+        .join(file)
+}
+
+fn fixture(version: ArrowheadVersion, endpoint: &str, file: &str) -> Option<Value> {
+    let path = fixture_path(version, endpoint, file);
+    path.exists()
+        .then(|| serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap())
+}
+
+// The query string of the accepted request, without the leading '?'.
+fn query_fixture(version: ArrowheadVersion, endpoint: &str) -> Option<String> {
+    fs::read_to_string(fixture_path(version, endpoint, "query.txt"))
+        .ok()
+        .map(|query| query.trim_end().to_owned())
+}
+
+// The inputs of the adapter are read from the recorded requests, ignoring the fields filled in by
+// the adapter (e.g. the requester system).
+fn from_request<T: DeserializeOwned>(request: Option<Value>) -> T {
+    serde_json::from_value(request.expect("the contract has a recorded request")).unwrap()
+}
+
+fn deserialize<T: DeserializeOwned>(value: Value) -> serde_json::Result<()> {
+    serde_json::from_value::<T>(value).map(drop)
+}
+
+fn adapter(version: ArrowheadVersion) -> ArrowheadSystemAdapter {
+    let address = |core_system: &str| {
+        format!(
+            "{}/contract/{}/{}/",
+            mockito::server_url(),
+            version_folder(version),
+            core_system
+        )
+    };
+    ArrowheadSystemAdapter::new(
+        &address("serviceregistry"),
+        &address("authorization"),
+        &address("orchestrator"),
+        ArrowheadSystem {
+            entry_tag: NoEntryTag {},
+            system_name: "thermometer".to_owned(),
+            address: "192.168.0.10".to_owned(),
+            port: 8080,
+            authentication_info: None,
+            metadata: Some(HashMap::from([("location".to_owned(), "hall".to_owned())])),
+        },
+    )
+    .unwrap()
+    .with_version(version)
+}
+
+fn with_unknown_fields(value: &Value) -> Value {
+    match value {
+        Value::Object(object) => {
+            let mut object: serde_json::Map<String, Value> = object
+                .iter()
+                .map(|(key, value)| (key.clone(), with_unknown_fields(value)))
+                .collect();
+            object.insert(UNKNOWN_FIELD.to_owned(), Value::from("unknown"));
+            Value::Object(object)
+        }
+        Value::Array(array) => Value::Array(array.iter().map(with_unknown_fields).collect()),
+        value => value.clone(),
+    }
+}
+
+fn without_fields(value: &Value, fields: &[&str]) -> Value {
+    match value {
+        Value::Object(object) => Value::Object(
+            object
+                .iter()
+                .filter(|(key, _)| !fields.contains(&key.as_str()))
+                .map(|(key, value)| (key.clone(), without_fields(value, fields)))
+                .collect(),
+        ),
+        Value::Array(array) => Value::Array(
+            array
+                .iter()
+                .map(|value| without_fields(value, fields))
+                .collect(),
+        ),
+        value => value.clone(),
+    }
+}
+
+fn mock(version: ArrowheadVersion, contract: &Contract) -> mockito::Mock {
+    mockito::mock(
+        contract.method,
+        format!("/contract/{}/{}", version_folder(version), contract.path).as_str(),
+    )
+    .match_query(query_fixture(version, contract.endpoint).map_or(Matcher::Missing, Matcher::Exact))
+}
+
+fn check_contracts(version: ArrowheadVersion) {
+    let adapter = adapter(version);
+    for contract in CONTRACTS
+        .iter()
+        .filter(|contract| contract.versions.contains(&version))
+    {
+        let request = fixture(version, contract.endpoint, "request.json");
+        let body_matcher = request.clone().map_or(Matcher::Any, Matcher::Json);
+
+        if let Some(response) = fixture(version, contract.endpoint, "response.json") {
+            for (variant, response) in [
+                ("recorded", response.clone()),
+                ("unknown fields", with_unknown_fields(&response)),
+                (
+                    "missing optional fields",
+                    without_fields(&response, contract.optional_fields),
+                ),
+            ] {
+                assert!(
+                    (contract.response)(response.clone()).is_ok(),
+                    "{} response of {} is not accepted",
+                    variant,
+                    contract.endpoint
+                );
+                let mock = mock(version, contract)
+                    .match_body(body_matcher.clone())
+                    .with_status(contract.status)
+                    .with_header("content-type", "application/json")
+                    .with_body(response.to_string())
+                    .create();
+                let result = (contract.call)(&adapter, request.clone());
+                assert!(
+                    result.is_ok(),
+                    "{} call of {} failed: {}",
+                    variant,
+                    contract.endpoint,
+                    result.unwrap_err()
+                );
+                mock.assert();
+            }
+        }
+
+        if let Some(error) = fixture(version, contract.endpoint, "error.json") {
+            let expected: ArrowheadServerException = serde_json::from_value(error.clone()).unwrap();
+            for error in [error.clone(), with_unknown_fields(&error)] {
+                let mock = mock(version, contract)
+                    .match_body(body_matcher.clone())
+                    .with_status(expected.error_code as usize)
+                    .with_header("content-type", "application/json")
+                    .with_body(error.to_string())
+                    .create();
+                assert!(
+                    matches!(
+                        (contract.call)(&adapter, request.clone()),
+                        Err(Error::ArrowheadError(exception)) if exception == expected
+                    ),
+                    "error of {} is not returned",
+                    contract.endpoint
+                );
+                mock.assert();
+            }
+        }
+    }
+}
+
+#[test]
+fn contract_4_4() {
+    check_contracts(ArrowheadVersion::V4_4);
+}
+
+#[test]
+fn contract_4_6() {
+    check_contracts(ArrowheadVersion::V4_6);
+}
+
+#[test]
+fn every_recorded_endpoint_is_checked() {
+    for version in ALL_VERSIONS {
+        let folder = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("fixtures")
+            .join(version_folder(*version));
+        for core_system in fs::read_dir(folder).unwrap() {
+            let core_system = core_system.unwrap();
+            for endpoint in fs::read_dir(core_system.path()).unwrap() {
+                let endpoint = format!(
+                    "{}/{}",
+                    core_system.file_name().to_string_lossy(),
+                    endpoint.unwrap().file_name().to_string_lossy()
+                );
+                assert!(
+                    CONTRACTS
+                        .iter()
+                        .any(|contract| contract.endpoint == endpoint
+                            && contract.versions.contains(version)),
+                    "no contract for the recorded endpoint {}",
+                    endpoint
+                );
+            }
+        }
+    }
+}
+
+// A blank service definition requirement, rejected by every core version.
+fn blank_query(request: &mut Value) {
+    request["serviceDefinitionRequirement"] = Value::from("");
+}
+
+fn blank_orchestration(request: &mut Value) {
+    request["requestedService"]["serviceDefinitionRequirement"] = Value::from("");
+}
+
+// A recording step: the endpoint, the fixture written from the answer of the core (none for the
+// steps only preparing the next ones) and the change of the recorded request, if any. The entries
+// registered by the steps are removed by the last ones.
+struct RecordingStep {
+    endpoint: &'static str,
+    fixture: Option<&'static str>,
+    change: Option<fn(&mut Value)>,
+}
+
+const RECORDING_STEPS: &[RecordingStep] = &[
+    RecordingStep {
+        endpoint: "service_registry/register",
+        fixture: Some("response.json"),
+        change: None,
+    },
+    RecordingStep {
+        endpoint: "service_registry/register",
+        fixture: Some("error.json"),
+        change: None,
+    },
+    RecordingStep {
+        endpoint: "service_registry/query",
+        fixture: Some("response.json"),
+        change: None,
+    },
+    RecordingStep {
+        endpoint: "service_registry/query",
+        fixture: Some("error.json"),
+        change: Some(blank_query),
+    },
+    RecordingStep {
+        endpoint: "service_registry/query_multi",
+        fixture: Some("response.json"),
+        change: None,
+    },
+    RecordingStep {
+        endpoint: "authorization/publickey",
+        fixture: Some("response.json"),
+        change: None,
+    },
+    RecordingStep {
+        endpoint: "orchestrator/orchestration",
+        fixture: Some("response.json"),
+        change: None,
+    },
+    RecordingStep {
+        endpoint: "orchestrator/orchestration",
+        fixture: Some("error.json"),
+        change: Some(blank_orchestration),
+    },
+    RecordingStep {
+        endpoint: "orchestrator/orchestration_by_id",
+        fixture: Some("error.json"),
+        change: None,
+    },
+    RecordingStep {
+        endpoint: "service_registry/unregister_entry",
+        fixture: Some("response.json"),
+        change: None,
+    },
+    RecordingStep {
+        endpoint: "service_registry/unregister_entry",
+        fixture: Some("error.json"),
+        change: None,
+    },
+    RecordingStep {
+        endpoint: "service_registry/register",
+        fixture: None,
+        change: None,
+    },
+    RecordingStep {
+        endpoint: "service_registry/unregister",
+        fixture: Some("response.json"),
+        change: None,
+    },
+    RecordingStep {
+        endpoint: "service_registry/unregister",
+        fixture: Some("error.json"),
+        change: None,
+    },
+    RecordingStep {
+        endpoint: "service_registry/mgmt_delete",
+        fixture: Some("error.json"),
+        change: None,
+    },
+];
+
+// Records the fixtures of a version from running core systems in insecure mode, replacing the
+// response and error bodies:
+//
+//   AH_RECORD_VERSION=4.6 cargo test record_fixtures -- --ignored
+//
+// The cores are reached on their default ports of localhost, or on the addresses given in
+// AH_RECORD_SERVICE_REGISTRY, AH_RECORD_AUTHORIZATION and AH_RECORD_ORCHESTRATOR. The recorded
+// requests and query strings are sent as they are, so they are checked to be accepted by the
+// cores. The Service Registry must not hold the recorded entry beforehand.
+#[test]
+#[ignore]
+fn record_fixtures() {
+    let version = match std::env::var("AH_RECORD_VERSION").as_deref() {
+        Ok("4.4") => ArrowheadVersion::V4_4,
+        Ok("4.6") => ArrowheadVersion::V4_6,
+        _ => panic!("AH_RECORD_VERSION must be 4.4 or 4.6"),
+    };
+    let address = |variable: &str, default: &str| {
+        std::env::var(variable).unwrap_or_else(|_| default.to_owned())
+    };
+    let core_addresses = HashMap::from([
+        (
+            "serviceregistry",
+            address("AH_RECORD_SERVICE_REGISTRY", "http://127.0.0.1:8443/"),
+        ),
+        (
+            "authorization",
+            address("AH_RECORD_AUTHORIZATION", "http://127.0.0.1:8445/"),
+        ),
+        (
+            "orchestrator",
+            address("AH_RECORD_ORCHESTRATOR", "http://127.0.0.1:8441/"),
+        ),
+    ]);
+    let client = reqwest::blocking::Client::new();
+
+    for step in RECORDING_STEPS {
+        let endpoint = step.endpoint;
+        let Some(contract) = CONTRACTS
+            .iter()
+            .find(|c| c.endpoint == endpoint && c.versions.contains(&version))
+        else {
+            continue;
+        };
+        let (core_system, path) = contract.path.split_once('/').unwrap();
+        let mut url = reqwest::Url::parse(&core_addresses[core_system])
+            .and_then(|address| address.join(&format!("{}/{}", core_system, path)))
+            .unwrap();
+        url.set_query(query_fixture(version, endpoint).as_deref());
+        let mut request = client.request(contract.method.parse().unwrap(), url);
+        if let Some(mut body) = fixture(version, endpoint, "request.json") {
+            if let Some(change) = step.change {
+                change(&mut body);
+            }
+            request = request.json(&body);
+        }
+
+        let response = request.send().unwrap();
+        let status = response.status();
+        let text = response.text().unwrap();
+        let Some(file) = step.fixture else {
+            continue;
+        };
+        assert!(
+            (file == "error.json") == (status.is_client_error() || status.is_server_error()),
+            "unexpected status {} for the {} of {}: {}",
+            status,
+            file,
+            endpoint,
+            text
+        );
+        let body = serde_json::from_str(&text).unwrap_or(Value::String(text));
+        fs::write(
+            fixture_path(version, endpoint, file),
+            format!("{}\n", serde_json::to_string_pretty(&body).unwrap()),
+        )
+        .unwrap();
+    }
+}
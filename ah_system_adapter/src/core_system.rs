@@ -0,0 +1,238 @@
+use crate::dtos::{
+    ArrowheadService, EntryTag, OrchestrationResponse, RegisterServiceInput,
+    RequestOrchestrationInput, ServiceQueryForm, ServiceQueryList,
+};
+use crate::error::Result;
+use crate::ArrowheadSystemAdapter;
+
+pub trait ServiceRegistry {
+    fn echo_service_registry(&self) -> Result<()>;
+    fn query_service(&self, service_query_form: &ServiceQueryForm) -> Result<ServiceQueryList>;
+    fn query_services(
+        &self,
+        service_query_forms: &[ServiceQueryForm],
+    ) -> Result<Vec<(String, ServiceQueryList)>> {
+        service_query_forms
+            .iter()
+            .map(|form| {
+                Ok((
+                    form.service_requirements
+                        .service_definition_requirement
+                        .clone(),
+                    self.query_service(form)?,
+                ))
+            })
+            .collect()
+    }
+    fn register_service(&self, input: RegisterServiceInput) -> Result<ArrowheadService<EntryTag>>;
+    fn unregister_service(&self, service_definition: &str) -> Result<()>;
+    // Removes exactly the given entry, the other entries of the service definition are kept.
+    fn unregister_service_entry(&self, service: &ArrowheadService<EntryTag>) -> Result<()>;
+}
+
+pub trait Authorization {
+    fn echo_authorization(&self) -> Result<()>;
+    fn get_public_key(&self) -> Result<String>;
+}
+
+pub trait Orchestrator {
+    fn echo_orchestrator(&self) -> Result<()>;
+    fn request_orchestration(
+        &self,
+        input: RequestOrchestrationInput,
+    ) -> Result<OrchestrationResponse>;
+    fn request_orchestration_by_id(&self, id: i64) -> Result<OrchestrationResponse>;
+}
+
+pub trait ArrowheadCore: ServiceRegistry + Authorization + Orchestrator {}
+impl<T: ServiceRegistry + Authorization + Orchestrator + ?Sized> ArrowheadCore for T {}
+
+impl ServiceRegistry for ArrowheadSystemAdapter {
+    fn echo_service_registry(&self) -> Result<()> {
+        ArrowheadSystemAdapter::echo_service_registry(self)
+    }
+
+    fn query_service(&self, service_query_form: &ServiceQueryForm) -> Result<ServiceQueryList> {
+        ArrowheadSystemAdapter::query_service(self, service_query_form)
+    }
+
+    fn query_services(
+        &self,
+        service_query_forms: &[ServiceQueryForm],
+    ) -> Result<Vec<(String, ServiceQueryList)>> {
+        ArrowheadSystemAdapter::query_services(self, service_query_forms)
+    }
+
+    fn register_service(&self, input: RegisterServiceInput) -> Result<ArrowheadService<EntryTag>> {
+        ArrowheadSystemAdapter::register_service(self, input)
+    }
+
+    fn unregister_service(&self, service_definition: &str) -> Result<()> {
+        ArrowheadSystemAdapter::unregister_service(self, service_definition)
+    }
+
+    fn unregister_service_entry(&self, service: &ArrowheadService<EntryTag>) -> Result<()> {
+        ArrowheadSystemAdapter::unregister_service_entry(self, service)
+    }
+}
+
+impl Authorization for ArrowheadSystemAdapter {
+    fn echo_authorization(&self) -> Result<()> {
+        ArrowheadSystemAdapter::echo_authorization(self)
+    }
+
+    fn get_public_key(&self) -> Result<String> {
+        ArrowheadSystemAdapter::get_public_key(self)
+    }
+}
+
+impl Orchestrator for ArrowheadSystemAdapter {
+    fn echo_orchestrator(&self) -> Result<()> {
+        ArrowheadSystemAdapter::echo_orchestrator(self)
+    }
+
+    fn request_orchestration(
+        &self,
+        input: RequestOrchestrationInput,
+    ) -> Result<OrchestrationResponse> {
+        ArrowheadSystemAdapter::request_orchestration(self, input)
+    }
+
+    fn request_orchestration_by_id(&self, id: i64) -> Result<OrchestrationResponse> {
+        ArrowheadSystemAdapter::request_orchestration_by_id(self, id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dtos::{
+        ArrowheadSystem, InterfaceEntry, Orchestration, SecurityType, ServiceDefinitionEntry,
+        ServiceRequirements,
+    };
+    use crate::{Error, OrchestrationCache, OrchestrationCacheConfig};
+    use std::cell::Cell;
+    use std::collections::HashMap;
+
+    #[derive(Default)]
+    struct FakeCore {
+        orchestration_requests: Cell<u32>,
+    }
+
+    impl ServiceRegistry for FakeCore {
+        fn echo_service_registry(&self) -> Result<()> {
+            Ok(())
+        }
+
+        fn query_service(&self, _: &ServiceQueryForm) -> Result<ServiceQueryList> {
+            Ok(ServiceQueryList {
+                service_query_data: vec![],
+                unfiltered_hits: 0,
+            })
+        }
+
+        fn register_service(&self, _: RegisterServiceInput) -> Result<ArrowheadService<EntryTag>> {
+            Err(Error::HttpError("string".to_owned()))
+        }
+
+        fn unregister_service(&self, _: &str) -> Result<()> {
+            Ok(())
+        }
+
+        fn unregister_service_entry(&self, _: &ArrowheadService<EntryTag>) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Authorization for FakeCore {
+        fn echo_authorization(&self) -> Result<()> {
+            Ok(())
+        }
+
+        fn get_public_key(&self) -> Result<String> {
+            Ok("fake-key".to_owned())
+        }
+    }
+
+    impl Orchestrator for FakeCore {
+        fn echo_orchestrator(&self) -> Result<()> {
+            Ok(())
+        }
+
+        fn request_orchestration(
+            &self,
+            _: RequestOrchestrationInput,
+        ) -> Result<OrchestrationResponse> {
+            self.orchestration_requests
+                .set(self.orchestration_requests.get() + 1);
+            Ok(OrchestrationResponse {
+                response: vec![Orchestration {
+                    provider: ArrowheadSystem {
+                        entry_tag: EntryTag {
+                            id: 1,
+                            created_at: "string".to_owned(),
+                            updated_at: "string".to_owned(),
+                        },
+                        system_name: "provider".to_owned(),
+                        address: "127.0.0.1".to_owned(),
+                        port: 8080,
+                        authentication_info: None,
+                        metadata: None,
+                    },
+                    service: ServiceDefinitionEntry::Value("temperature".to_owned()),
+                    service_uri: "/temperature".to_owned(),
+                    secure: SecurityType::NotSecure,
+                    metadata: HashMap::new(),
+                    interfaces: vec![InterfaceEntry::Value("HTTP-INSECURE-JSON".to_owned())],
+                    version: 1,
+                    authorization_tokens: None,
+                    warnings: vec![],
+                }],
+            })
+        }
+
+        fn request_orchestration_by_id(&self, _: i64) -> Result<OrchestrationResponse> {
+            Ok(OrchestrationResponse { response: vec![] })
+        }
+    }
+
+    fn input() -> RequestOrchestrationInput {
+        RequestOrchestrationInput {
+            requested_service: ServiceRequirements {
+                service_definition_requirement: "temperature".to_owned(),
+                interface_requirements: None,
+                security_requirements: None,
+                metadata_requirements: None,
+                version_requirement: None,
+                max_version_requirement: None,
+                min_version_requirement: None,
+            },
+            preferred_providers: None,
+            orchestration_flags: None,
+        }
+    }
+
+    #[test]
+    fn use_fake_through_trait_object() {
+        let core: Box<dyn ArrowheadCore> = Box::new(FakeCore::default());
+
+        assert!(core.echo_service_registry().is_ok());
+        assert!(matches!(core.get_public_key(), Ok(key) if key == "fake-key"));
+        assert!(matches!(
+            core.request_orchestration(input()),
+            Ok(response) if response.response.len() == 1
+        ));
+    }
+
+    #[test]
+    fn decorate_orchestrator_with_cache() {
+        let cache =
+            OrchestrationCache::new(FakeCore::default(), OrchestrationCacheConfig::default());
+        let orchestrator: &dyn Orchestrator = &cache;
+
+        assert!(orchestrator.request_orchestration(input()).is_ok());
+        assert!(orchestrator.request_orchestration(input()).is_ok());
+        assert_eq!(cache.adapter().orchestration_requests.get(), 1);
+        assert_eq!(cache.stats().hits, 1);
+    }
+}
@@ -1,7 +1,38 @@
+use reqwest::Url;
+#[cfg(feature = "json-schema")]
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Serialize, PartialEq, Clone)]
+#[derive(Deserialize, Serialize, PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "json-schema", derive(JsonSchema))]
+pub enum ArrowheadVersion {
+    #[serde(rename = "4.4.0")]
+    V4_4,
+    #[serde(rename = "4.6.0")]
+    V4_6,
+}
+impl ArrowheadVersion {
+    pub fn supports_system_metadata(&self) -> bool {
+        *self == Self::V4_6
+    }
+
+    pub fn supports_multi_query(&self) -> bool {
+        *self == Self::V4_6
+    }
+}
+impl Default for ArrowheadVersion {
+    fn default() -> Self {
+        if cfg!(feature = "arrowhead-4-6") {
+            Self::V4_6
+        } else {
+            Self::V4_4
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[cfg_attr(feature = "json-schema", derive(JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct ServiceQueryForm {
     #[serde(flatten)]
@@ -10,7 +41,22 @@ pub struct ServiceQueryForm {
     pub ping_providers: Option<bool>,
 }
 
-#[derive(Deserialize, PartialEq, Clone)]
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[cfg_attr(feature = "json-schema", derive(JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceQueryFormList {
+    pub forms: Vec<ServiceQueryForm>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[cfg_attr(feature = "json-schema", derive(JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceQueryResultList {
+    pub results: Vec<ServiceQueryList>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[cfg_attr(feature = "json-schema", derive(JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct ServiceQueryList {
     pub service_query_data: Vec<ArrowheadService<EntryTag>>,
@@ -18,6 +64,7 @@ pub struct ServiceQueryList {
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[cfg_attr(feature = "json-schema", derive(JsonSchema))]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum SecurityType {
     NotSecure,
@@ -26,6 +73,7 @@ pub enum SecurityType {
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[cfg_attr(feature = "json-schema", derive(JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct ArrowheadService<T> {
     #[serde(flatten)]
@@ -46,6 +94,7 @@ pub struct ArrowheadService<T> {
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[cfg_attr(feature = "json-schema", derive(JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct EntryTag {
     pub id: u32,
@@ -54,6 +103,7 @@ pub struct EntryTag {
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[cfg_attr(feature = "json-schema", derive(JsonSchema))]
 #[serde(untagged)]
 pub enum ServiceDefinitionEntry {
     Value(String),
@@ -76,6 +126,7 @@ impl ServiceDefinitionEntry {
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[cfg_attr(feature = "json-schema", derive(JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct ArrowheadSystem<T> {
     #[serde(flatten)]
@@ -85,9 +136,22 @@ pub struct ArrowheadSystem<T> {
     pub port: u32,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub authentication_info: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<HashMap<String, String>>,
+}
+impl<T: Clone> ArrowheadSystem<T> {
+    // System metadata was introduced in Arrowhead 4.6, so it is not sent to older core systems.
+    pub(crate) fn for_version(&self, version: ArrowheadVersion) -> Self {
+        let mut system = self.clone();
+        if !version.supports_system_metadata() {
+            system.metadata = None;
+        }
+        system
+    }
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[cfg_attr(feature = "json-schema", derive(JsonSchema))]
 #[serde(untagged)]
 pub enum InterfaceEntry {
     Value(String),
@@ -108,9 +172,11 @@ impl InterfaceEntry {
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[cfg_attr(feature = "json-schema", derive(JsonSchema))]
 pub struct NoEntryTag {}
 
-#[derive(Serialize, PartialEq, Clone)]
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[cfg_attr(feature = "json-schema", derive(JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct ServiceRequirements {
     pub service_definition_requirement: String,
@@ -128,7 +194,8 @@ pub struct ServiceRequirements {
     pub min_version_requirement: Option<u32>,
 }
 
-#[derive(Serialize, PartialEq, Clone)]
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[cfg_attr(feature = "json-schema", derive(JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct ServiceRequestForm {
     pub requester_system: ArrowheadSystem<NoEntryTag>,
@@ -139,21 +206,32 @@ pub struct ServiceRequestForm {
     pub orchestration_flags: Option<HashMap<OrchestrationFlagKey, bool>>,
 }
 
-#[derive(Serialize, PartialEq, Clone)]
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[cfg_attr(feature = "json-schema", derive(JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct ArrowheadProvider {
     pub provider_cloud: ArrowheadCloud,
     pub provider_system: ArrowheadSystem<NoEntryTag>,
 }
 
-#[derive(Serialize, PartialEq, Clone)]
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[cfg_attr(feature = "json-schema", derive(JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct ArrowheadCloud {
     pub operator: String,
     pub name: String,
 }
+impl From<&ArrowheadCloudEntry> for ArrowheadCloud {
+    fn from(cloud: &ArrowheadCloudEntry) -> Self {
+        ArrowheadCloud {
+            operator: cloud.operator.clone(),
+            name: cloud.name.clone(),
+        }
+    }
+}
 
-#[derive(Serialize, PartialEq, Eq, Hash, Clone)]
+#[derive(Deserialize, Serialize, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "json-schema", derive(JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub enum OrchestrationFlagKey {
     Machmaking,
@@ -165,13 +243,15 @@ pub enum OrchestrationFlagKey {
     TriggerInterCloud,
 }
 
-#[derive(Deserialize, PartialEq, Clone)]
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[cfg_attr(feature = "json-schema", derive(JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct OrchestrationResponse {
     pub response: Vec<Orchestration>,
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[cfg_attr(feature = "json-schema", derive(JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct Orchestration {
     pub provider: ArrowheadSystem<EntryTag>,
@@ -184,17 +264,38 @@ pub struct Orchestration {
     pub authorization_tokens: Option<HashMap<String, String>>,
     pub warnings: Vec<OrchestrationWarning>,
 }
+impl Orchestration {
+    // Results of inter-cloud orchestration are marked by the FROM_OTHER_CLOUD warning. Their
+    // provider ids are the ids of the Service Registry of the other cloud, so they must not be
+    // matched with the local ids (e.g. of QoS measurements), and their authorization tokens are
+    // issued by the Authorization of the other cloud.
+    pub fn is_from_other_cloud(&self) -> bool {
+        self.warnings
+            .contains(&OrchestrationWarning::FromOtherCloud)
+    }
+
+    // When a Gateway is mandatory between the clouds, the result is marked by the VIA_GATEWAY
+    // warning, and its provider is the consumer-side end of the Gateway session: the address of
+    // the Gateway and the port opened for the session. The service is invoked there with the
+    // same service URI, until the Gateway closes the inactive session.
+    pub fn is_through_gateway(&self) -> bool {
+        self.warnings.contains(&OrchestrationWarning::ViaGateway)
+    }
+}
 
 #[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[cfg_attr(feature = "json-schema", derive(JsonSchema))]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum OrchestrationWarning {
     FromOtherCloud,
+    ViaGateway,
     TtlExpired,
     TtlExpiring,
     TtlUnknown,
 }
 
-#[derive(Deserialize, PartialEq, Clone, Debug)]
+#[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "json-schema", derive(JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct ArrowheadServerException {
     pub error_message: String,
@@ -203,7 +304,8 @@ pub struct ArrowheadServerException {
     pub origin: String,
 }
 
-#[derive(Deserialize, PartialEq, Clone)]
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[cfg_attr(feature = "json-schema", derive(JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct RegisterServiceInput {
     pub service_definition: ServiceDefinitionEntry,
@@ -237,7 +339,8 @@ impl RegisterServiceInput {
     }
 }
 
-#[derive(Serialize, PartialEq, Clone)]
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[cfg_attr(feature = "json-schema", derive(JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct RequestOrchestrationInput {
     pub requested_service: ServiceRequirements,
@@ -259,3 +362,261 @@ impl RequestOrchestrationInput {
         }
     }
 }
+
+#[derive(Deserialize, Serialize, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "json-schema", derive(JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct PageRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item_per_page: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub direction: Option<SortDirection>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort_field: Option<String>,
+}
+impl PageRequest {
+    pub(crate) fn append_query_pairs(&self, url: &mut Url) {
+        let query_pairs: Vec<(&str, String)> = [
+            ("page", self.page.map(|p| p.to_string())),
+            ("item_per_page", self.item_per_page.map(|i| i.to_string())),
+            (
+                "direction",
+                self.direction.as_ref().map(|d| d.as_str().to_owned()),
+            ),
+            ("sort_field", self.sort_field.clone()),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| value.map(|v| (key, v)))
+        .collect();
+        if !query_pairs.is_empty() {
+            url.query_pairs_mut().extend_pairs(query_pairs);
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[cfg_attr(feature = "json-schema", derive(JsonSchema))]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+impl SortDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Asc => "ASC",
+            Self::Desc => "DESC",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[cfg_attr(feature = "json-schema", derive(JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ArrowheadCloudEntry {
+    #[serde(flatten)]
+    pub entry_tag: EntryTag,
+    pub operator: String,
+    pub name: String,
+    pub secure: bool,
+    pub neighbor: bool,
+    pub own_cloud: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authentication_info: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[cfg_attr(feature = "json-schema", derive(JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ArrowheadRelay<T> {
+    #[serde(flatten)]
+    pub entry_tag: T,
+    pub address: String,
+    pub port: u32,
+    pub secure: bool,
+    pub exclusive: bool,
+    #[serde(rename = "type")]
+    pub relay_type: RelayType,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[cfg_attr(feature = "json-schema", derive(JsonSchema))]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RelayType {
+    GatekeeperRelay,
+    GatewayRelay,
+    GeneralRelay,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::DeserializeOwned;
+    use serde_json::{json, Value};
+
+    fn round_trip<T: Serialize + DeserializeOwned + PartialEq>(value: Value) -> bool {
+        let dto: T = serde_json::from_value(value).unwrap();
+        let serialized = serde_json::to_value(&dto).unwrap();
+        serde_json::from_value::<T>(serialized).unwrap() == dto
+    }
+
+    fn fixture(path: &str) -> Value {
+        let path = format!("{}/fixtures/{}", env!("CARGO_MANIFEST_DIR"), path);
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    fn system() -> Value {
+        json!({
+            "systemName": "thermometer",
+            "address": "192.168.0.10",
+            "port": 8080,
+            "metadata": { "location": "hall" }
+        })
+    }
+
+    fn service_requirements() -> Value {
+        json!({
+            "serviceDefinitionRequirement": "temperature",
+            "interfaceRequirements": ["HTTP-INSECURE-JSON"],
+            "securityRequirements": ["NOT_SECURE"],
+            "versionRequirement": 1
+        })
+    }
+
+    #[test]
+    fn round_trip_service_registry_dtos() {
+        assert!(round_trip::<ServiceQueryList>(fixture(
+            "4.4/service_registry/query/response.json"
+        )));
+        assert!(round_trip::<ServiceQueryList>(fixture(
+            "4.6/service_registry/query/response.json"
+        )));
+        assert!(round_trip::<ServiceQueryFormList>(fixture(
+            "4.6/service_registry/query_multi/request.json"
+        )));
+        assert!(round_trip::<ServiceQueryResultList>(fixture(
+            "4.6/service_registry/query_multi/response.json"
+        )));
+        assert!(round_trip::<ArrowheadService<NoEntryTag>>(fixture(
+            "4.6/service_registry/register/request.json"
+        )));
+        assert!(round_trip::<RegisterServiceInput>(json!({
+            "serviceDefinition": "temperature",
+            "serviceUri": "/temperature",
+            "secure": "TOKEN",
+            "interfaces": ["HTTP-SECURE-JSON"]
+        })));
+    }
+
+    #[test]
+    fn round_trip_orchestrator_dtos() {
+        let preferred_provider = json!({
+            "providerCloud": { "operator": "aitia", "name": "testcloud" },
+            "providerSystem": system()
+        });
+        assert!(round_trip::<ArrowheadProvider>(preferred_provider.clone()));
+        assert!(round_trip::<ServiceRequestForm>(json!({
+            "requesterSystem": system(),
+            "requestedService": service_requirements(),
+            "preferredProviders": [preferred_provider.clone()],
+            "orchestrationFlags": { "overrideStore": true, "metadataSearch": false }
+        })));
+        assert!(round_trip::<RequestOrchestrationInput>(json!({
+            "requestedService": service_requirements(),
+            "preferredProviders": [preferred_provider],
+            "orchestrationFlags": { "onlyPreferred": true }
+        })));
+        assert!(round_trip::<OrchestrationResponse>(json!({
+            "response": [{
+                "provider": {
+                    "id": 7,
+                    "systemName": "thermometer",
+                    "address": "192.168.0.10",
+                    "port": 8080,
+                    "createdAt": "2022-10-03 08:41:17",
+                    "updatedAt": "2022-10-03 08:41:17"
+                },
+                "service": "temperature",
+                "serviceUri": "/temperature",
+                "secure": "TOKEN",
+                "metadata": {},
+                "interfaces": ["HTTP-SECURE-JSON"],
+                "version": 1,
+                "authorizationTokens": { "HTTP-SECURE-JSON": "token" },
+                "warnings": ["TTL_UNKNOWN"]
+            }]
+        })));
+    }
+
+    #[test]
+    fn inter_cloud_orchestration() {
+        let direct = json!({
+            "provider": {
+                "id": 3,
+                "systemName": "thermometer",
+                "address": "10.0.0.1",
+                "port": 8001,
+                "createdAt": "2022-10-03 08:41:17",
+                "updatedAt": "2022-10-03 08:41:17"
+            },
+            "service": "temperature",
+            "serviceUri": "/temperature",
+            "secure": "NOT_SECURE",
+            "metadata": {},
+            "interfaces": ["HTTP-INSECURE-JSON"],
+            "version": 1,
+            "warnings": ["FROM_OTHER_CLOUD", "TTL_UNKNOWN"]
+        });
+        let mut through_gateway = direct.clone();
+        through_gateway["provider"]["port"] = json!(8003);
+        through_gateway["warnings"] = json!(["FROM_OTHER_CLOUD", "VIA_GATEWAY", "TTL_UNKNOWN"]);
+        assert!(round_trip::<Orchestration>(direct.clone()));
+        assert!(round_trip::<Orchestration>(through_gateway.clone()));
+
+        let orchestration: Orchestration = serde_json::from_value(direct).unwrap();
+        assert!(orchestration.is_from_other_cloud());
+        assert!(!orchestration.is_through_gateway());
+        let orchestration: Orchestration = serde_json::from_value(through_gateway).unwrap();
+        assert!(orchestration.is_from_other_cloud());
+        assert!(orchestration.is_through_gateway());
+    }
+
+    #[test]
+    fn round_trip_other_dtos() {
+        assert!(round_trip::<ArrowheadServerException>(json!({
+            "errorMessage": "Service definition is not found",
+            "errorCode": 400,
+            "exceptionType": "INVALID_PARAMETER",
+            "origin": "/serviceregistry/query"
+        })));
+        assert!(round_trip::<PageRequest>(json!({
+            "page": 0,
+            "itemPerPage": 10,
+            "direction": "DESC",
+            "sortField": "id"
+        })));
+        assert!(round_trip::<ArrowheadVersion>(json!("4.6.0")));
+        assert!(round_trip::<ArrowheadRelay<NoEntryTag>>(json!({
+            "address": "10.0.0.1",
+            "port": 8443,
+            "secure": true,
+            "exclusive": false,
+            "type": "GATEWAY_RELAY"
+        })));
+    }
+
+    #[cfg(feature = "json-schema")]
+    #[test]
+    fn generate_json_schema() {
+        let schema =
+            serde_json::to_value(schemars::schema_for!(ArrowheadService<EntryTag>)).unwrap();
+
+        assert!(schema["required"]
+            .as_array()
+            .is_some_and(|required| required.contains(&json!("serviceUri"))
+                && required.contains(&json!("createdAt"))));
+        assert!(schema["properties"]["providerSystem"].is_object());
+    }
+}
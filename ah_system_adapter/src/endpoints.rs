@@ -0,0 +1,330 @@
+use crate::circuit_breaker::{CircuitBreaker, CircuitBreakerConfig, CircuitBreakerStatus};
+use crate::error::{Error, Result};
+use crate::instrumentation;
+use crate::middleware::{self, Middleware};
+use crate::transport::Transport;
+
+use reqwest::blocking::{Client, Request, RequestBuilder, Response};
+use reqwest::{Method, Url};
+
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub struct CoreSystemEndpoints {
+    addresses: Vec<Url>,
+    active: AtomicUsize,
+    probe_interval: Duration,
+    last_probe: Mutex<Instant>,
+    circuit_breaker: Option<CircuitBreaker>,
+    middleware: Vec<Arc<dyn Middleware>>,
+    transport: Arc<dyn Transport>,
+}
+
+impl CoreSystemEndpoints {
+    pub fn new(addresses: &[&str]) -> Result<Self> {
+        if addresses.is_empty() {
+            return Err(Error::ConfigError(
+                "At least one core system address must be given".to_owned(),
+            ));
+        }
+        Ok(Self {
+            addresses: addresses
+                .iter()
+                .map(|address| {
+                    Url::parse(address).map_err(|e| {
+                        Error::ConfigError(format!(
+                            "The core system address '{}' is invalid: {}",
+                            address, e
+                        ))
+                    })
+                })
+                .collect::<Result<_>>()?,
+            active: AtomicUsize::new(0),
+            probe_interval: Duration::from_secs(30),
+            last_probe: Mutex::new(Instant::now()),
+            circuit_breaker: None,
+            middleware: vec![],
+            transport: Arc::new(Client::new()),
+        })
+    }
+
+    pub fn with_probe_interval(mut self, probe_interval: Duration) -> Self {
+        self.probe_interval = probe_interval;
+        self
+    }
+
+    // The requests are sent by the given client, e.g. one with the client certificate of the
+    // system and the certificates of the cloud.
+    pub fn with_client(self, client: Client) -> Self {
+        self.with_transport(client)
+    }
+
+    // The requests are carried by the given transport instead of HTTP, e.g. over MQTT or CoAP.
+    pub fn with_transport<T: Transport + 'static>(self, transport: T) -> Self {
+        self.with_shared_transport(Arc::new(transport))
+    }
+
+    pub(crate) fn with_shared_transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    pub fn with_circuit_breaker(mut self, config: CircuitBreakerConfig) -> Self {
+        self.circuit_breaker = Some(CircuitBreaker::new(config));
+        self
+    }
+
+    pub fn with_middleware<M: Middleware + 'static>(self, middleware: M) -> Self {
+        self.with_shared_middleware(Arc::new(middleware))
+    }
+
+    pub(crate) fn with_shared_middleware(mut self, middleware: Arc<dyn Middleware>) -> Self {
+        self.middleware.push(middleware);
+        self
+    }
+
+    pub fn circuit_breaker_status(&self) -> Option<CircuitBreakerStatus> {
+        self.circuit_breaker.as_ref().map(CircuitBreaker::status)
+    }
+
+    pub fn addresses(&self) -> &[Url] {
+        &self.addresses
+    }
+
+    pub fn active_address(&self) -> &Url {
+        &self.addresses[self.active.load(Ordering::Relaxed)]
+    }
+
+    // Sends the request built for the active address, and fails over to the next addresses in
+    // order on connection errors and server errors. The address answering the request becomes
+    // the active one. If every address fails, the last error or server error response is returned.
+    // With a circuit breaker, requests fail fast while the circuit is open, and every call is
+    // reported to it as a success or a failure. Every request sent and every response received
+    // pass the middleware.
+    pub(crate) fn send<F>(&self, build_request: F) -> Result<Response>
+    where
+        F: Fn(&Url) -> Result<RequestBuilder>,
+    {
+        if let Some(circuit_breaker) = &self.circuit_breaker {
+            let allowed =
+                circuit_breaker.allow_request(|| match self.probe(0..self.addresses.len()) {
+                    Some(index) => {
+                        self.active.store(index, Ordering::Relaxed);
+                        true
+                    }
+                    None => false,
+                });
+            if !allowed {
+                return Err(Error::CircuitOpenError(format!(
+                    "The circuit breaker of '{}' is open",
+                    self.active_address()
+                )));
+            }
+        }
+        self.probe_preferred_addresses();
+
+        // The attempts are one call for the middleware, e.g. they share the correlation id.
+        middleware::in_call(|| self.fail_over(&build_request))
+    }
+
+    fn fail_over<F>(&self, build_request: &F) -> Result<Response>
+    where
+        F: Fn(&Url) -> Result<RequestBuilder>,
+    {
+        let active = self.active.load(Ordering::Relaxed);
+        let mut last_result = None;
+        for offset in 0..self.addresses.len() {
+            let index = (active + offset) % self.addresses.len();
+            // A request which cannot be built for an address counts as a failed attempt.
+            let result = build_request(&self.addresses[index])
+                .and_then(|request| self.send_through_middleware(request));
+            match result {
+                Ok(response) if !response.status().is_server_error() => {
+                    self.active.store(index, Ordering::Relaxed);
+                    if let Some(circuit_breaker) = &self.circuit_breaker {
+                        circuit_breaker.report_success();
+                    }
+                    return Ok(response);
+                }
+                result => last_result = Some(result),
+            }
+        }
+        if let Some(circuit_breaker) = &self.circuit_breaker {
+            circuit_breaker.report_failure();
+        }
+        last_result.expect("there is at least one address")
+    }
+
+    fn send_through_middleware(&self, request: RequestBuilder) -> Result<Response> {
+        let mut request = request.build()?;
+        if self.middleware.is_empty() {
+            instrumentation::record_request(&request);
+            let response = self.transport.execute(request)?;
+            instrumentation::record_status(&response);
+            return Ok(response);
+        }
+        for middleware in &self.middleware {
+            middleware.on_request(&mut request)?;
+        }
+        instrumentation::record_request(&request);
+        let sent = request
+            .try_clone()
+            .ok_or_else(|| Error::HttpError("The request body cannot be cloned".to_owned()))?;
+        let mut response = self.transport.execute(request)?;
+        instrumentation::record_status(&response);
+        for middleware in self.middleware.iter().rev() {
+            response = middleware.on_response(&sent, response)?;
+        }
+        Ok(response)
+    }
+
+    // Switches back to the first address in order answering the echo request, if the active
+    // address is not the first one and the probe interval has elapsed since the last probe.
+    fn probe_preferred_addresses(&self) {
+        let active = self.active.load(Ordering::Relaxed);
+        if active == 0 {
+            return;
+        }
+        {
+            let mut last_probe = self.last_probe.lock().unwrap_or_else(|e| e.into_inner());
+            if last_probe.elapsed() < self.probe_interval {
+                return;
+            }
+            *last_probe = Instant::now();
+        }
+        if let Some(index) = self.probe(0..active) {
+            self.active.store(index, Ordering::Relaxed);
+        }
+    }
+
+    // Returns the index of the first address in the range answering the echo request.
+    fn probe(&self, mut indices: Range<usize>) -> Option<usize> {
+        indices.find(|index| {
+            self.addresses[*index]
+                .join("echo")
+                .ok()
+                .and_then(|url| self.transport.execute(Request::new(Method::GET, url)).ok())
+                .is_some_and(|response| response.status().is_success())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit_breaker::CircuitState;
+
+    fn echo(endpoints: &CoreSystemEndpoints) -> Result<Response> {
+        endpoints.send(|address| Ok(Client::new().get(address.join("echo")?)))
+    }
+
+    #[test]
+    fn fail_over_to_next_address() {
+        let mock = mockito::mock("GET", "/second/echo").expect(2).create();
+        let endpoints = CoreSystemEndpoints::new(&[
+            "http://127.0.0.1:1/",
+            &format!("{}/second/", mockito::server_url()),
+        ])
+        .unwrap();
+
+        assert!(matches!(echo(&endpoints), Ok(r) if r.status().is_success()));
+        assert!(endpoints.active_address().path() == "/second/");
+        assert!(matches!(echo(&endpoints), Ok(r) if r.status().is_success()));
+        mock.assert();
+    }
+
+    #[test]
+    fn fail_over_on_server_error() {
+        let first_mock = mockito::mock("GET", "/first/echo")
+            .with_status(503)
+            .create();
+        let second_mock = mockito::mock("GET", "/second/echo")
+            .with_status(500)
+            .create();
+        let endpoints = CoreSystemEndpoints::new(&[
+            &format!("{}/first/", mockito::server_url()),
+            &format!("{}/second/", mockito::server_url()),
+        ])
+        .unwrap();
+
+        assert!(matches!(echo(&endpoints), Ok(r) if r.status().as_u16() == 500));
+        assert!(endpoints.active_address().path() == "/first/");
+        first_mock.assert();
+        second_mock.assert();
+    }
+
+    #[test]
+    fn probe_preferred_address() {
+        let first_mock = mockito::mock("GET", "/probe-first/echo")
+            .with_status(503)
+            .create();
+        let second_mock = mockito::mock("GET", "/probe-second/echo").create();
+        let endpoints = CoreSystemEndpoints::new(&[
+            &format!("{}/probe-first/", mockito::server_url()),
+            &format!("{}/probe-second/", mockito::server_url()),
+        ])
+        .unwrap()
+        .with_probe_interval(Duration::ZERO);
+
+        assert!(echo(&endpoints).is_ok());
+        assert!(endpoints.active_address().path() == "/probe-second/");
+        first_mock.assert();
+        drop(first_mock);
+
+        let first_mock = mockito::mock("GET", "/probe-first/echo").expect(2).create();
+        assert!(echo(&endpoints).is_ok());
+        assert!(endpoints.active_address().path() == "/probe-first/");
+        first_mock.assert();
+        second_mock.assert();
+    }
+
+    #[test]
+    fn fail_fast_when_circuit_is_open() {
+        let endpoints = CoreSystemEndpoints::new(&["http://127.0.0.1:1/"])
+            .unwrap()
+            .with_circuit_breaker(CircuitBreakerConfig {
+                failure_threshold: 1,
+                cool_down: Duration::from_secs(60),
+            });
+
+        assert!(matches!(echo(&endpoints), Err(Error::HttpError(_))));
+        assert!(matches!(
+            endpoints.circuit_breaker_status(),
+            Some(status) if status.state == CircuitState::Open && status.consecutive_failures == 1
+        ));
+        assert!(matches!(echo(&endpoints), Err(Error::CircuitOpenError(_))));
+    }
+
+    #[test]
+    fn report_request_build_errors() {
+        let endpoints = CoreSystemEndpoints::new(&["http://127.0.0.1:1/"])
+            .unwrap()
+            .with_circuit_breaker(CircuitBreakerConfig {
+                failure_threshold: 1,
+                cool_down: Duration::from_secs(60),
+            });
+
+        let result = endpoints.send(|_| -> Result<RequestBuilder> {
+            Err(Error::HttpError("The request cannot be built".to_owned()))
+        });
+        assert!(matches!(result, Err(Error::HttpError(_))));
+        assert!(matches!(
+            endpoints.circuit_breaker_status(),
+            Some(status) if status.state == CircuitState::Open
+        ));
+    }
+
+    #[test]
+    fn at_least_one_address() {
+        assert!(matches!(
+            CoreSystemEndpoints::new(&[]),
+            Err(Error::ConfigError(_))
+        ));
+        assert!(matches!(
+            CoreSystemEndpoints::new(&["not a url"]),
+            Err(Error::ConfigError(_))
+        ));
+    }
+}
@@ -10,6 +10,14 @@ pub type Result<T> = result::Result<T, Error>;
 pub enum Error {
     HttpError(String),
     ArrowheadError(ArrowheadServerException),
+    ServerError(String),
+    InvocationError(String),
+    CircuitOpenError(String),
+    UnsupportedError(String),
+    ConfigError(String),
+    ValidationError(String),
+    UnexpectedResponseError(String),
+    TransportError(String),
 }
 
 impl fmt::Display for Error {
@@ -19,6 +27,33 @@ impl fmt::Display for Error {
             Self::ArrowheadError(ah_server_exception) => {
                 write!(f, "Arrowhead error: {}", ah_server_exception.error_message)
             }
+            Self::ServerError(message) => write!(f, "Server error: {}", message),
+            Self::InvocationError(message) => write!(f, "Invocation error: {}", message),
+            Self::CircuitOpenError(message) => write!(f, "Circuit open error: {}", message),
+            Self::UnsupportedError(message) => write!(f, "Unsupported error: {}", message),
+            Self::ConfigError(message) => write!(f, "Config error: {}", message),
+            Self::ValidationError(message) => write!(f, "Validation error: {}", message),
+            Self::UnexpectedResponseError(message) => {
+                write!(f, "Unexpected response error: {}", message)
+            }
+            Self::TransportError(message) => write!(f, "Transport error: {}", message),
+        }
+    }
+}
+
+impl Error {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::HttpError(_) => "http",
+            Self::ArrowheadError(_) => "arrowhead",
+            Self::ServerError(_) => "server",
+            Self::InvocationError(_) => "invocation",
+            Self::CircuitOpenError(_) => "circuit_open",
+            Self::UnsupportedError(_) => "unsupported",
+            Self::ConfigError(_) => "config",
+            Self::ValidationError(_) => "validation",
+            Self::UnexpectedResponseError(_) => "unexpected_response",
+            Self::TransportError(_) => "transport",
         }
     }
 }
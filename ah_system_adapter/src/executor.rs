@@ -0,0 +1,356 @@
+use crate::choreographer::{
+    ChoreographerAbortStepRequest, ChoreographerAdapter, ChoreographerExecuteStepRequest,
+    ChoreographerExecutedStepResult, ChoreographerExecutedStepStatus, ChoreographerExecutorEntry,
+    ChoreographerExecutorForm, ChoreographerExecutorServiceDefinition,
+};
+use crate::dtos::{ArrowheadSystem, NoEntryTag};
+use crate::error::{Error, Result};
+
+use serde::de::DeserializeOwned;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+pub trait ChoreographerStepHandler: Send + Sync + 'static {
+    fn start_step(&self, request: ChoreographerExecuteStepRequest) -> ChoreographerStepOutcome;
+    fn abort_step(&self, request: ChoreographerAbortStepRequest);
+}
+
+#[derive(PartialEq, Clone)]
+pub struct ChoreographerStepOutcome {
+    pub status: ChoreographerExecutedStepStatus,
+    pub message: Option<String>,
+    pub exception: Option<String>,
+}
+
+#[derive(PartialEq, Clone)]
+pub struct ChoreographerExecutorInput {
+    pub base_uri: String,
+    pub service_definition_name: String,
+    pub min_version: u32,
+    pub max_version: u32,
+}
+
+pub struct ChoreographerExecutorServer {
+    pub executor_entry: ChoreographerExecutorEntry,
+    choreographer: Arc<ChoreographerAdapter>,
+    executor_system: ArrowheadSystem<NoEntryTag>,
+    base_uri: String,
+    server: Arc<Server>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl ChoreographerExecutorServer {
+    pub fn start<H: ChoreographerStepHandler>(
+        choreographer: ChoreographerAdapter,
+        executor_system: ArrowheadSystem<NoEntryTag>,
+        input: ChoreographerExecutorInput,
+        bind_address: &str,
+        handler: H,
+    ) -> Result<Self> {
+        let server =
+            Arc::new(Server::http(bind_address).map_err(|e| Error::ServerError(format!("{}", e)))?);
+        let executor_entry = match choreographer.register_executor(&ChoreographerExecutorForm {
+            system: executor_system.clone(),
+            base_uri: input.base_uri.clone(),
+            service_definition_name: input.service_definition_name.clone(),
+            min_version: input.min_version,
+            max_version: input.max_version,
+        }) {
+            Ok(executor_entry) => executor_entry,
+            Err(err) => {
+                server.unblock();
+                return Err(err);
+            }
+        };
+
+        let choreographer = Arc::new(choreographer);
+        let worker = {
+            let server = server.clone();
+            let choreographer = choreographer.clone();
+            let handler = Arc::new(handler);
+            let service_info = ChoreographerExecutorServiceDefinition {
+                service_definition_name: input.service_definition_name,
+                min_version: input.min_version,
+                max_version: input.max_version,
+            };
+            let base_uri = input.base_uri.trim_end_matches('/').to_owned();
+            thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    Self::handle_request(
+                        request,
+                        &base_uri,
+                        &service_info,
+                        &choreographer,
+                        &handler,
+                    );
+                }
+            })
+        };
+
+        Ok(Self {
+            executor_entry,
+            choreographer,
+            executor_system,
+            base_uri: input.base_uri,
+            server,
+            worker: Some(worker),
+        })
+    }
+
+    pub fn port(&self) -> Option<u16> {
+        self.server.server_addr().to_ip().map(|a| a.port())
+    }
+
+    pub fn stop(mut self) -> Result<()> {
+        self.shutdown();
+        self.choreographer.unregister_executor(
+            &self.executor_system.address,
+            self.executor_system.port,
+            &self.base_uri,
+        )
+    }
+
+    fn shutdown(&mut self) {
+        self.server.unblock();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+
+    fn handle_request<H: ChoreographerStepHandler>(
+        mut request: Request,
+        base_uri: &str,
+        service_info: &ChoreographerExecutorServiceDefinition,
+        choreographer: &Arc<ChoreographerAdapter>,
+        handler: &Arc<H>,
+    ) {
+        let path = request.url().split('?').next().unwrap_or("").to_owned();
+        let route = path.strip_prefix(base_uri).unwrap_or("");
+        let status_code = match (request.method(), route) {
+            (Method::Post, "/start") => {
+                match Self::read_json::<ChoreographerExecuteStepRequest>(&mut request) {
+                    Some(step_request) => {
+                        let choreographer = choreographer.clone();
+                        let handler = handler.clone();
+                        thread::spawn(move || {
+                            let session_id = step_request.session_id;
+                            let session_step_id = step_request.session_step_id;
+                            let outcome = handler.start_step(step_request);
+                            // There is nobody to report a failed notification to, the
+                            // Choreographer times the step out on its own.
+                            let _ =
+                                choreographer.notify_step_done(&ChoreographerExecutedStepResult {
+                                    session_id,
+                                    session_step_id,
+                                    status: outcome.status,
+                                    message: outcome.message,
+                                    exception: outcome.exception,
+                                });
+                        });
+                        200
+                    }
+                    None => 400,
+                }
+            }
+            (Method::Post, "/abort") => {
+                match Self::read_json::<ChoreographerAbortStepRequest>(&mut request) {
+                    Some(abort_request) => {
+                        handler.abort_step(abort_request);
+                        200
+                    }
+                    None => 400,
+                }
+            }
+            (Method::Get, "/executor-info") => {
+                let body = serde_json::to_string(service_info).unwrap_or_default();
+                let content_type =
+                    Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+                        .expect("static header is valid");
+                let _ = request.respond(Response::from_string(body).with_header(content_type));
+                return;
+            }
+            _ => 404,
+        };
+        let _ = request.respond(Response::empty(status_code));
+    }
+
+    fn read_json<T: DeserializeOwned>(request: &mut Request) -> Option<T> {
+        serde_json::from_reader(request.as_reader()).ok()
+    }
+}
+
+impl Drop for ChoreographerExecutorServer {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dtos::{EntryTag, ServiceDefinitionEntry};
+    use crate::SecurityType;
+    use mockito::Matcher;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+
+    struct RecordingHandler {
+        aborted: Arc<Mutex<Vec<u32>>>,
+    }
+
+    impl ChoreographerStepHandler for RecordingHandler {
+        fn start_step(&self, request: ChoreographerExecuteStepRequest) -> ChoreographerStepOutcome {
+            ChoreographerStepOutcome {
+                status: ChoreographerExecutedStepStatus::Success,
+                message: Some(request.main_orchestration_result.service_uri),
+                exception: None,
+            }
+        }
+
+        fn abort_step(&self, request: ChoreographerAbortStepRequest) {
+            self.aborted.lock().unwrap().push(request.session_step_id);
+        }
+    }
+
+    #[test]
+    fn executor_lifecycle() {
+        let register_mock = mockito::mock("POST", "/executor")
+            .match_body(Matcher::Json(json!({
+                "system": {
+                  "systemName": "executor",
+                  "address": "127.0.0.1",
+                  "port": 0
+                },
+                "baseUri": "/exec",
+                "serviceDefinitionName": "move",
+                "minVersion": 1,
+                "maxVersion": 1
+            })))
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                  "id": 4,
+                  "name": "executor",
+                  "address": "127.0.0.1",
+                  "port": 0,
+                  "baseUri": "/exec",
+                  "createdAt": "string",
+                  "updatedAt": "string"
+                })
+                .to_string(),
+            )
+            .create();
+        let notify_mock = mockito::mock("POST", "/session/notifyStepDone")
+            .match_body(Matcher::Json(json!({
+                "sessionId": 1,
+                "sessionStepId": 2,
+                "status": "SUCCESS",
+                "message": "/move"
+            })))
+            .create();
+        let unregister_mock = mockito::mock("DELETE", "/executor")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("address".into(), "127.0.0.1".into()),
+                Matcher::UrlEncoded("port".into(), "0".into()),
+                Matcher::UrlEncoded("base_uri".into(), "/exec".into()),
+            ]))
+            .create();
+
+        let aborted = Arc::new(Mutex::new(Vec::new()));
+        let server = ChoreographerExecutorServer::start(
+            ChoreographerAdapter::new(&mockito::server_url()).unwrap(),
+            ArrowheadSystem {
+                entry_tag: NoEntryTag {},
+                system_name: "executor".to_owned(),
+                address: "127.0.0.1".to_owned(),
+                port: 0,
+                authentication_info: None,
+                metadata: None,
+            },
+            ChoreographerExecutorInput {
+                base_uri: "/exec".to_owned(),
+                service_definition_name: "move".to_owned(),
+                min_version: 1,
+                max_version: 1,
+            },
+            "127.0.0.1:0",
+            RecordingHandler {
+                aborted: aborted.clone(),
+            },
+        )
+        .unwrap();
+        assert_eq!(server.executor_entry.entry_tag.id, 4);
+        register_mock.assert();
+
+        let executor_url = format!("http://127.0.0.1:{}/exec", server.port().unwrap());
+        let client = reqwest::blocking::Client::new();
+        let start_request = ChoreographerExecuteStepRequest {
+            session_id: 1,
+            session_step_id: 2,
+            main_orchestration_result: crate::Orchestration {
+                provider: ArrowheadSystem {
+                    entry_tag: EntryTag {
+                        id: 0,
+                        created_at: "string".to_owned(),
+                        updated_at: "string".to_owned(),
+                    },
+                    system_name: "robot".to_owned(),
+                    address: "127.0.0.1".to_owned(),
+                    port: 8080,
+                    authentication_info: None,
+                    metadata: None,
+                },
+                service: ServiceDefinitionEntry::Value("move".to_owned()),
+                service_uri: "/move".to_owned(),
+                secure: SecurityType::NotSecure,
+                metadata: HashMap::new(),
+                interfaces: vec![],
+                version: 1,
+                authorization_tokens: None,
+                warnings: vec![],
+            },
+            precondition_orchestration_results: vec![],
+            quantity: None,
+            static_parameters: None,
+        };
+        let start_status = client
+            .post(format!("{}/start", executor_url))
+            .json(&start_request)
+            .send()
+            .unwrap()
+            .status();
+        let abort_status = client
+            .post(format!("{}/abort", executor_url))
+            .json(&ChoreographerAbortStepRequest {
+                session_id: 1,
+                session_step_id: 3,
+            })
+            .send()
+            .unwrap()
+            .status();
+        let info: ChoreographerExecutorServiceDefinition = client
+            .get(format!("{}/executor-info", executor_url))
+            .send()
+            .unwrap()
+            .json()
+            .unwrap();
+
+        assert!(start_status.is_success());
+        assert!(abort_status.is_success());
+        assert_eq!(*aborted.lock().unwrap(), vec![3]);
+        assert_eq!(info.service_definition_name, "move");
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !notify_mock.matched() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        notify_mock.assert();
+
+        assert!(server.stop().is_ok());
+        unregister_mock.assert();
+    }
+}
@@ -0,0 +1,296 @@
+use crate::dtos::{
+    ArrowheadCloud, ArrowheadCloudEntry, ArrowheadRelay, EntryTag, PageRequest, ServiceQueryForm,
+};
+use crate::error::{Error, Result};
+use crate::instrumentation::{self, SendObserved, GATEKEEPER};
+
+use reqwest::blocking::Client;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CloudWithRelays {
+    #[serde(flatten)]
+    pub cloud: ArrowheadCloudEntry,
+    #[serde(default)]
+    pub gatekeeper_relays: Vec<ArrowheadRelay<EntryTag>>,
+    #[serde(default)]
+    pub gateway_relays: Vec<ArrowheadRelay<EntryTag>>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CloudWithRelaysList {
+    pub data: Vec<CloudWithRelays>,
+    pub count: u32,
+}
+impl CloudWithRelaysList {
+    pub fn own_cloud(&self) -> Option<&CloudWithRelays> {
+        self.data.iter().find(|c| c.cloud.own_cloud)
+    }
+
+    pub fn neighbor_clouds(&self) -> impl Iterator<Item = &CloudWithRelays> {
+        self.data.iter().filter(|c| c.cloud.neighbor)
+    }
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GsdQueryForm {
+    pub requested_service: ServiceQueryForm,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preferred_clouds: Option<Vec<ArrowheadCloud>>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GsdPollResponse {
+    pub provider_cloud: ArrowheadCloudEntry,
+    pub required_service_definition: String,
+    pub available_interfaces: Vec<String>,
+    pub num_of_providers: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_metadata: Option<HashMap<String, String>>,
+    pub gateway_is_mandatory: bool,
+}
+impl GsdPollResponse {
+    // The cloud to be given as provider cloud in the preferred providers of an inter-cloud
+    // orchestration request.
+    pub fn cloud(&self) -> ArrowheadCloud {
+        ArrowheadCloud::from(&self.provider_cloud)
+    }
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GsdQueryResult {
+    pub results: Vec<GsdPollResponse>,
+    pub unsuccessful_requests: u32,
+}
+
+pub struct GatekeeperAdapter {
+    pub gatekeeper_address: Url,
+}
+
+impl GatekeeperAdapter {
+    pub fn new(gatekeeper_address: &str) -> Result<Self> {
+        Ok(GatekeeperAdapter {
+            gatekeeper_address: gatekeeper_address.try_into()?,
+        })
+    }
+
+    pub fn echo_gatekeeper(&self) -> Result<()> {
+        instrumentation::observe(GATEKEEPER, "echo_gatekeeper", || {
+            let client = Client::new();
+            client
+                .get(self.gatekeeper_address.join("echo")?)
+                .send_observed()?
+                .error_for_status()?;
+            Ok(())
+        })
+    }
+
+    pub fn get_clouds(&self, page_request: &PageRequest) -> Result<CloudWithRelaysList> {
+        instrumentation::observe(GATEKEEPER, "get_clouds", || {
+            let client = Client::new();
+            let mut url = self.gatekeeper_address.join("mgmt/clouds")?;
+            page_request.append_query_pairs(&mut url);
+            let response = client.get(url).send_observed()?;
+            if response.status().is_client_error() {
+                Err(Error::ArrowheadError(response.json()?))
+            } else {
+                Ok(response.json()?)
+            }
+        })
+    }
+
+    pub fn get_cloud(&self, id: u32) -> Result<CloudWithRelays> {
+        instrumentation::observe(GATEKEEPER, "get_cloud", || {
+            let client = Client::new();
+            let response = client
+                .get(
+                    self.gatekeeper_address
+                        .join(&format!("mgmt/clouds/{}", id))?,
+                )
+                .send_observed()?;
+            if response.status().is_client_error() {
+                Err(Error::ArrowheadError(response.json()?))
+            } else {
+                Ok(response.json()?)
+            }
+        })
+    }
+
+    // Global service discovery: polls the neighbor clouds (or the preferred ones) for providers
+    // of the requested service.
+    pub fn global_service_discovery(
+        &self,
+        gsd_query_form: &GsdQueryForm,
+    ) -> Result<GsdQueryResult> {
+        instrumentation::observe(GATEKEEPER, "global_service_discovery", || {
+            let client = Client::new();
+            let response = client
+                .post(self.gatekeeper_address.join("init_gsd")?)
+                .json(gsd_query_form)
+                .send_observed()?;
+            if response.status().is_client_error() {
+                Err(Error::ArrowheadError(response.json()?))
+            } else {
+                Ok(response.json()?)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dtos::{RelayType, ServiceRequirements};
+    use mockito::Matcher;
+    use serde_json::{json, Value};
+
+    fn cloud_json(id: u32, name: &str, own_cloud: bool) -> Value {
+        json!({
+          "id": id,
+          "operator": "aitia",
+          "name": name,
+          "secure": true,
+          "neighbor": !own_cloud,
+          "ownCloud": own_cloud,
+          "authenticationInfo": "string",
+          "createdAt": "string",
+          "updatedAt": "string"
+        })
+    }
+
+    fn adapter(prefix: &str) -> GatekeeperAdapter {
+        GatekeeperAdapter::new(&format!("{}/{}/", mockito::server_url(), prefix)).unwrap()
+    }
+
+    #[test]
+    fn echo_gatekeeper() {
+        let mock = mockito::mock("GET", "/gatekeeper/echo").create();
+
+        assert!(adapter("gatekeeper").echo_gatekeeper().is_ok());
+        mock.assert();
+    }
+
+    #[test]
+    fn get_clouds() {
+        let mut neighbor = cloud_json(2, "testcloud2", false);
+        neighbor["gatewayRelays"] = json!([{
+          "id": 1,
+          "address": "10.0.0.1",
+          "port": 8443,
+          "secure": true,
+          "exclusive": false,
+          "type": "GATEWAY_RELAY",
+          "createdAt": "string",
+          "updatedAt": "string"
+        }]);
+        let mock = mockito::mock("GET", "/clouds/mgmt/clouds")
+            .match_query(Matcher::UrlEncoded("page".into(), "0".into()))
+            .with_body(
+                json!({
+                  "data": [cloud_json(1, "testcloud1", true), neighbor],
+                  "count": 2
+                })
+                .to_string(),
+            )
+            .create();
+
+        let clouds = adapter("clouds")
+            .get_clouds(&PageRequest {
+                page: Some(0),
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(clouds
+            .own_cloud()
+            .is_some_and(|c| c.cloud.name == "testcloud1"));
+        let neighbors: Vec<_> = clouds.neighbor_clouds().collect();
+        assert!(neighbors.len() == 1 && neighbors[0].gatekeeper_relays.is_empty());
+        assert!(neighbors[0].gateway_relays[0].relay_type == RelayType::GatewayRelay);
+        mock.assert();
+    }
+
+    #[test]
+    fn get_cloud_arrowhead_error() {
+        let mock = mockito::mock("GET", "/cloud/mgmt/clouds/9")
+            .with_status(400)
+            .with_body(
+                json!({
+                  "errorMessage": "Cloud with id 9 not exists",
+                  "errorCode": 400,
+                  "exceptionType": "INVALID_PARAMETER",
+                  "origin": "/gatekeeper/mgmt/clouds/9"
+                })
+                .to_string(),
+            )
+            .create();
+
+        assert!(matches!(
+            adapter("cloud").get_cloud(9),
+            Err(Error::ArrowheadError(e)) if e.exception_type == "INVALID_PARAMETER"
+        ));
+        mock.assert();
+    }
+
+    #[test]
+    fn global_service_discovery() {
+        let mock = mockito::mock("POST", "/gsd/init_gsd")
+            .match_body(Matcher::Json(json!({
+              "requestedService": { "serviceDefinitionRequirement": "temperature" },
+              "preferredClouds": [{ "operator": "aitia", "name": "testcloud2" }]
+            })))
+            .with_body(
+                json!({
+                  "results": [{
+                    "providerCloud": cloud_json(2, "testcloud2", false),
+                    "requiredServiceDefinition": "temperature",
+                    "availableInterfaces": ["HTTP-SECURE-JSON"],
+                    "numOfProviders": 2,
+                    "serviceMetadata": { "unit": "celsius" },
+                    "gatewayIsMandatory": true
+                  }],
+                  "unsuccessfulRequests": 1
+                })
+                .to_string(),
+            )
+            .create();
+
+        let result = adapter("gsd")
+            .global_service_discovery(&GsdQueryForm {
+                requested_service: ServiceQueryForm {
+                    service_requirements: ServiceRequirements {
+                        service_definition_requirement: "temperature".to_owned(),
+                        interface_requirements: None,
+                        security_requirements: None,
+                        metadata_requirements: None,
+                        version_requirement: None,
+                        max_version_requirement: None,
+                        min_version_requirement: None,
+                    },
+                    ping_providers: None,
+                },
+                preferred_clouds: Some(vec![ArrowheadCloud {
+                    operator: "aitia".to_owned(),
+                    name: "testcloud2".to_owned(),
+                }]),
+            })
+            .unwrap();
+        assert!(result.unsuccessful_requests == 1);
+        assert!(result.results[0].gateway_is_mandatory);
+        assert!(
+            result.results[0].cloud()
+                == ArrowheadCloud {
+                    operator: "aitia".to_owned(),
+                    name: "testcloud2".to_owned(),
+                }
+        );
+        mock.assert();
+    }
+}
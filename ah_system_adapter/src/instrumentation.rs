@@ -0,0 +1,205 @@
+use crate::error::{Error, Result};
+
+use reqwest::blocking::{Request, RequestBuilder, Response};
+use reqwest::Url;
+use tracing::field;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+static BODY_LOGGING: AtomicBool = AtomicBool::new(false);
+
+pub(crate) const REDACTED: &str = "[redacted]";
+
+pub(crate) const SERVICE_REGISTRY: &str = "service_registry";
+pub(crate) const AUTHORIZATION: &str = "authorization";
+pub(crate) const ORCHESTRATOR: &str = "orchestrator";
+pub(crate) const CHOREOGRAPHER: &str = "choreographer";
+pub(crate) const QOS_MONITOR: &str = "qos_monitor";
+pub(crate) const GATEKEEPER: &str = "gatekeeper";
+pub(crate) const PLANT_DESCRIPTION_ENGINE: &str = "plant_description_engine";
+
+pub fn set_body_logging(enabled: bool) {
+    BODY_LOGGING.store(enabled, Ordering::Relaxed);
+}
+
+pub fn body_logging() -> bool {
+    BODY_LOGGING.load(Ordering::Relaxed)
+}
+
+// Runs a core system call inside a span named after the operation, and records the outcome of the
+// call on the span and in the metrics.
+pub(crate) fn observe<T, F>(
+    core_system: &'static str,
+    operation: &'static str,
+    call: F,
+) -> Result<T>
+where
+    F: FnOnce() -> Result<T>,
+{
+    let span = tracing::info_span!(
+        "arrowhead_call",
+        core_system,
+        operation,
+        url = field::Empty,
+        request_body = field::Empty,
+        status = field::Empty,
+        error_kind = field::Empty,
+        exception_type = field::Empty,
+        correlation_id = field::Empty,
+    );
+    let start = Instant::now();
+    let result = span.in_scope(call);
+    let elapsed = start.elapsed();
+
+    match &result {
+        Ok(_) => {
+            tracing::debug!(parent: &span, elapsed_ms = elapsed.as_millis() as u64, "call succeeded");
+        }
+        Err(err) => {
+            span.record("error_kind", err.kind());
+            if let Error::ArrowheadError(exception) = err {
+                span.record("exception_type", exception.exception_type.as_str());
+            }
+            tracing::warn!(parent: &span, elapsed_ms = elapsed.as_millis() as u64, error = %err, "call failed");
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    {
+        metrics::counter!(
+            "arrowhead_requests_total",
+            "core_system" => core_system,
+            "operation" => operation
+        )
+        .increment(1);
+        metrics::histogram!(
+            "arrowhead_request_duration_seconds",
+            "core_system" => core_system,
+            "operation" => operation
+        )
+        .record(elapsed.as_secs_f64());
+        if let Err(err) = &result {
+            metrics::counter!(
+                "arrowhead_errors_total",
+                "core_system" => core_system,
+                "operation" => operation,
+                "kind" => err.kind()
+            )
+            .increment(1);
+        }
+    }
+
+    result
+}
+
+pub(crate) trait SendObserved {
+    fn send_observed(self) -> Result<Response>;
+}
+
+impl SendObserved for RequestBuilder {
+    // Sends the request and records its URL, body and response status on the current span.
+    fn send_observed(self) -> Result<Response> {
+        if let Some(Ok(request)) = self.try_clone().map(RequestBuilder::build) {
+            record_request(&request);
+        }
+        let response = self.send()?;
+        record_status(&response);
+        Ok(response)
+    }
+}
+
+// Records the URL and the body of the request on the current span.
+pub(crate) fn record_request(request: &Request) {
+    let span = tracing::Span::current();
+    span.record("url", redact_url(request.url()).as_str());
+    if let Some(body) = request.body().and_then(|b| b.as_bytes()) {
+        if body_logging() {
+            span.record("request_body", String::from_utf8_lossy(body).as_ref());
+        } else {
+            span.record("request_body", REDACTED);
+        }
+    }
+}
+
+pub(crate) fn record_status(response: &Response) {
+    tracing::Span::current().record("status", response.status().as_u16());
+}
+
+// Tokens may be passed as query parameters, so their values are never recorded.
+pub(crate) fn redact_url(url: &Url) -> String {
+    if !url.query_pairs().any(|(key, _)| key == "token") {
+        return url.to_string();
+    }
+    let mut redacted = url.clone();
+    let query_pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(key, value)| {
+            let value = if key == "token" {
+                REDACTED.to_owned()
+            } else {
+                value.into_owned()
+            };
+            (key.into_owned(), value)
+        })
+        .collect();
+    redacted.query_pairs_mut().clear().extend_pairs(query_pairs);
+    redacted.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redact_token() {
+        let url =
+            Url::parse("http://127.0.0.1:8080/temperature?unit=celsius&token=secret").unwrap();
+
+        assert_eq!(
+            redact_url(&url),
+            "http://127.0.0.1:8080/temperature?unit=celsius&token=%5Bredacted%5D"
+        );
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn record_metrics() {
+        use crate::{ArrowheadSystem, ArrowheadSystemAdapter, NoEntryTag};
+        use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+        let mock = mockito::mock("GET", "/metrics/echo")
+            .with_status(503)
+            .create();
+        let adapter = ArrowheadSystemAdapter::new(
+            &format!("{}/metrics/", mockito::server_url()),
+            "http://dontcare",
+            "http://dontcare",
+            ArrowheadSystem {
+                entry_tag: NoEntryTag {},
+                system_name: "string".to_owned(),
+                address: "string".to_owned(),
+                port: 0,
+                authentication_info: None,
+                metadata: None,
+            },
+        )
+        .unwrap();
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        metrics::with_local_recorder(&recorder, || {
+            assert!(adapter.echo_service_registry().is_err());
+        });
+
+        let snapshot = snapshotter.snapshot().into_vec();
+        let counter = |name: &str| {
+            snapshot.iter().find_map(|(key, _, _, value)| match value {
+                DebugValue::Counter(count) if key.key().name() == name => Some(*count),
+                _ => None,
+            })
+        };
+        assert_eq!(counter("arrowhead_requests_total"), Some(1));
+        assert_eq!(counter("arrowhead_errors_total"), Some(1));
+        mock.assert();
+    }
+}
@@ -0,0 +1,315 @@
+use crate::dtos::{Orchestration, OrchestrationResponse, SecurityType};
+use crate::error::{Error, Result};
+
+use reqwest::blocking::Client;
+use reqwest::{Method, Url};
+use serde::{de::DeserializeOwned, Serialize};
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum InvocationMethod {
+    Get,
+    Post,
+    Put,
+    Delete,
+}
+impl From<InvocationMethod> for Method {
+    fn from(method: InvocationMethod) -> Self {
+        match method {
+            InvocationMethod::Get => Method::GET,
+            InvocationMethod::Post => Method::POST,
+            InvocationMethod::Put => Method::PUT,
+            InvocationMethod::Delete => Method::DELETE,
+        }
+    }
+}
+
+impl Orchestration {
+    pub fn select_interface(&self, preferred_interface: Option<&str>) -> Option<&str> {
+        let mut interface_names = self.interfaces.iter().map(|i| i.get_interface_name());
+        match preferred_interface {
+            Some(preferred) => interface_names.find(|i| i.eq_ignore_ascii_case(preferred)),
+            None => interface_names.next(),
+        }
+    }
+
+    pub fn service_url(&self, preferred_interface: Option<&str>) -> Result<Url> {
+        let interface_name = self.select_interface(preferred_interface);
+        if let (Some(preferred), None) = (preferred_interface, interface_name) {
+            return Err(Error::InvocationError(format!(
+                "Provider '{}' does not support interface '{}'",
+                self.provider.system_name, preferred
+            )));
+        }
+
+        let interface_parts: Vec<String> = interface_name
+            .map(|i| {
+                i.to_ascii_uppercase()
+                    .split('-')
+                    .map(str::to_owned)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let secure = if interface_parts.iter().any(|p| p == "INSECURE") {
+            false
+        } else if interface_parts.iter().any(|p| p == "SECURE") {
+            true
+        } else {
+            self.secure != SecurityType::NotSecure
+        };
+        let scheme = if secure { "https" } else { "http" };
+
+        let mut url: Url = format!(
+            "{}://{}:{}",
+            scheme, self.provider.address, self.provider.port
+        )
+        .as_str()
+        .try_into()?;
+        url = url.join(&format!("/{}", self.service_uri.trim_start_matches('/')))?;
+
+        let token = interface_name.and_then(|i| {
+            self.authorization_tokens
+                .as_ref()
+                .and_then(|tokens| tokens.get(i))
+        });
+        if let Some(token) = token {
+            url.query_pairs_mut().append_pair("token", token);
+        }
+
+        Ok(url)
+    }
+}
+
+pub struct ServiceInvoker {
+    orchestrations: Vec<Orchestration>,
+    preferred_interface: Option<String>,
+    client: Client,
+}
+
+impl ServiceInvoker {
+    pub fn new(orchestrations: Vec<Orchestration>) -> Self {
+        Self {
+            orchestrations,
+            preferred_interface: None,
+            client: Client::new(),
+        }
+    }
+
+    pub fn from_response(orchestration_response: OrchestrationResponse) -> Self {
+        Self::new(orchestration_response.response)
+    }
+
+    pub fn with_preferred_interface(mut self, interface_name: &str) -> Self {
+        self.preferred_interface = Some(interface_name.to_owned());
+        self
+    }
+
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    pub fn orchestrations(&self) -> &[Orchestration] {
+        &self.orchestrations
+    }
+
+    pub fn get<R: DeserializeOwned>(&self) -> Result<R> {
+        self.invoke::<(), R>(InvocationMethod::Get, None)
+    }
+
+    pub fn post<B: Serialize, R: DeserializeOwned>(&self, body: &B) -> Result<R> {
+        self.invoke(InvocationMethod::Post, Some(body))
+    }
+
+    pub fn put<B: Serialize, R: DeserializeOwned>(&self, body: &B) -> Result<R> {
+        self.invoke(InvocationMethod::Put, Some(body))
+    }
+
+    pub fn delete<R: DeserializeOwned>(&self) -> Result<R> {
+        self.invoke::<(), R>(InvocationMethod::Delete, None)
+    }
+
+    pub fn invoke<B: Serialize, R: DeserializeOwned>(
+        &self,
+        method: InvocationMethod,
+        body: Option<&B>,
+    ) -> Result<R> {
+        let mut last_error = None;
+        for orchestration in &self.orchestrations {
+            match self.invoke_orchestration(orchestration, method, body) {
+                Ok(result) => return Ok(result),
+                Err(err) => last_error = Some(err),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            Error::InvocationError("There is no orchestration result to invoke".to_owned())
+        }))
+    }
+
+    pub fn invoke_orchestration<B: Serialize, R: DeserializeOwned>(
+        &self,
+        orchestration: &Orchestration,
+        method: InvocationMethod,
+        body: Option<&B>,
+    ) -> Result<R> {
+        let url = orchestration.service_url(self.preferred_interface.as_deref())?;
+        let mut request = self.client.request(method.into(), url);
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        let response = request.send()?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(Error::InvocationError(format!(
+                "Provider '{}' responded with status {}",
+                orchestration.provider.system_name, status
+            )));
+        }
+        let text = response.text()?;
+        let text = if text.trim().is_empty() {
+            "null"
+        } else {
+            &text
+        };
+        serde_json::from_str(text).map_err(|e| {
+            Error::InvocationError(format!(
+                "Invalid response from provider '{}': {}",
+                orchestration.provider.system_name, e
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dtos::{ArrowheadSystem, EntryTag, InterfaceEntry, ServiceDefinitionEntry};
+    use mockito::Matcher;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn orchestration(address: &str, port: u32, secure: SecurityType) -> Orchestration {
+        Orchestration {
+            provider: ArrowheadSystem {
+                entry_tag: EntryTag {
+                    id: 0,
+                    created_at: "string".to_owned(),
+                    updated_at: "string".to_owned(),
+                },
+                system_name: "provider".to_owned(),
+                address: address.to_owned(),
+                port,
+                authentication_info: None,
+                metadata: None,
+            },
+            service: ServiceDefinitionEntry::Value("temperature".to_owned()),
+            service_uri: "temperature".to_owned(),
+            secure,
+            metadata: HashMap::new(),
+            interfaces: vec![
+                InterfaceEntry::Value("HTTP-SECURE-JSON".to_owned()),
+                InterfaceEntry::Value("HTTP-INSECURE-JSON".to_owned()),
+            ],
+            version: 1,
+            authorization_tokens: Some(HashMap::from([
+                ("HTTP-SECURE-JSON".to_owned(), "secure-token".to_owned()),
+                ("HTTP-INSECURE-JSON".to_owned(), "insecure-token".to_owned()),
+            ])),
+            warnings: vec![],
+        }
+    }
+
+    fn mockito_orchestration() -> Orchestration {
+        let address = mockito::server_address();
+        orchestration(
+            &address.ip().to_string(),
+            address.port().into(),
+            SecurityType::Token,
+        )
+    }
+
+    #[test]
+    fn service_url() {
+        let orchestration = orchestration("10.0.0.1", 8443, SecurityType::Token);
+
+        assert_eq!(
+            orchestration.service_url(None).unwrap().as_str(),
+            "https://10.0.0.1:8443/temperature?token=secure-token"
+        );
+        assert_eq!(
+            orchestration
+                .service_url(Some("http-insecure-json"))
+                .unwrap()
+                .as_str(),
+            "http://10.0.0.1:8443/temperature?token=insecure-token"
+        );
+        assert!(matches!(
+            orchestration.service_url(Some("COAP-INSECURE-JSON")),
+            Err(Error::InvocationError(_))
+        ));
+    }
+
+    #[test]
+    fn post_with_token() {
+        let mock = mockito::mock("POST", "/temperature")
+            .match_query(Matcher::UrlEncoded("token".into(), "insecure-token".into()))
+            .match_body(Matcher::Json(json!({ "unit": "celsius" })))
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "value": 21.5 }).to_string())
+            .create();
+        let invoker = ServiceInvoker::new(vec![mockito_orchestration()])
+            .with_preferred_interface("HTTP-INSECURE-JSON");
+        let result: Result<HashMap<String, f64>> = invoker.post(&json!({ "unit": "celsius" }));
+
+        assert!(matches!(result, Ok(body) if body["value"] == 21.5));
+        mock.assert();
+    }
+
+    #[test]
+    fn delete_with_empty_response() {
+        let mock = mockito::mock("DELETE", "/temperature")
+            .match_query(Matcher::Any)
+            .create();
+        let invoker = ServiceInvoker::new(vec![mockito_orchestration()])
+            .with_preferred_interface("HTTP-INSECURE-JSON");
+        let result: Result<()> = invoker.delete();
+
+        assert!(result.is_ok());
+        mock.assert();
+    }
+
+    #[test]
+    fn fall_back_to_next_orchestration() {
+        let mock = mockito::mock("GET", "/temperature")
+            .match_query(Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body("21")
+            .create();
+        let invoker = ServiceInvoker::new(vec![
+            orchestration("127.0.0.1", 1, SecurityType::NotSecure),
+            mockito_orchestration(),
+        ])
+        .with_preferred_interface("HTTP-INSECURE-JSON");
+        let result: Result<u32> = invoker.get();
+
+        assert!(matches!(result, Ok(21)));
+        mock.assert();
+    }
+
+    #[test]
+    fn every_orchestration_fails() {
+        let mock = mockito::mock("GET", "/temperature")
+            .match_query(Matcher::Any)
+            .with_status(500)
+            .create();
+        let invoker = ServiceInvoker::new(vec![mockito_orchestration()])
+            .with_preferred_interface("HTTP-INSECURE-JSON");
+        let result: Result<u32> = invoker.get();
+
+        assert!(matches!(result, Err(Error::InvocationError(_))));
+        assert!(matches!(
+            ServiceInvoker::new(vec![]).get::<u32>(),
+            Err(Error::InvocationError(_))
+        ));
+        mock.assert();
+    }
+}
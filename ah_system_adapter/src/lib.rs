@@ -1,23 +1,124 @@
+#[cfg(feature = "provider")]
+mod access_token;
+mod choreographer;
+mod circuit_breaker;
+#[cfg(feature = "coap")]
+mod coap;
+mod config;
+#[cfg(test)]
+mod contract_tests;
+mod core_system;
 mod dtos;
+mod endpoints;
 mod error;
+#[cfg(feature = "executor")]
+mod executor;
+mod gatekeeper;
+mod instrumentation;
+mod invoker;
+mod middleware;
+#[cfg(any(test, feature = "mock-core"))]
+mod mock_core;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+mod naming;
+mod orchestration_cache;
+mod plant_description;
+#[cfg(feature = "provider")]
+mod provider;
+mod provider_selection;
+mod qos_monitor;
+mod random;
+#[cfg(all(test, any(feature = "provider", feature = "ssl")))]
+mod test_certificates;
+mod transport;
 
+pub use crate::choreographer::{
+    ChoreographerAbortStepRequest, ChoreographerAction, ChoreographerAdapter,
+    ChoreographerExecuteStepRequest, ChoreographerExecutedStepResult,
+    ChoreographerExecutedStepStatus, ChoreographerExecutorEntry, ChoreographerExecutorForm,
+    ChoreographerExecutorServiceDefinition, ChoreographerPlan, ChoreographerPlanList,
+    ChoreographerRunPlanRequest, ChoreographerRunPlanResponse, ChoreographerSession,
+    ChoreographerSessionList, ChoreographerSessionStatus, ChoreographerStep,
+};
+pub use crate::circuit_breaker::{CircuitBreakerConfig, CircuitBreakerStatus, CircuitState};
+#[cfg(feature = "coap")]
+pub use crate::coap::{CoapConfig, CoapTransport};
+pub use crate::config::AdapterConfig;
+pub use crate::core_system::{ArrowheadCore, Authorization, Orchestrator, ServiceRegistry};
 pub use crate::dtos::{
-    ArrowheadCloud, ArrowheadProvider, ArrowheadServerException, ArrowheadService, ArrowheadSystem,
-    EntryTag, InterfaceEntry, NoEntryTag, Orchestration, OrchestrationFlagKey,
-    OrchestrationResponse, OrchestrationWarning, RegisterServiceInput, RequestOrchestrationInput,
-    SecurityType, ServiceDefinitionEntry, ServiceQueryForm, ServiceQueryList, ServiceRequestForm,
-    ServiceRequirements,
+    ArrowheadCloud, ArrowheadCloudEntry, ArrowheadProvider, ArrowheadRelay,
+    ArrowheadServerException, ArrowheadService, ArrowheadSystem, ArrowheadVersion, EntryTag,
+    InterfaceEntry, NoEntryTag, Orchestration, OrchestrationFlagKey, OrchestrationResponse,
+    OrchestrationWarning, PageRequest, RegisterServiceInput, RelayType, RequestOrchestrationInput,
+    SecurityType, ServiceDefinitionEntry, ServiceQueryForm, ServiceQueryFormList, ServiceQueryList,
+    ServiceQueryResultList, ServiceRequestForm, ServiceRequirements, SortDirection,
 };
+pub use crate::endpoints::CoreSystemEndpoints;
 pub use crate::error::{Error, Result};
+#[cfg(feature = "executor")]
+pub use crate::executor::{
+    ChoreographerExecutorInput, ChoreographerExecutorServer, ChoreographerStepHandler,
+    ChoreographerStepOutcome,
+};
+pub use crate::gatekeeper::{
+    CloudWithRelays, CloudWithRelaysList, GatekeeperAdapter, GsdPollResponse, GsdQueryForm,
+    GsdQueryResult,
+};
+pub use crate::instrumentation::{body_logging, set_body_logging};
+pub use crate::invoker::{InvocationMethod, ServiceInvoker};
+pub use crate::middleware::{
+    AuthHeader, CorrelationId, Middleware, RequestLogging, CORRELATION_ID_HEADER,
+};
+#[cfg(any(test, feature = "mock-core"))]
+pub use crate::mock_core::{MockCore, MockStoreRule};
+#[cfg(feature = "mqtt")]
+pub use crate::mqtt::{MqttConfig, MqttRequest, MqttResponse, MqttTransport};
+pub use crate::naming::{
+    validate_metadata_key, validate_service_definition, validate_service_uri, validate_system_name,
+    MAX_METADATA_KEY_LENGTH, MAX_NAME_LENGTH, MAX_SERVICE_URI_LENGTH,
+};
+pub use crate::orchestration_cache::{
+    OrchestrationCache, OrchestrationCacheConfig, OrchestrationCacheStats,
+};
+pub use crate::plant_description::{
+    PlantAlarm, PlantAlarmList, PlantAlarmSeverity, PlantConnection, PlantConnectionEndpoint,
+    PlantDescription, PlantDescriptionEngineAdapter, PlantDescriptionList,
+    PlantDescriptionMismatch, PlantMonitorInfo, PlantSystem, PlantSystemPort,
+};
+#[cfg(feature = "provider")]
+pub use crate::provider::{
+    ProvidedService, ProviderRequest, ProviderResponse, ProviderSecurity, ProviderServer,
+    ServiceHandler,
+};
+pub use crate::provider_selection::{
+    HealthPolicy, ProviderHealth, ProviderSelector, SelectionStrategy,
+};
+pub use crate::qos_monitor::{
+    CloudRelayForm, CloudSystemForm, QosInterDirectMeasurement, QosInterDirectPingMeasurement,
+    QosInterDirectPingMeasurementList, QosInterRelayEchoMeasurement,
+    QosInterRelayEchoMeasurementList, QosInterRelayMeasurement, QosIntraMeasurement,
+    QosIntraPingMeasurement, QosIntraPingMeasurementList, QosMeasurementStatus, QosMeasurementType,
+    QosMonitorAdapter, QosPingStatistics,
+};
+pub use crate::transport::Transport;
+
+use crate::instrumentation::{AUTHORIZATION, ORCHESTRATOR, SERVICE_REGISTRY};
 
 use reqwest::blocking::Client;
-use reqwest::Url;
+use reqwest::{StatusCode, Url};
+
+use std::panic;
+use std::sync::Arc;
+use std::thread;
 
 pub struct ArrowheadSystemAdapter {
-    pub service_registry_address: Url,
-    pub authorization_address: Url,
-    pub orchestrator_address: Url,
+    pub service_registry: CoreSystemEndpoints,
+    pub authorization: CoreSystemEndpoints,
+    pub orchestrator: CoreSystemEndpoints,
     pub client_system: ArrowheadSystem<NoEntryTag>,
+    pub version: ArrowheadVersion,
+    pub naming_validation: bool,
 }
 
 impl ArrowheadSystemAdapter {
@@ -27,126 +128,405 @@ impl ArrowheadSystemAdapter {
         orchestrator_address: &str,
         client_system: ArrowheadSystem<NoEntryTag>,
     ) -> Result<Self> {
-        Ok(ArrowheadSystemAdapter {
-            service_registry_address: service_registry_address.try_into()?,
-            authorization_address: authorization_address.try_into()?,
-            orchestrator_address: orchestrator_address.try_into()?,
+        Ok(Self::with_endpoints(
+            CoreSystemEndpoints::new(&[service_registry_address])?,
+            CoreSystemEndpoints::new(&[authorization_address])?,
+            CoreSystemEndpoints::new(&[orchestrator_address])?,
             client_system,
-        })
+        ))
     }
+
+    pub fn with_endpoints(
+        service_registry: CoreSystemEndpoints,
+        authorization: CoreSystemEndpoints,
+        orchestrator: CoreSystemEndpoints,
+        client_system: ArrowheadSystem<NoEntryTag>,
+    ) -> Self {
+        ArrowheadSystemAdapter {
+            service_registry,
+            authorization,
+            orchestrator,
+            client_system,
+            version: ArrowheadVersion::default(),
+            naming_validation: true,
+        }
+    }
+
+    pub fn with_version(mut self, version: ArrowheadVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    // The names in the registered services and in the orchestration requests are checked against
+    // the naming conventions of the core systems before sending, unless disabled.
+    pub fn with_naming_validation(mut self, enabled: bool) -> Self {
+        self.naming_validation = enabled;
+        self
+    }
+
+    // Sends the requests to every core system with the given client.
+    pub fn with_client(self, client: Client) -> Self {
+        self.with_transport(client)
+    }
+
+    // Carries the requests to every core system over the given transport, e.g. MQTT or CoAP.
+    pub fn with_transport<T: Transport + 'static>(self, transport: T) -> Self {
+        let transport: Arc<dyn Transport> = Arc::new(transport);
+        ArrowheadSystemAdapter {
+            service_registry: self
+                .service_registry
+                .with_shared_transport(transport.clone()),
+            authorization: self.authorization.with_shared_transport(transport.clone()),
+            orchestrator: self.orchestrator.with_shared_transport(transport),
+            ..self
+        }
+    }
+
+    pub fn with_circuit_breaker(self, config: CircuitBreakerConfig) -> Self {
+        ArrowheadSystemAdapter {
+            service_registry: self.service_registry.with_circuit_breaker(config.clone()),
+            authorization: self.authorization.with_circuit_breaker(config.clone()),
+            orchestrator: self.orchestrator.with_circuit_breaker(config),
+            ..self
+        }
+    }
+
+    // Adds a middleware seeing the requests sent to every core system and their responses.
+    pub fn with_middleware<M: Middleware + 'static>(self, middleware: M) -> Self {
+        let middleware: Arc<dyn Middleware> = Arc::new(middleware);
+        ArrowheadSystemAdapter {
+            service_registry: self
+                .service_registry
+                .with_shared_middleware(middleware.clone()),
+            authorization: self
+                .authorization
+                .with_shared_middleware(middleware.clone()),
+            orchestrator: self.orchestrator.with_shared_middleware(middleware),
+            ..self
+        }
+    }
+    // The address fields were replaced by the endpoints of the core systems. These return the
+    // active address of each core system.
+    #[deprecated(note = "use `service_registry.active_address()` instead")]
+    pub fn service_registry_address(&self) -> &Url {
+        self.service_registry.active_address()
+    }
+
+    #[deprecated(note = "use `authorization.active_address()` instead")]
+    pub fn authorization_address(&self) -> &Url {
+        self.authorization.active_address()
+    }
+
+    #[deprecated(note = "use `orchestrator.active_address()` instead")]
+    pub fn orchestrator_address(&self) -> &Url {
+        self.orchestrator.active_address()
+    }
+
     pub fn echo_service_registry(&self) -> Result<()> {
-        let client = Client::new();
-        client
-            .get(self.service_registry_address.join("echo")?)
-            .send()?
-            .error_for_status()?;
-        Ok(())
+        instrumentation::observe(SERVICE_REGISTRY, "echo_service_registry", || {
+            self.service_registry
+                .send(|address| Ok(Client::new().get(address.join("echo")?)))?
+                .error_for_status()?;
+            Ok(())
+        })
     }
 
     pub fn query_service(&self, service_query_form: &ServiceQueryForm) -> Result<ServiceQueryList> {
-        let client = Client::new();
-        let response = client
-            .post(self.service_registry_address.join("query")?)
-            .json(&service_query_form)
-            .send()?;
-        if response.status().is_client_error() {
-            Err(Error::ArrowheadError(response.json()?))
-        } else {
-            Ok(response.json()?)
+        instrumentation::observe(SERVICE_REGISTRY, "query_service", || {
+            let response = self.service_registry.send(|address| {
+                Ok(Client::new()
+                    .post(address.join("query")?)
+                    .json(&service_query_form))
+            })?;
+            if response.status().is_client_error() {
+                Err(Error::ArrowheadError(response.json()?))
+            } else {
+                Ok(response.json()?)
+            }
+        })
+    }
+
+    pub fn query_service_multi(
+        &self,
+        service_query_form_list: &ServiceQueryFormList,
+    ) -> Result<ServiceQueryResultList> {
+        if !self.version.supports_multi_query() {
+            return Err(Error::UnsupportedError(format!(
+                "The query/multi endpoint is not supported by Arrowhead {:?}",
+                self.version
+            )));
         }
+        instrumentation::observe(SERVICE_REGISTRY, "query_service_multi", || {
+            let response = self.service_registry.send(|address| {
+                Ok(Client::new()
+                    .post(address.join("query/multi")?)
+                    .json(&service_query_form_list))
+            })?;
+            if response.status().is_client_error() {
+                Err(Error::ArrowheadError(response.json()?))
+            } else {
+                Ok(response.json()?)
+            }
+        })
+    }
+
+    // Returns the results keyed by the service definition of their query form, in the order of
+    // the query forms. The queries are sent in one query/multi request when the Service Registry
+    // provides it, as parallel queries otherwise.
+    pub fn query_services(
+        &self,
+        service_query_forms: &[ServiceQueryForm],
+    ) -> Result<Vec<(String, ServiceQueryList)>> {
+        let results = if self.version.supports_multi_query() {
+            self.query_service_multi_if_available(service_query_forms)?
+        } else {
+            None
+        };
+        let results = match results {
+            Some(results) => results,
+            None => thread::scope(|scope| {
+                let queries: Vec<_> = service_query_forms
+                    .iter()
+                    .map(|form| scope.spawn(move || self.query_service(form)))
+                    .collect();
+                queries
+                    .into_iter()
+                    .map(|query| query.join().unwrap_or_else(|err| panic::resume_unwind(err)))
+                    .collect::<Result<Vec<_>>>()
+            })?,
+        };
+        Ok(service_query_forms
+            .iter()
+            .map(|form| {
+                form.service_requirements
+                    .service_definition_requirement
+                    .clone()
+            })
+            .zip(results)
+            .collect())
+    }
+
+    // Returns None if the Service Registry does not provide the query/multi endpoint.
+    fn query_service_multi_if_available(
+        &self,
+        service_query_forms: &[ServiceQueryForm],
+    ) -> Result<Option<Vec<ServiceQueryList>>> {
+        instrumentation::observe(SERVICE_REGISTRY, "query_service_multi", || {
+            let service_query_form_list = ServiceQueryFormList {
+                forms: service_query_forms.to_vec(),
+            };
+            let response = self.service_registry.send(|address| {
+                Ok(Client::new()
+                    .post(address.join("query/multi")?)
+                    .json(&service_query_form_list))
+            })?;
+            match response.status() {
+                StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED => Ok(None),
+                status if status.is_client_error() => Err(Error::ArrowheadError(response.json()?)),
+                _ => {
+                    let results = response.json::<ServiceQueryResultList>()?.results;
+                    if results.len() != service_query_forms.len() {
+                        return Err(Error::UnexpectedResponseError(format!(
+                            "The query/multi response has {} results for {} query forms",
+                            results.len(),
+                            service_query_forms.len()
+                        )));
+                    }
+                    // The results are paired with the forms by position, so a result holding
+                    // services of another definition means the order was not kept.
+                    for (form, result) in service_query_forms.iter().zip(&results) {
+                        let requirement = form
+                            .service_requirements
+                            .service_definition_requirement
+                            .trim();
+                        if let Some(service) = result.service_query_data.iter().find(|service| {
+                            !service
+                                .service_definition
+                                .get_service_definition()
+                                .eq_ignore_ascii_case(requirement)
+                        }) {
+                            return Err(Error::UnexpectedResponseError(format!(
+                                "The query/multi response has a '{}' service in the result of '{}'",
+                                service.service_definition.get_service_definition(),
+                                requirement
+                            )));
+                        }
+                    }
+                    Ok(Some(results))
+                }
+            }
+        })
     }
 
     pub fn register_service(
         &self,
         input: RegisterServiceInput,
     ) -> Result<ArrowheadService<EntryTag>> {
-        let service = input.to_arrowhead_service(self.client_system.clone());
-        let client = Client::new();
-        let response = client
-            .post(self.service_registry_address.join("register")?)
-            .json(&service)
-            .send()?;
-        if response.status().is_client_error() {
-            Err(Error::ArrowheadError(response.json()?))
-        } else {
-            Ok(response.json()?)
-        }
+        instrumentation::observe(SERVICE_REGISTRY, "register_service", || {
+            let service = input.to_arrowhead_service(self.client_system.for_version(self.version));
+            if self.naming_validation {
+                naming::validate_service(&service)?;
+            }
+            let response = self
+                .service_registry
+                .send(|address| Ok(Client::new().post(address.join("register")?).json(&service)))?;
+            if response.status().is_client_error() {
+                Err(Error::ArrowheadError(response.json()?))
+            } else {
+                Ok(response.json()?)
+            }
+        })
     }
 
     pub fn unregister_service(&self, service_definition: &str) -> Result<()> {
-        let client = Client::new();
-        let mut url = self.service_registry_address.join("unregister")?;
-        url.query_pairs_mut().extend_pairs(&[
-            ("service_definition", service_definition),
-            ("system_name", &self.client_system.system_name),
-            ("address", &self.client_system.address),
-            ("port", &self.client_system.port.to_string()),
-        ]);
-        let response = client.delete(url).send()?;
-        if response.status().is_client_error() {
-            Err(Error::ArrowheadError(response.json()?))
-        } else {
-            Ok(())
-        }
+        self.unregister(
+            "unregister_service",
+            service_definition,
+            None,
+            &self.client_system,
+        )
+    }
+
+    // Unregisters only the entry with the given service URI, keeping the other entries of the
+    // client system with the same service definition.
+    pub fn unregister_service_uri(
+        &self,
+        service_definition: &str,
+        service_uri: &str,
+    ) -> Result<()> {
+        self.unregister(
+            "unregister_service_uri",
+            service_definition,
+            Some(service_uri),
+            &self.client_system,
+        )
+    }
+
+    // Unregisters exactly the given entry returned by register_service or query_service.
+    pub fn unregister_service_entry(&self, service: &ArrowheadService<EntryTag>) -> Result<()> {
+        self.unregister(
+            "unregister_service_entry",
+            service.service_definition.get_service_definition(),
+            Some(&service.service_uri),
+            &service.provider_system,
+        )
+    }
+
+    // Deletes the entry with the given id through the management interface of the Service
+    // Registry, which is restricted to the system operator in secure mode.
+    pub fn delete_service_by_id(&self, id: u32) -> Result<()> {
+        instrumentation::observe(SERVICE_REGISTRY, "delete_service_by_id", || {
+            let response = self
+                .service_registry
+                .send(|address| Ok(Client::new().delete(address.join(&format!("mgmt/{}", id))?)))?;
+            if response.status().is_client_error() {
+                Err(Error::ArrowheadError(response.json()?))
+            } else {
+                Ok(())
+            }
+        })
+    }
+
+    fn unregister<T>(
+        &self,
+        operation: &'static str,
+        service_definition: &str,
+        service_uri: Option<&str>,
+        provider_system: &ArrowheadSystem<T>,
+    ) -> Result<()> {
+        instrumentation::observe(SERVICE_REGISTRY, operation, || {
+            let response = self.service_registry.send(|address| {
+                let mut url = address.join("unregister")?;
+                url.query_pairs_mut().extend_pairs(&[
+                    ("service_definition", service_definition),
+                    ("system_name", &provider_system.system_name),
+                    ("address", &provider_system.address),
+                    ("port", &provider_system.port.to_string()),
+                ]);
+                if let Some(service_uri) = service_uri {
+                    url.query_pairs_mut()
+                        .append_pair("service_uri", service_uri);
+                }
+                Ok(Client::new().delete(url))
+            })?;
+            if response.status().is_client_error() {
+                Err(Error::ArrowheadError(response.json()?))
+            } else {
+                Ok(())
+            }
+        })
     }
 
     pub fn echo_authorization(&self) -> Result<()> {
-        let client = Client::new();
-        client
-            .get(self.authorization_address.join("echo")?)
-            .send()?
-            .error_for_status()?;
-        Ok(())
+        instrumentation::observe(AUTHORIZATION, "echo_authorization", || {
+            self.authorization
+                .send(|address| Ok(Client::new().get(address.join("echo")?)))?
+                .error_for_status()?;
+            Ok(())
+        })
     }
 
     pub fn get_public_key(&self) -> Result<String> {
-        let client = Client::new();
-        let response = client
-            .get(self.authorization_address.join("publickey")?)
-            .send()?
-            .error_for_status()?;
-        Ok(response.text()?)
+        instrumentation::observe(AUTHORIZATION, "get_public_key", || {
+            let response = self
+                .authorization
+                .send(|address| Ok(Client::new().get(address.join("publickey")?)))?
+                .error_for_status()?;
+            Ok(response.text()?)
+        })
     }
 
     pub fn echo_orchestrator(&self) -> Result<()> {
-        let client = Client::new();
-        client
-            .get(self.orchestrator_address.join("echo")?)
-            .send()?
-            .error_for_status()?;
-        Ok(())
+        instrumentation::observe(ORCHESTRATOR, "echo_orchestrator", || {
+            self.orchestrator
+                .send(|address| Ok(Client::new().get(address.join("echo")?)))?
+                .error_for_status()?;
+            Ok(())
+        })
     }
 
     pub fn request_orchestration(
         &self,
         input: RequestOrchestrationInput,
     ) -> Result<OrchestrationResponse> {
-        let service_request_form = input.to_service_request_form(self.client_system.clone());
-        let client = Client::new();
-        let response = client
-            .post(self.orchestrator_address.join("orchestration")?)
-            .json(&service_request_form)
-            .send()?;
-        if response.status().is_client_error() {
-            Err(Error::ArrowheadError(response.json()?))
-        } else {
-            Ok(response.json()?)
-        }
+        instrumentation::observe(ORCHESTRATOR, "request_orchestration", || {
+            let mut service_request_form =
+                input.to_service_request_form(self.client_system.for_version(self.version));
+            for preferred_provider in service_request_form
+                .preferred_providers
+                .iter_mut()
+                .flatten()
+            {
+                preferred_provider.provider_system =
+                    preferred_provider.provider_system.for_version(self.version);
+            }
+            if self.naming_validation {
+                naming::validate_service_request_form(&service_request_form)?;
+            }
+            let response = self.orchestrator.send(|address| {
+                Ok(Client::new()
+                    .post(address.join("orchestration")?)
+                    .json(&service_request_form))
+            })?;
+            if response.status().is_client_error() {
+                Err(Error::ArrowheadError(response.json()?))
+            } else {
+                Ok(response.json()?)
+            }
+        })
     }
 
     pub fn request_orchestration_by_id(&self, id: i64) -> Result<OrchestrationResponse> {
-        let client = Client::new();
-        let response = client
-            .get(
-                self.orchestrator_address
-                    .join(&format!("orchestration/{}", id))?,
-            )
-            .send()?;
-        if response.status().is_client_error() {
-            Err(Error::ArrowheadError(response.json()?))
-        } else {
-            Ok(response.json()?)
-        }
+        instrumentation::observe(ORCHESTRATOR, "request_orchestration_by_id", || {
+            let response = self.orchestrator.send(|address| {
+                Ok(Client::new().get(address.join(&format!("orchestration/{}", id))?))
+            })?;
+            if response.status().is_client_error() {
+                Err(Error::ArrowheadError(response.json()?))
+            } else {
+                Ok(response.json()?)
+            }
+        })
     }
 }
 
@@ -170,6 +550,7 @@ mod tests {
                 address: "string".to_owned(),
                 port: 0,
                 authentication_info: Some("string".to_owned()),
+                metadata: None,
             },
         )
         .unwrap();
@@ -191,6 +572,7 @@ mod tests {
                 address: "string".to_owned(),
                 port: 0,
                 authentication_info: Some("string".to_owned()),
+                metadata: None,
             },
         )
         .unwrap();
@@ -278,6 +660,7 @@ mod tests {
                 address: "string".to_owned(),
                 port: 0,
                 authentication_info: Some("string".to_owned()),
+                metadata: None,
             },
         )
         .unwrap();
@@ -322,6 +705,7 @@ mod tests {
                     address: "string".to_owned(),
                     port: 0,
                     authentication_info: Some("string".to_owned()),
+                    metadata: None,
                 },
                 service_uri: "string".to_owned(),
                 end_of_validity: Some("string".to_owned()),
@@ -361,6 +745,7 @@ mod tests {
                 address: "string".to_owned(),
                 port: 0,
                 authentication_info: Some("string".to_owned()),
+                metadata: None,
             },
         )
         .unwrap();
@@ -428,6 +813,7 @@ mod tests {
                 address: "string".to_owned(),
                 port: 0,
                 authentication_info: Some("string".to_owned()),
+                metadata: None,
             },
         )
         .unwrap();
@@ -536,6 +922,7 @@ mod tests {
                 address: "string".to_owned(),
                 port: 0,
                 authentication_info: Some("string".to_owned()),
+                metadata: None,
             },
         )
         .unwrap();
@@ -576,6 +963,7 @@ mod tests {
                 address: "string".to_owned(),
                 port: 0,
                 authentication_info: Some("string".to_owned()),
+                metadata: None,
             },
             service_uri: "string".to_owned(),
             end_of_validity: Some("string".to_owned()),
@@ -611,6 +999,7 @@ mod tests {
                 address: "string".to_owned(),
                 port: 0,
                 authentication_info: Some("string".to_owned()),
+                metadata: None,
             },
         )
         .unwrap();
@@ -631,6 +1020,67 @@ mod tests {
         assert!(matches!(result, Err(Error::HttpError(_))));
     }
 
+    #[test]
+    fn register_service_naming_validation() {
+        let mock = mockito::mock("POST", "/naming/register")
+            .with_status(400)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                  "errorMessage": "Invalid service definition",
+                  "errorCode": 400,
+                  "exceptionType": "INVALID_PARAMETER",
+                  "origin": "serviceregistry/register"
+                })
+                .to_string(),
+            )
+            .expect(1)
+            .create();
+        let ah_adapter = ArrowheadSystemAdapter::new(
+            &format!("{}/naming/", mockito::server_url()),
+            "http://dontcare",
+            "http://dontcare",
+            ArrowheadSystem {
+                entry_tag: NoEntryTag {},
+                system_name: "string".to_owned(),
+                address: "string".to_owned(),
+                port: 0,
+                authentication_info: None,
+                metadata: None,
+            },
+        )
+        .unwrap();
+        let input = RegisterServiceInput {
+            service_definition: ServiceDefinitionEntry::Value("Temperature_C".to_owned()),
+            service_uri: "/temperature".to_owned(),
+            end_of_validity: None,
+            secure: None,
+            metadata: None,
+            version: None,
+            interfaces: vec![InterfaceEntry::Value("HTTP-INSECURE-JSON".to_owned())],
+        };
+
+        let result = ah_adapter.register_service(input.clone());
+        assert!(matches!(
+            result,
+            Err(Error::ValidationError(message)) if message.starts_with(
+                "Invalid service definition 'Temperature_C': uppercase letter 'T' at position 1"
+            )
+        ));
+        // The invalid name is rejected before sending the request.
+        assert!(!mock.matched());
+        let result = ah_adapter
+            .with_naming_validation(false)
+            .register_service(input);
+        assert!(matches!(
+            result,
+            Err(Error::ArrowheadError(exception))
+                if exception.error_message == "Invalid service definition"
+                    && exception.exception_type == "INVALID_PARAMETER"
+        ));
+        mock.assert();
+    }
+
     #[test]
     fn register_service_arrowhead_error() {
         let mock = mockito::mock("POST", "/register")
@@ -678,6 +1128,7 @@ mod tests {
                 address: "string".to_owned(),
                 port: 0,
                 authentication_info: Some("string".to_owned()),
+                metadata: None,
             },
         )
         .unwrap();
@@ -726,6 +1177,7 @@ mod tests {
                 address: "string".to_owned(),
                 port: 0,
                 authentication_info: Some("string".to_owned()),
+                metadata: None,
             },
         )
         .unwrap();
@@ -747,6 +1199,7 @@ mod tests {
                 address: "string".to_owned(),
                 port: 0,
                 authentication_info: Some("string".to_owned()),
+                metadata: None,
             },
         )
         .unwrap();
@@ -786,6 +1239,7 @@ mod tests {
                 address: "string".to_owned(),
                 port: 0,
                 authentication_info: Some("string".to_owned()),
+                metadata: None,
             },
         )
         .unwrap();
@@ -815,6 +1269,7 @@ mod tests {
                 address: "string".to_owned(),
                 port: 0,
                 authentication_info: Some("string".to_owned()),
+                metadata: None,
             },
         )
         .unwrap();
@@ -836,6 +1291,7 @@ mod tests {
                 address: "string".to_owned(),
                 port: 0,
                 authentication_info: Some("string".to_owned()),
+                metadata: None,
             },
         )
         .unwrap();
@@ -859,6 +1315,7 @@ mod tests {
                 address: "string".to_owned(),
                 port: 0,
                 authentication_info: Some("string".to_owned()),
+                metadata: None,
             },
         )
         .unwrap();
@@ -881,6 +1338,7 @@ mod tests {
                 address: "string".to_owned(),
                 port: 0,
                 authentication_info: Some("string".to_owned()),
+                metadata: None,
             },
         )
         .unwrap();
@@ -902,6 +1360,7 @@ mod tests {
                 address: "string".to_owned(),
                 port: 0,
                 authentication_info: Some("string".to_owned()),
+                metadata: None,
             },
         )
         .unwrap();
@@ -923,6 +1382,7 @@ mod tests {
                 address: "string".to_owned(),
                 port: 0,
                 authentication_info: Some("string".to_owned()),
+                metadata: None,
             },
         )
         .unwrap();
@@ -1041,6 +1501,7 @@ mod tests {
                 address: "string".to_owned(),
                 port: 0,
                 authentication_info: Some("string".to_owned()),
+                metadata: None,
             },
         )
         .unwrap();
@@ -1073,6 +1534,7 @@ mod tests {
                     address: "string".to_owned(),
                     port: 0,
                     authentication_info: None,
+                    metadata: None,
                 },
             }]),
             orchestration_flags: Some(HashMap::from([
@@ -1097,6 +1559,7 @@ mod tests {
                     address: "string".to_owned(),
                     port: 0,
                     authentication_info: Some("string".to_owned()),
+                    metadata: None,
                 },
                 service: ServiceDefinitionEntry::Entry {
                     entry_tag: EntryTag {
@@ -1150,6 +1613,7 @@ mod tests {
                 address: "string".to_owned(),
                 port: 0,
                 authentication_info: Some("string".to_owned()),
+                metadata: None,
             },
         )
         .unwrap();
@@ -1182,6 +1646,7 @@ mod tests {
                     address: "string".to_owned(),
                     port: 0,
                     authentication_info: None,
+                    metadata: None,
                 },
             }]),
             orchestration_flags: Some(HashMap::from([
@@ -1271,6 +1736,7 @@ mod tests {
                 address: "string".to_owned(),
                 port: 0,
                 authentication_info: Some("string".to_owned()),
+                metadata: None,
             },
         )
         .unwrap();
@@ -1303,6 +1769,7 @@ mod tests {
                     address: "string".to_owned(),
                     port: 0,
                     authentication_info: None,
+                    metadata: None,
                 },
             }]),
             orchestration_flags: Some(HashMap::from([
@@ -1389,6 +1856,7 @@ mod tests {
                 address: "string".to_owned(),
                 port: 0,
                 authentication_info: Some("string".to_owned()),
+                metadata: None,
             },
         )
         .unwrap();
@@ -1405,6 +1873,7 @@ mod tests {
                     address: "string".to_owned(),
                     port: 0,
                     authentication_info: Some("string".to_owned()),
+                    metadata: None,
                 },
                 service: ServiceDefinitionEntry::Entry {
                     entry_tag: EntryTag {
@@ -1458,6 +1927,7 @@ mod tests {
                 address: "string".to_owned(),
                 port: 0,
                 authentication_info: Some("string".to_owned()),
+                metadata: None,
             },
         )
         .unwrap();
@@ -1491,6 +1961,7 @@ mod tests {
                 address: "string".to_owned(),
                 port: 0,
                 authentication_info: Some("string".to_owned()),
+                metadata: None,
             },
         )
         .unwrap();
@@ -1506,4 +1977,274 @@ mod tests {
         );
         mock.assert();
     }
+
+    fn fixture(path: &str) -> serde_json::Value {
+        let path = format!("{}/fixtures/{}", env!("CARGO_MANIFEST_DIR"), path);
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    fn versioned_adapter(version: ArrowheadVersion) -> ArrowheadSystemAdapter {
+        ArrowheadSystemAdapter::new(
+            &mockito::server_url(),
+            "http://dontcare",
+            "http://dontcare",
+            ArrowheadSystem {
+                entry_tag: NoEntryTag {},
+                system_name: "thermometer".to_owned(),
+                address: "192.168.0.10".to_owned(),
+                port: 8080,
+                authentication_info: None,
+                metadata: Some(HashMap::from([("location".to_owned(), "hall".to_owned())])),
+            },
+        )
+        .unwrap()
+        .with_version(version)
+    }
+
+    #[test]
+    fn deserialize_service_query_list_fixtures() {
+        let service_query_list: ServiceQueryList =
+            serde_json::from_value(fixture("4.4/service_registry/query/response.json")).unwrap();
+        assert!(service_query_list.service_query_data[0]
+            .provider_system
+            .metadata
+            .is_none());
+
+        let service_query_list: ServiceQueryList =
+            serde_json::from_value(fixture("4.6/service_registry/query/response.json")).unwrap();
+        assert!(matches!(
+            &service_query_list.service_query_data[0].provider_system.metadata,
+            Some(metadata) if metadata["location"] == "hall"
+        ));
+    }
+
+    #[test]
+    fn register_service_per_version() {
+        for (version, fixture_version) in [
+            (ArrowheadVersion::V4_4, "4.4"),
+            (ArrowheadVersion::V4_6, "4.6"),
+        ] {
+            let mock = mockito::mock("POST", "/register")
+                .match_body(Matcher::Json(fixture(&format!(
+                    "{}/service_registry/register/request.json",
+                    fixture_version
+                ))))
+                .with_status(201)
+                .with_header("content-type", "application/json")
+                .with_body(
+                    fixture(&format!(
+                        "{}/service_registry/query/response.json",
+                        fixture_version
+                    ))["serviceQueryData"][0]
+                        .to_string(),
+                )
+                .create();
+            let result = versioned_adapter(version).register_service(RegisterServiceInput {
+                service_definition: ServiceDefinitionEntry::Value("temperature".to_owned()),
+                service_uri: "/temperature".to_owned(),
+                end_of_validity: None,
+                secure: None,
+                metadata: None,
+                version: Some(1),
+                interfaces: vec![InterfaceEntry::Value("HTTP-INSECURE-JSON".to_owned())],
+            });
+
+            assert!(result.is_ok());
+            mock.assert();
+        }
+    }
+
+    #[test]
+    fn query_service_multi() {
+        let mock = mockito::mock("POST", "/query/multi")
+            .match_body(Matcher::Json(fixture(
+                "4.6/service_registry/query_multi/request.json",
+            )))
+            .with_header("content-type", "application/json")
+            .with_body(fixture("4.6/service_registry/query_multi/response.json").to_string())
+            .create();
+        let service_query_form_list: ServiceQueryFormList =
+            serde_json::from_value(fixture("4.6/service_registry/query_multi/request.json"))
+                .unwrap();
+        let result =
+            versioned_adapter(ArrowheadVersion::V4_6).query_service_multi(&service_query_form_list);
+
+        assert!(matches!(
+            result,
+            Ok(list) if list.results.len() == 2 && list.results[1].unfiltered_hits == 2
+        ));
+        mock.assert();
+    }
+
+    #[test]
+    fn query_service_multi_unsupported() {
+        let result = versioned_adapter(ArrowheadVersion::V4_4)
+            .query_service_multi(&ServiceQueryFormList { forms: vec![] });
+
+        assert!(matches!(result, Err(Error::UnsupportedError(_))));
+    }
+
+    fn service_query_form(service_definition: &str) -> ServiceQueryForm {
+        ServiceQueryForm {
+            service_requirements: ServiceRequirements {
+                service_definition_requirement: service_definition.to_owned(),
+                interface_requirements: None,
+                security_requirements: None,
+                metadata_requirements: None,
+                version_requirement: None,
+                max_version_requirement: None,
+                min_version_requirement: None,
+            },
+            ping_providers: None,
+        }
+    }
+
+    #[test]
+    fn query_services_in_one_request() {
+        let mock = mockito::mock("POST", "/query/multi")
+            .match_body(Matcher::PartialJson(json!({
+                "forms": [
+                    { "serviceDefinitionRequirement": "temperature" },
+                    { "serviceDefinitionRequirement": "humidity" }
+                ]
+            })))
+            .with_header("content-type", "application/json")
+            .with_body(fixture("4.6/service_registry/query_multi/response.json").to_string())
+            .expect(1)
+            .create();
+        let result = versioned_adapter(ArrowheadVersion::V4_6).query_services(&[
+            service_query_form("temperature"),
+            service_query_form("humidity"),
+        ]);
+
+        assert!(matches!(
+            result,
+            Ok(results) if results[0].0 == "temperature"
+                && results[0].1.service_query_data.len() == 1
+                && results[1].0 == "humidity"
+                && results[1].1.unfiltered_hits == 2
+        ));
+        mock.assert();
+    }
+
+    #[test]
+    fn query_services_with_mismatched_results() {
+        let mock = mockito::mock("POST", "/query/multi")
+            .with_header("content-type", "application/json")
+            .with_body(fixture("4.6/service_registry/query_multi/response.json").to_string())
+            .expect(2)
+            .create();
+        let adapter = versioned_adapter(ArrowheadVersion::V4_6);
+
+        assert!(matches!(
+            adapter.query_services(&[service_query_form("temperature")]),
+            Err(Error::UnexpectedResponseError(_))
+        ));
+        assert!(matches!(
+            adapter.query_services(&[
+                service_query_form("humidity"),
+                service_query_form("temperature"),
+            ]),
+            Err(Error::UnexpectedResponseError(message)) if message.contains("'humidity'")
+        ));
+        mock.assert();
+    }
+
+    #[test]
+    fn query_services_in_parallel() {
+        let temperature_mock = mockito::mock("POST", "/query")
+            .match_body(Matcher::PartialJson(
+                json!({ "serviceDefinitionRequirement": "temperature" }),
+            ))
+            .with_header("content-type", "application/json")
+            .with_body(fixture("4.4/service_registry/query/response.json").to_string())
+            .create();
+        let humidity_mock = mockito::mock("POST", "/query")
+            .match_body(Matcher::PartialJson(
+                json!({ "serviceDefinitionRequirement": "humidity" }),
+            ))
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "serviceQueryData": [], "unfilteredHits": 3 }).to_string())
+            .create();
+        let result = versioned_adapter(ArrowheadVersion::V4_4).query_services(&[
+            service_query_form("temperature"),
+            service_query_form("humidity"),
+        ]);
+
+        assert!(matches!(
+            result,
+            Ok(results) if results[0].0 == "temperature" && results[0].1.unfiltered_hits == 1
+                && results[1].0 == "humidity" && results[1].1.unfiltered_hits == 3
+        ));
+        temperature_mock.assert();
+        humidity_mock.assert();
+    }
+
+    #[test]
+    fn query_services_without_multi_endpoint() {
+        let multi_mock = mockito::mock("POST", "/legacy/query/multi")
+            .with_status(404)
+            .create();
+        let query_mock = mockito::mock("POST", "/legacy/query")
+            .match_body(Matcher::PartialJson(
+                json!({ "serviceDefinitionRequirement": "temperature" }),
+            ))
+            .with_header("content-type", "application/json")
+            .with_body(fixture("4.4/service_registry/query/response.json").to_string())
+            .expect(2)
+            .create();
+        let mut adapter = versioned_adapter(ArrowheadVersion::V4_6);
+        adapter.service_registry =
+            CoreSystemEndpoints::new(&[&format!("{}/legacy/", mockito::server_url())]).unwrap();
+        let result = adapter.query_services(&[
+            service_query_form("temperature"),
+            service_query_form("temperature"),
+        ]);
+
+        assert!(matches!(result, Ok(results) if results.len() == 2));
+        multi_mock.assert();
+        query_mock.assert();
+    }
+
+    #[test]
+    fn unregister_service_entry() {
+        let mock = mockito::mock("DELETE", "/unregister")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("service_definition".into(), "temperature".into()),
+                Matcher::UrlEncoded("system_name".into(), "thermometer".into()),
+                Matcher::UrlEncoded("address".into(), "192.168.0.10".into()),
+                Matcher::UrlEncoded("port".into(), "8080".into()),
+                Matcher::UrlEncoded("service_uri".into(), "/temperature".into()),
+            ]))
+            .expect(2)
+            .create();
+        let adapter = versioned_adapter(ArrowheadVersion::V4_4);
+        let service: ArrowheadService<EntryTag> =
+            serde_json::from_value(fixture("4.4/service_registry/register/response.json")).unwrap();
+
+        assert!(adapter.unregister_service_entry(&service).is_ok());
+        assert!(adapter
+            .unregister_service_uri("temperature", "/temperature")
+            .is_ok());
+        mock.assert();
+    }
+
+    #[test]
+    fn delete_service_by_id() {
+        let mock = mockito::mock("DELETE", "/mgmt/12").create();
+        let error_mock = mockito::mock("DELETE", "/mgmt/13")
+            .with_status(400)
+            .with_header("content-type", "application/json")
+            .with_body(fixture("4.4/service_registry/mgmt_delete/error.json").to_string())
+            .create();
+        let adapter = versioned_adapter(ArrowheadVersion::V4_4);
+
+        assert!(adapter.delete_service_by_id(12).is_ok());
+        assert!(matches!(
+            adapter.delete_service_by_id(13),
+            Err(Error::ArrowheadError(e)) if e.exception_type == "INVALID_PARAMETER"
+        ));
+        mock.assert();
+        error_mock.assert();
+    }
 }
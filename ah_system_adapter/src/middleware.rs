@@ -0,0 +1,551 @@
+use crate::error::{Error, Result};
+use crate::instrumentation::{redact_url, REDACTED};
+use crate::random;
+
+use reqwest::blocking::{Request, Response};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use serde_json::Value;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+pub const CORRELATION_ID_HEADER: &str = "X-Correlation-ID";
+
+// Hooks seeing and modifying every request sent to a core system and every response received.
+// The requests pass the middleware in the order they were added, the responses in the reverse
+// order. Returning an error stops the call with the error.
+pub trait Middleware: Send + Sync {
+    fn on_request(&self, _request: &mut Request) -> Result<()> {
+        Ok(())
+    }
+
+    // The request is the one sent, after every middleware modified it.
+    fn on_response(&self, _request: &Request, response: Response) -> Result<Response> {
+        Ok(response)
+    }
+}
+
+thread_local! {
+    // The correlation ids of the call being sent on this thread, by header.
+    static CALL_CORRELATION_IDS: RefCell<Option<HashMap<HeaderName, String>>> =
+        const { RefCell::new(None) };
+}
+
+// Runs one call of an operation, which may send several requests when failing over to other
+// addresses. The attempts share the correlation ids generated for the first one.
+pub(crate) fn in_call<T>(call: impl FnOnce() -> T) -> T {
+    struct Restore(Option<HashMap<HeaderName, String>>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let outer = self.0.take();
+            CALL_CORRELATION_IDS.with(|ids| *ids.borrow_mut() = outer);
+        }
+    }
+    let _restore = Restore(CALL_CORRELATION_IDS.with(|ids| ids.replace(Some(HashMap::new()))));
+    call()
+}
+
+// Sets a unique id in the X-Correlation-ID header (or another header) of the requests, unless
+// it is already set, and records it on the span of the call as `correlation_id`. Every attempt of
+// a call failing over to other addresses is sent with the same id.
+pub struct CorrelationId {
+    header: HeaderName,
+    generate: Box<dyn Fn() -> String + Send + Sync>,
+}
+
+impl CorrelationId {
+    pub fn new() -> Self {
+        Self {
+            header: HeaderName::from_static("x-correlation-id"),
+            generate: Box::new(random_id),
+        }
+    }
+
+    pub fn with_header(mut self, header: &str) -> Result<Self> {
+        self.header = HeaderName::try_from(header)
+            .map_err(|e| Error::HttpError(format!("Invalid header name '{}': {}", header, e)))?;
+        Ok(self)
+    }
+
+    pub fn with_generator<F>(mut self, generate: F) -> Self
+    where
+        F: Fn() -> String + Send + Sync + 'static,
+    {
+        self.generate = Box::new(generate);
+        self
+    }
+}
+
+impl CorrelationId {
+    fn call_id(&self) -> String {
+        let id = CALL_CORRELATION_IDS.with(|ids| {
+            ids.borrow()
+                .as_ref()
+                .and_then(|ids| ids.get(&self.header).cloned())
+        });
+        id.unwrap_or_else(|| {
+            let id = (self.generate)();
+            CALL_CORRELATION_IDS.with(|ids| {
+                if let Some(ids) = ids.borrow_mut().as_mut() {
+                    ids.insert(self.header.clone(), id.clone());
+                }
+            });
+            id
+        })
+    }
+}
+
+impl Default for CorrelationId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Middleware for CorrelationId {
+    fn on_request(&self, request: &mut Request) -> Result<()> {
+        let id = match request.headers().get(&self.header) {
+            Some(id) => id.to_str().unwrap_or_default().to_owned(),
+            None => {
+                let id = self.call_id();
+                let value = HeaderValue::try_from(id.as_str())
+                    .map_err(|e| Error::HttpError(format!("Invalid correlation id: {}", e)))?;
+                request.headers_mut().insert(self.header.clone(), value);
+                id
+            }
+        };
+        tracing::Span::current().record("correlation_id", id.as_str());
+        Ok(())
+    }
+}
+
+// A random id in the format of a version 4 UUID.
+fn random_id() -> String {
+    let (high, low) = (random::random_u64(), random::random_u64());
+    format!(
+        "{:08x}-{:04x}-4{:03x}-{:04x}-{:012x}",
+        high >> 32,
+        (high >> 16) & 0xffff,
+        high & 0x0fff,
+        (low >> 48) & 0x3fff | 0x8000,
+        low & 0xffff_ffff_ffff
+    )
+}
+
+// Logs the requests and the responses with their headers and JSON bodies at info level. The
+// sensitive headers (Authorization, Cookie and the ones marked sensitive), the token query
+// parameters and the `authenticationInfo`, `authorizationTokens` and `token` fields of the bodies
+// are redacted.
+pub struct RequestLogging {
+    redacted_headers: Vec<HeaderName>,
+    redacted_fields: Vec<String>,
+}
+
+impl RequestLogging {
+    pub fn new() -> Self {
+        Self {
+            redacted_headers: vec![
+                AUTHORIZATION,
+                reqwest::header::PROXY_AUTHORIZATION,
+                reqwest::header::COOKIE,
+                reqwest::header::SET_COOKIE,
+            ],
+            redacted_fields: ["authenticationInfo", "authorizationTokens", "token"]
+                .map(str::to_owned)
+                .to_vec(),
+        }
+    }
+
+    pub fn with_redacted_header(mut self, header: &str) -> Result<Self> {
+        self.redacted_headers.push(
+            HeaderName::try_from(header).map_err(|e| {
+                Error::HttpError(format!("Invalid header name '{}': {}", header, e))
+            })?,
+        );
+        Ok(self)
+    }
+
+    pub fn with_redacted_field(mut self, field: &str) -> Self {
+        self.redacted_fields.push(field.to_owned());
+        self
+    }
+
+    fn headers(&self, headers: &HeaderMap) -> String {
+        headers
+            .iter()
+            .map(|(name, value)| {
+                let value = if value.is_sensitive() || self.redacted_headers.contains(name) {
+                    REDACTED
+                } else {
+                    value.to_str().unwrap_or("[binary]")
+                };
+                format!("{}: {}", name, value)
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn body(&self, body: &[u8]) -> String {
+        match serde_json::from_slice::<Value>(body) {
+            Ok(mut json) => {
+                self.redact(&mut json);
+                json.to_string()
+            }
+            Err(_) => String::from_utf8_lossy(body).into_owned(),
+        }
+    }
+
+    fn redact(&self, json: &mut Value) {
+        match json {
+            Value::Object(object) => {
+                for (key, value) in object.iter_mut() {
+                    if self.redacted_fields.contains(key) {
+                        *value = Value::from(REDACTED);
+                    } else {
+                        self.redact(value);
+                    }
+                }
+            }
+            Value::Array(array) => array.iter_mut().for_each(|value| self.redact(value)),
+            _ => {}
+        }
+    }
+}
+
+impl Default for RequestLogging {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Middleware for RequestLogging {
+    fn on_request(&self, request: &mut Request) -> Result<()> {
+        let body = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .map(|body| self.body(body))
+            .unwrap_or_default();
+        tracing::info!(
+            method = %request.method(),
+            url = %redact_url(request.url()),
+            headers = %self.headers(request.headers()),
+            body = %body,
+            "sending request"
+        );
+        Ok(())
+    }
+
+    // The body is read to be logged, and the response is rebuilt with the same status, headers
+    // and body.
+    fn on_response(&self, request: &Request, response: Response) -> Result<Response> {
+        let status = response.status();
+        let version = response.version();
+        let headers = response.headers().clone();
+        let body = response.bytes()?;
+        tracing::info!(
+            method = %request.method(),
+            url = %redact_url(request.url()),
+            status = status.as_u16(),
+            headers = %self.headers(&headers),
+            body = %self.body(&body),
+            "received response"
+        );
+
+        let mut rebuilt = http::Response::new(body);
+        *rebuilt.status_mut() = status;
+        *rebuilt.version_mut() = version;
+        *rebuilt.headers_mut() = headers;
+        Ok(Response::from(rebuilt))
+    }
+}
+
+// Adds an authentication header to the requests, e.g. for core systems behind an API gateway.
+// The value may be computed for every request (e.g. a token refreshed when it expires). The
+// header is marked sensitive, so it is never logged.
+pub struct AuthHeader {
+    name: HeaderName,
+    value: Box<dyn Fn() -> Result<String> + Send + Sync>,
+}
+
+impl AuthHeader {
+    pub fn new(name: &str, value: &str) -> Result<Self> {
+        let value = value.to_owned();
+        Self::from_fn(name, move || Ok(value.clone()))
+    }
+
+    pub fn bearer(token: &str) -> Self {
+        let value = format!("Bearer {}", token);
+        Self {
+            name: AUTHORIZATION,
+            value: Box::new(move || Ok(value.clone())),
+        }
+    }
+
+    pub fn from_fn<F>(name: &str, value: F) -> Result<Self>
+    where
+        F: Fn() -> Result<String> + Send + Sync + 'static,
+    {
+        Ok(Self {
+            name: HeaderName::try_from(name)
+                .map_err(|e| Error::HttpError(format!("Invalid header name '{}': {}", name, e)))?,
+            value: Box::new(value),
+        })
+    }
+}
+
+impl Middleware for AuthHeader {
+    fn on_request(&self, request: &mut Request) -> Result<()> {
+        let mut value = HeaderValue::try_from((self.value)()?).map_err(|e| {
+            Error::HttpError(format!("Invalid value of header '{}': {}", self.name, e))
+        })?;
+        value.set_sensitive(true);
+        request.headers_mut().insert(self.name.clone(), value);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dtos::{ArrowheadSystem, NoEntryTag};
+    use crate::endpoints::CoreSystemEndpoints;
+    use crate::ArrowheadSystemAdapter;
+
+    use mockito::Matcher;
+    use reqwest::Method;
+
+    use std::sync::{Arc, Mutex};
+
+    fn adapter(prefix: &str) -> ArrowheadSystemAdapter {
+        let address = format!("{}/{}/", mockito::server_url(), prefix);
+        ArrowheadSystemAdapter::new(
+            &address,
+            &address,
+            &address,
+            ArrowheadSystem {
+                entry_tag: NoEntryTag {},
+                system_name: "thermometer".to_owned(),
+                address: "127.0.0.1".to_owned(),
+                port: 8080,
+                authentication_info: Some("secret-key".to_owned()),
+                metadata: None,
+            },
+        )
+        .unwrap()
+    }
+
+    // Records the requests and the responses seen, and modifies both of them.
+    struct Recorder {
+        name: &'static str,
+        events: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Middleware for Recorder {
+        fn on_request(&self, request: &mut Request) -> Result<()> {
+            let seen = request
+                .headers()
+                .get("x-seen")
+                .map_or("", |value| value.to_str().unwrap())
+                .to_owned();
+            request.headers_mut().insert(
+                "x-seen",
+                HeaderValue::try_from(format!("{}{}", seen, self.name)).unwrap(),
+            );
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("request {}", self.name));
+            Ok(())
+        }
+
+        fn on_response(&self, _: &Request, mut response: Response) -> Result<Response> {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("response {}", self.name));
+            response
+                .headers_mut()
+                .insert("x-modified", HeaderValue::from_static("true"));
+            Ok(response)
+        }
+    }
+
+    #[test]
+    fn middleware_order() {
+        let mock = mockito::mock("GET", "/order/echo")
+            .match_header("x-seen", "ab")
+            .create();
+        let events = Arc::new(Mutex::new(vec![]));
+        let adapter = adapter("order")
+            .with_middleware(Recorder {
+                name: "a",
+                events: events.clone(),
+            })
+            .with_middleware(Recorder {
+                name: "b",
+                events: events.clone(),
+            });
+
+        assert!(adapter.echo_service_registry().is_ok());
+        assert_eq!(
+            *events.lock().unwrap(),
+            ["request a", "request b", "response b", "response a"]
+        );
+        mock.assert();
+    }
+
+    #[test]
+    fn middleware_error_stops_the_call() {
+        struct Reject;
+        impl Middleware for Reject {
+            fn on_request(&self, _: &mut Request) -> Result<()> {
+                Err(Error::HttpError("rejected".to_owned()))
+            }
+        }
+        let mock = mockito::mock("GET", "/reject/echo").expect(0).create();
+
+        assert!(matches!(
+            adapter("reject").with_middleware(Reject).echo_service_registry(),
+            Err(Error::HttpError(message)) if message == "rejected"
+        ));
+        mock.assert();
+    }
+
+    #[test]
+    fn correlation_id() {
+        let mock = mockito::mock("GET", "/correlation/echo")
+            .match_header("x-correlation-id", "id-1")
+            .create();
+        let custom_mock = mockito::mock("GET", "/correlation/publickey")
+            .match_header("x-request-id", Matcher::Regex("^[0-9a-f-]{36}$".to_owned()))
+            .with_body("\"key\"")
+            .create();
+
+        assert!(adapter("correlation")
+            .with_middleware(CorrelationId::new().with_generator(|| "id-1".to_owned()))
+            .echo_service_registry()
+            .is_ok());
+        assert!(adapter("correlation")
+            .with_middleware(CorrelationId::new().with_header("X-Request-ID").unwrap())
+            .get_public_key()
+            .is_ok());
+        mock.assert();
+        custom_mock.assert();
+        assert_ne!(random_id(), random_id());
+    }
+
+    #[test]
+    fn correlation_id_across_failover() {
+        struct SeenIds(Arc<Mutex<Vec<String>>>);
+        impl Middleware for SeenIds {
+            fn on_request(&self, request: &mut Request) -> Result<()> {
+                let id = request.headers()[CORRELATION_ID_HEADER].to_str().unwrap();
+                self.0.lock().unwrap().push(id.to_owned());
+                Ok(())
+            }
+        }
+        let failing_mock = mockito::mock("GET", "/failing/echo")
+            .with_status(503)
+            .expect(2)
+            .create();
+        let mock = mockito::mock("GET", "/failover/echo").expect(2).create();
+        let seen_ids = Arc::new(Mutex::new(vec![]));
+        let echo = || {
+            let mut adapter = adapter("failover");
+            adapter.service_registry = CoreSystemEndpoints::new(&[
+                &format!("{}/failing/", mockito::server_url()),
+                &format!("{}/failover/", mockito::server_url()),
+            ])
+            .unwrap();
+            adapter
+                .with_middleware(CorrelationId::new())
+                .with_middleware(SeenIds(seen_ids.clone()))
+                .echo_service_registry()
+        };
+
+        assert!(echo().is_ok());
+        assert!(echo().is_ok());
+        let seen_ids = seen_ids.lock().unwrap();
+        assert_eq!(seen_ids.len(), 4);
+        assert_eq!(seen_ids[0], seen_ids[1]);
+        assert_eq!(seen_ids[2], seen_ids[3]);
+        assert_ne!(seen_ids[0], seen_ids[2]);
+        failing_mock.assert();
+        mock.assert();
+    }
+
+    #[test]
+    fn auth_header() {
+        let mock = mockito::mock("GET", "/auth/echo")
+            .match_header("authorization", "Bearer token")
+            .match_header("x-api-key", "key-1")
+            .create();
+        let adapter = adapter("auth")
+            .with_middleware(AuthHeader::bearer("token"))
+            .with_middleware(AuthHeader::from_fn("X-API-Key", || Ok("key-1".to_owned())).unwrap());
+
+        assert!(adapter.echo_service_registry().is_ok());
+        assert!(AuthHeader::new("invalid header", "value").is_err());
+        mock.assert();
+    }
+
+    #[test]
+    fn logging_keeps_the_response() {
+        let mock = mockito::mock("POST", "/logging/register")
+            .with_status(201)
+            .with_header("content-type", "application/json")
+            .with_body(
+                std::fs::read_to_string(format!(
+                    "{}/fixtures/4.4/service_registry/register/response.json",
+                    env!("CARGO_MANIFEST_DIR")
+                ))
+                .unwrap(),
+            )
+            .create();
+        let input = serde_json::from_value(serde_json::json!({
+            "serviceDefinition": "temperature",
+            "serviceUri": "/temperature",
+            "interfaces": ["HTTP-INSECURE-JSON"]
+        }))
+        .unwrap();
+
+        let registered = adapter("logging")
+            .with_middleware(RequestLogging::new())
+            .register_service(input);
+        assert!(matches!(registered, Ok(service) if service.entry_tag.id == 12));
+        mock.assert();
+    }
+
+    #[test]
+    fn logging_redaction() {
+        let logging = RequestLogging::new()
+            .with_redacted_field("password")
+            .with_redacted_header("X-API-Key")
+            .unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer token"));
+        headers.insert("x-api-key", HeaderValue::from_static("key"));
+        let mut sensitive = HeaderValue::from_static("secret");
+        sensitive.set_sensitive(true);
+        headers.insert("x-custom", sensitive);
+        headers.insert("accept", HeaderValue::from_static("application/json"));
+
+        assert_eq!(
+            logging.headers(&headers),
+            "authorization: [redacted], x-api-key: [redacted], x-custom: [redacted], \
+             accept: application/json"
+        );
+        assert_eq!(
+            logging.body(
+                br#"{"providerSystem":{"authenticationInfo":"key"},"password":"p","list":[{"token":"t"}],"authorizationTokens":{"HTTP":"t"}}"#
+            ),
+            r#"{"authorizationTokens":"[redacted]","list":[{"token":"[redacted]"}],"password":"[redacted]","providerSystem":{"authenticationInfo":"[redacted]"}}"#
+        );
+        assert_eq!(logging.body(b"plain text"), "plain text");
+
+        let request = Request::new(
+            Method::GET,
+            "http://127.0.0.1/temperature?token=secret".parse().unwrap(),
+        );
+        assert!(!redact_url(request.url()).contains("secret"));
+    }
+}
@@ -0,0 +1,843 @@
+use crate::dtos::{
+    ArrowheadServerException, ArrowheadService, ArrowheadSystem, EntryTag, InterfaceEntry,
+    NoEntryTag, Orchestration, OrchestrationFlagKey, OrchestrationResponse, OrchestrationWarning,
+    SecurityType, ServiceDefinitionEntry, ServiceQueryForm, ServiceQueryFormList, ServiceQueryList,
+    ServiceQueryResultList, ServiceRequestForm, ServiceRequirements,
+};
+use crate::error::{Error, Result};
+use crate::ArrowheadSystemAdapter;
+
+use serde::{de::DeserializeOwned, Serialize};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(PartialEq, Clone)]
+pub struct MockStoreRule {
+    pub consumer_system_name: String,
+    pub service_definition: String,
+    pub provider_system_name: String,
+    pub priority: u32,
+}
+
+#[derive(Default)]
+struct MockCoreState {
+    public_key: String,
+    next_id: u32,
+    systems: Vec<ArrowheadSystem<EntryTag>>,
+    services: Vec<ArrowheadService<EntryTag>>,
+    store_rules: Vec<MockStoreRule>,
+}
+
+pub struct MockCore {
+    state: Arc<Mutex<MockCoreState>>,
+    server: Arc<Server>,
+    worker: Option<JoinHandle<()>>,
+    base_url: String,
+}
+
+type MockResponse = Response<std::io::Cursor<Vec<u8>>>;
+
+impl MockCore {
+    pub fn start() -> Result<Self> {
+        Self::start_on("127.0.0.1:0")
+    }
+
+    pub fn start_on(bind_address: &str) -> Result<Self> {
+        let server =
+            Arc::new(Server::http(bind_address).map_err(|e| Error::ServerError(format!("{}", e)))?);
+        let socket_address = server
+            .server_addr()
+            .to_ip()
+            .ok_or_else(|| Error::ServerError("The mock core must listen on TCP".to_owned()))?;
+        let state = Arc::new(Mutex::new(MockCoreState {
+            public_key: "mock-public-key".to_owned(),
+            ..Default::default()
+        }));
+        let worker = {
+            let server = server.clone();
+            let state = state.clone();
+            thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    Self::handle_request(request, &state);
+                }
+            })
+        };
+
+        Ok(Self {
+            state,
+            server,
+            worker: Some(worker),
+            base_url: format!("http://{}/", socket_address),
+        })
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub fn service_registry_address(&self) -> String {
+        format!("{}serviceregistry/", self.base_url)
+    }
+
+    pub fn authorization_address(&self) -> String {
+        format!("{}authorization/", self.base_url)
+    }
+
+    pub fn orchestrator_address(&self) -> String {
+        format!("{}orchestrator/", self.base_url)
+    }
+
+    pub fn adapter(
+        &self,
+        client_system: ArrowheadSystem<NoEntryTag>,
+    ) -> Result<ArrowheadSystemAdapter> {
+        ArrowheadSystemAdapter::new(
+            &self.service_registry_address(),
+            &self.authorization_address(),
+            &self.orchestrator_address(),
+            client_system,
+        )
+    }
+
+    pub fn set_public_key(&self, public_key: &str) {
+        self.lock_state().public_key = public_key.to_owned();
+    }
+
+    pub fn add_store_rule(&self, store_rule: MockStoreRule) {
+        self.lock_state().store_rules.push(store_rule);
+    }
+
+    pub fn services(&self) -> Vec<ArrowheadService<EntryTag>> {
+        self.lock_state().services.clone()
+    }
+
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.server.unblock();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+
+    fn lock_state(&self) -> MutexGuard<'_, MockCoreState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn handle_request(mut request: Request, state: &Mutex<MockCoreState>) {
+        let url = request.url().to_owned();
+        let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+        let query: HashMap<String, String> = url::form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .collect();
+        let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+
+        let response = match (request.method().clone(), path) {
+            (Method::Get, "/serviceregistry/echo")
+            | (Method::Get, "/authorization/echo")
+            | (Method::Get, "/orchestrator/echo") => Response::from_string("Got it!"),
+            (Method::Post, "/serviceregistry/register") => {
+                match Self::read_json(&mut request, path) {
+                    Ok(service) => state.register(service, path),
+                    Err(response) => response,
+                }
+            }
+            (Method::Delete, "/serviceregistry/unregister") => state.unregister(&query, path),
+            (Method::Delete, path) if path.starts_with("/serviceregistry/mgmt/") => {
+                match path["/serviceregistry/mgmt/".len()..].parse() {
+                    Ok(id) => state.delete_by_id(id, path),
+                    Err(_) => Self::error_response(
+                        400,
+                        "BAD_PAYLOAD",
+                        "The service registry entry id must be a number",
+                        path,
+                    ),
+                }
+            }
+            (Method::Post, "/serviceregistry/query") => {
+                match Self::read_json::<ServiceQueryForm>(&mut request, path) {
+                    Ok(form) => Self::json_response(200, &state.query(&form)),
+                    Err(response) => response,
+                }
+            }
+            (Method::Post, "/serviceregistry/query/multi") => {
+                match Self::read_json::<ServiceQueryFormList>(&mut request, path) {
+                    Ok(form_list) => Self::json_response(
+                        200,
+                        &ServiceQueryResultList {
+                            results: form_list.forms.iter().map(|f| state.query(f)).collect(),
+                        },
+                    ),
+                    Err(response) => response,
+                }
+            }
+            (Method::Get, "/authorization/publickey") => {
+                Response::from_string(state.public_key.clone())
+            }
+            (Method::Post, "/orchestrator/orchestration") => {
+                match Self::read_json::<ServiceRequestForm>(&mut request, path) {
+                    Ok(form) => Self::json_response(200, &state.orchestrate(form)),
+                    Err(response) => response,
+                }
+            }
+            (Method::Get, path) if path.starts_with("/orchestrator/orchestration/") => {
+                match path["/orchestrator/orchestration/".len()..].parse() {
+                    Ok(id) => state.orchestrate_by_id(id, path),
+                    Err(_) => Self::error_response(
+                        400,
+                        "BAD_PAYLOAD",
+                        "The consumer system id must be a number",
+                        path,
+                    ),
+                }
+            }
+            _ => Self::error_response(404, "DATA_NOT_FOUND", "Unknown endpoint", path),
+        };
+        let _ = request.respond(response);
+    }
+
+    fn read_json<T: DeserializeOwned>(
+        request: &mut Request,
+        origin: &str,
+    ) -> std::result::Result<T, MockResponse> {
+        serde_json::from_reader(request.as_reader())
+            .map_err(|e| Self::error_response(400, "BAD_PAYLOAD", &format!("{}", e), origin))
+    }
+
+    fn json_response<T: Serialize>(status_code: u16, body: &T) -> MockResponse {
+        let content_type = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+            .expect("static header is valid");
+        Response::from_string(serde_json::to_string(body).unwrap_or_default())
+            .with_status_code(status_code)
+            .with_header(content_type)
+    }
+
+    fn error_response(
+        status_code: u16,
+        exception_type: &str,
+        message: &str,
+        origin: &str,
+    ) -> MockResponse {
+        Self::json_response(
+            status_code,
+            &ArrowheadServerException {
+                error_message: message.to_owned(),
+                error_code: status_code.into(),
+                exception_type: exception_type.to_owned(),
+                origin: origin.to_owned(),
+            },
+        )
+    }
+}
+
+impl Drop for MockCore {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+impl MockCoreState {
+    fn register(&mut self, service: ArrowheadService<NoEntryTag>, origin: &str) -> MockResponse {
+        let service_definition = service
+            .service_definition
+            .get_service_definition()
+            .to_lowercase();
+        if service_definition.is_empty() || service.interfaces.is_empty() {
+            return MockCore::error_response(
+                400,
+                "INVALID_PARAMETER",
+                "Service definition and interfaces must be specified",
+                origin,
+            );
+        }
+        let provider = &service.provider_system;
+        let duplicate = self.services.iter().any(|s| {
+            s.service_definition.get_service_definition() == service_definition
+                && s.provider_system.system_name == provider.system_name
+                && s.provider_system.address == provider.address
+                && s.provider_system.port == provider.port
+                && s.service_uri == service.service_uri
+        });
+        if duplicate {
+            return MockCore::error_response(
+                400,
+                "INVALID_PARAMETER",
+                "Service Registry entry already exists",
+                origin,
+            );
+        }
+
+        let provider_system = self.system_entry(&service.provider_system);
+        let entry = ArrowheadService {
+            entry_tag: self.new_entry_tag(),
+            service_definition: ServiceDefinitionEntry::Entry {
+                entry_tag: self.new_entry_tag(),
+                service_definition,
+            },
+            provider_system,
+            service_uri: service.service_uri,
+            end_of_validity: service.end_of_validity,
+            secure: Some(service.secure.unwrap_or(SecurityType::NotSecure)),
+            metadata: service.metadata,
+            version: Some(service.version.unwrap_or(1)),
+            interfaces: service
+                .interfaces
+                .iter()
+                .map(|i| InterfaceEntry::Entry {
+                    entry_tag: self.new_entry_tag(),
+                    interface_name: i.get_interface_name().to_uppercase(),
+                })
+                .collect(),
+        };
+        self.services.push(entry.clone());
+        MockCore::json_response(201, &entry)
+    }
+
+    fn unregister(&mut self, query: &HashMap<String, String>, origin: &str) -> MockResponse {
+        let param = |name: &str| query.get(name).map(String::as_str).unwrap_or("");
+        let service_definition = param("service_definition").to_lowercase();
+        let count = self.services.len();
+        self.services.retain(|s| {
+            !(s.service_definition.get_service_definition() == service_definition
+                && s.provider_system.system_name == param("system_name")
+                && s.provider_system.address == param("address")
+                && s.provider_system.port.to_string() == param("port")
+                && (param("service_uri").is_empty() || s.service_uri == param("service_uri")))
+        });
+        if self.services.len() == count {
+            MockCore::error_response(
+                400,
+                "INVALID_PARAMETER",
+                "Service Registry entry does not exist",
+                origin,
+            )
+        } else {
+            Response::from_string("")
+        }
+    }
+
+    fn delete_by_id(&mut self, id: u32, origin: &str) -> MockResponse {
+        let count = self.services.len();
+        self.services.retain(|s| s.entry_tag.id != id);
+        if self.services.len() == count {
+            MockCore::error_response(
+                400,
+                "INVALID_PARAMETER",
+                &format!("Service Registry entry with id '{}' does not exist", id),
+                origin,
+            )
+        } else {
+            Response::from_string("")
+        }
+    }
+
+    fn query(&self, form: &ServiceQueryForm) -> ServiceQueryList {
+        let requirements = &form.service_requirements;
+        let same_definition: Vec<&ArrowheadService<EntryTag>> = self
+            .services
+            .iter()
+            .filter(|s| {
+                s.service_definition
+                    .get_service_definition()
+                    .eq_ignore_ascii_case(&requirements.service_definition_requirement)
+            })
+            .collect();
+        ServiceQueryList {
+            unfiltered_hits: same_definition.len() as u32,
+            service_query_data: same_definition
+                .into_iter()
+                .filter(|s| Self::satisfies(s, requirements, true))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    fn orchestrate(&mut self, form: ServiceRequestForm) -> OrchestrationResponse {
+        let flag = |key| {
+            form.orchestration_flags
+                .as_ref()
+                .and_then(|flags| flags.get(&key))
+                .copied()
+                .unwrap_or(false)
+        };
+        let requester = self.system_entry(&form.requester_system);
+        let requirements = &form.requested_service;
+        let metadata_search = flag(OrchestrationFlagKey::MetadataSearch);
+
+        let mut candidates: Vec<&ArrowheadService<EntryTag>> =
+            if flag(OrchestrationFlagKey::OverrideStore) {
+                self.services
+                    .iter()
+                    .filter(|s| {
+                        s.service_definition
+                            .get_service_definition()
+                            .eq_ignore_ascii_case(&requirements.service_definition_requirement)
+                            && Self::satisfies(s, requirements, metadata_search)
+                    })
+                    .collect()
+            } else {
+                self.store_candidates(&requester.system_name, Some(requirements), metadata_search)
+            };
+
+        let preferred = form.preferred_providers.unwrap_or_default();
+        let is_preferred = |s: &ArrowheadService<EntryTag>| {
+            preferred.iter().any(|p| {
+                p.provider_system.system_name == s.provider_system.system_name
+                    && p.provider_system.address == s.provider_system.address
+                    && p.provider_system.port == s.provider_system.port
+            })
+        };
+        if flag(OrchestrationFlagKey::OnlyPreferred) {
+            candidates.retain(|s| is_preferred(s));
+        } else {
+            candidates.sort_by_key(|s| !is_preferred(s));
+        }
+
+        OrchestrationResponse {
+            response: candidates.into_iter().map(Self::to_orchestration).collect(),
+        }
+    }
+
+    fn orchestrate_by_id(&self, consumer_id: u32, origin: &str) -> MockResponse {
+        match self.systems.iter().find(|s| s.entry_tag.id == consumer_id) {
+            Some(consumer) => MockCore::json_response(
+                200,
+                &OrchestrationResponse {
+                    response: self
+                        .store_candidates(&consumer.system_name, None, false)
+                        .into_iter()
+                        .map(Self::to_orchestration)
+                        .collect(),
+                },
+            ),
+            None => MockCore::error_response(
+                400,
+                "INVALID_PARAMETER",
+                "Consumer system with the given id does not exist",
+                origin,
+            ),
+        }
+    }
+
+    fn store_candidates(
+        &self,
+        consumer_system_name: &str,
+        requirements: Option<&ServiceRequirements>,
+        metadata_search: bool,
+    ) -> Vec<&ArrowheadService<EntryTag>> {
+        let mut rules: Vec<&MockStoreRule> = self
+            .store_rules
+            .iter()
+            .filter(|r| {
+                r.consumer_system_name == consumer_system_name
+                    && requirements.is_none_or(|req| {
+                        r.service_definition
+                            .eq_ignore_ascii_case(&req.service_definition_requirement)
+                    })
+            })
+            .collect();
+        rules.sort_by_key(|r| r.priority);
+        rules
+            .into_iter()
+            .flat_map(|r| {
+                self.services.iter().filter(move |s| {
+                    s.provider_system.system_name == r.provider_system_name
+                        && s.service_definition
+                            .get_service_definition()
+                            .eq_ignore_ascii_case(&r.service_definition)
+                        && requirements.is_none_or(|req| Self::satisfies(s, req, metadata_search))
+                })
+            })
+            .collect()
+    }
+
+    fn satisfies(
+        service: &ArrowheadService<EntryTag>,
+        requirements: &ServiceRequirements,
+        check_metadata: bool,
+    ) -> bool {
+        let interfaces_ok = requirements
+            .interface_requirements
+            .as_ref()
+            .is_none_or(|req| {
+                req.is_empty()
+                    || service.interfaces.iter().any(|i| {
+                        req.iter()
+                            .any(|r| r.eq_ignore_ascii_case(i.get_interface_name()))
+                    })
+            });
+        let security_ok = requirements
+            .security_requirements
+            .as_ref()
+            .is_none_or(|req| {
+                req.is_empty()
+                    || req.contains(service.secure.as_ref().unwrap_or(&SecurityType::NotSecure))
+            });
+        let metadata_ok = !check_metadata
+            || requirements
+                .metadata_requirements
+                .as_ref()
+                .is_none_or(|req| {
+                    req.iter().all(|(key, value)| {
+                        service
+                            .metadata
+                            .as_ref()
+                            .and_then(|m| m.get(key))
+                            .is_some_and(|v| v == value)
+                    })
+                });
+        let version = service.version.unwrap_or(1);
+        let version_ok = match requirements.version_requirement {
+            Some(required) => version == required,
+            None => {
+                requirements
+                    .min_version_requirement
+                    .is_none_or(|min| version >= min)
+                    && requirements
+                        .max_version_requirement
+                        .is_none_or(|max| version <= max)
+            }
+        };
+        interfaces_ok && security_ok && metadata_ok && version_ok
+    }
+
+    fn to_orchestration(service: &ArrowheadService<EntryTag>) -> Orchestration {
+        Orchestration {
+            provider: service.provider_system.clone(),
+            service: service.service_definition.clone(),
+            service_uri: service.service_uri.clone(),
+            secure: service.secure.clone().unwrap_or(SecurityType::NotSecure),
+            metadata: service.metadata.clone().unwrap_or_default(),
+            interfaces: service.interfaces.clone(),
+            version: service.version.unwrap_or(1),
+            authorization_tokens: None,
+            warnings: match service.end_of_validity {
+                Some(_) => vec![],
+                None => vec![OrchestrationWarning::TtlUnknown],
+            },
+        }
+    }
+
+    fn system_entry(&mut self, system: &ArrowheadSystem<NoEntryTag>) -> ArrowheadSystem<EntryTag> {
+        let existing = self.systems.iter().find(|s| {
+            s.system_name == system.system_name
+                && s.address == system.address
+                && s.port == system.port
+        });
+        if let Some(existing) = existing {
+            return existing.clone();
+        }
+        let entry = ArrowheadSystem {
+            entry_tag: self.new_entry_tag(),
+            system_name: system.system_name.clone(),
+            address: system.address.clone(),
+            port: system.port,
+            authentication_info: system.authentication_info.clone(),
+            metadata: system.metadata.clone(),
+        };
+        self.systems.push(entry.clone());
+        entry
+    }
+
+    fn new_entry_tag(&mut self) -> EntryTag {
+        self.next_id += 1;
+        let timestamp = Self::timestamp();
+        EntryTag {
+            id: self.next_id,
+            created_at: timestamp.clone(),
+            updated_at: timestamp,
+        }
+    }
+
+    fn timestamp() -> String {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        // Civil date from the days since the epoch, see http://howardhinnant.github.io/date_algorithms.html
+        let days = (seconds / 86400) as i64 + 719468;
+        let era = days.div_euclid(146097);
+        let day_of_era = days.rem_euclid(146097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        format!(
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            year,
+            month,
+            day,
+            seconds % 86400 / 3600,
+            seconds % 3600 / 60,
+            seconds % 60
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dtos::{
+        ArrowheadCloud, ArrowheadProvider, ArrowheadVersion, RegisterServiceInput,
+        RequestOrchestrationInput,
+    };
+
+    fn system(system_name: &str, port: u32) -> ArrowheadSystem<NoEntryTag> {
+        ArrowheadSystem {
+            entry_tag: NoEntryTag {},
+            system_name: system_name.to_owned(),
+            address: "127.0.0.1".to_owned(),
+            port,
+            authentication_info: None,
+            metadata: None,
+        }
+    }
+
+    fn register_input(service_definition: &str, version: u32) -> RegisterServiceInput {
+        RegisterServiceInput {
+            service_definition: ServiceDefinitionEntry::Value(service_definition.to_owned()),
+            service_uri: format!("/{}", service_definition),
+            end_of_validity: None,
+            secure: None,
+            metadata: Some(HashMap::from([("unit".to_owned(), "celsius".to_owned())])),
+            version: Some(version),
+            interfaces: vec![InterfaceEntry::Value("HTTP-INSECURE-JSON".to_owned())],
+        }
+    }
+
+    fn requirements(service_definition: &str) -> ServiceRequirements {
+        ServiceRequirements {
+            service_definition_requirement: service_definition.to_owned(),
+            interface_requirements: None,
+            security_requirements: None,
+            metadata_requirements: None,
+            version_requirement: None,
+            max_version_requirement: None,
+            min_version_requirement: None,
+        }
+    }
+
+    fn query_form(service_definition: &str) -> ServiceQueryForm {
+        ServiceQueryForm {
+            service_requirements: requirements(service_definition),
+            ping_providers: None,
+        }
+    }
+
+    fn orchestration_input(
+        service_definition: &str,
+        flags: &[OrchestrationFlagKey],
+    ) -> RequestOrchestrationInput {
+        RequestOrchestrationInput {
+            requested_service: requirements(service_definition),
+            preferred_providers: None,
+            orchestration_flags: Some(flags.iter().map(|f| (f.clone(), true)).collect()),
+        }
+    }
+
+    #[test]
+    fn echo_and_public_key() {
+        let core = MockCore::start().unwrap();
+        let adapter = core.adapter(system("consumer", 0)).unwrap();
+        core.set_public_key("configured-key");
+
+        assert!(adapter.echo_service_registry().is_ok());
+        assert!(adapter.echo_authorization().is_ok());
+        assert!(adapter.echo_orchestrator().is_ok());
+        assert!(matches!(adapter.get_public_key(), Ok(key) if key == "configured-key"));
+    }
+
+    #[test]
+    fn register_query_unregister() {
+        let core = MockCore::start().unwrap();
+        let provider = core.adapter(system("provider", 8080)).unwrap();
+
+        let registered = provider.register_service(register_input("temperature", 1));
+        assert!(matches!(&registered, Ok(s) if s.provider_system.system_name == "provider"));
+        assert!(matches!(
+            provider.register_service(register_input("temperature", 1)),
+            Err(Error::ArrowheadError(e)) if e.exception_type == "INVALID_PARAMETER"
+        ));
+        assert!(matches!(
+            provider.query_service(&query_form("temperature")),
+            Ok(list) if list.service_query_data.len() == 1 && list.unfiltered_hits == 1
+        ));
+
+        let mut form = query_form("temperature");
+        form.service_requirements.min_version_requirement = Some(2);
+        assert!(matches!(
+            provider.query_service(&form),
+            Ok(list) if list.service_query_data.is_empty() && list.unfiltered_hits == 1
+        ));
+
+        assert!(provider.unregister_service("temperature").is_ok());
+        assert!(core.services().is_empty());
+        assert!(matches!(
+            provider.unregister_service("temperature"),
+            Err(Error::ArrowheadError(_))
+        ));
+    }
+
+    #[test]
+    fn query_services() {
+        let core = MockCore::start().unwrap();
+        let provider = core.adapter(system("provider", 8080)).unwrap();
+        provider
+            .register_service(register_input("temperature", 1))
+            .unwrap();
+        let mut other_interface = query_form("temperature");
+        other_interface.service_requirements.interface_requirements =
+            Some(vec!["HTTP-SECURE-JSON".to_owned()]);
+        let forms = [
+            query_form("temperature"),
+            query_form("humidity"),
+            other_interface,
+        ];
+
+        for version in [ArrowheadVersion::V4_4, ArrowheadVersion::V4_6] {
+            let results = core
+                .adapter(system("consumer", 0))
+                .unwrap()
+                .with_version(version)
+                .query_services(&forms)
+                .unwrap();
+            assert_eq!(results.len(), 3);
+            assert_eq!(results[0].0, "temperature");
+            assert_eq!(results[0].1.service_query_data.len(), 1);
+            assert_eq!(results[1].0, "humidity");
+            assert!(
+                results[1].1.service_query_data.is_empty() && results[1].1.unfiltered_hits == 0
+            );
+            assert_eq!(results[2].0, "temperature");
+            assert!(
+                results[2].1.service_query_data.is_empty() && results[2].1.unfiltered_hits == 1
+            );
+        }
+    }
+
+    #[test]
+    fn unregister_exact_entry() {
+        let core = MockCore::start().unwrap();
+        let provider = core.adapter(system("provider", 8080)).unwrap();
+        let mut input = register_input("temperature", 1);
+        let first = provider.register_service(input.clone()).unwrap();
+        input.service_uri = "/celsius".to_owned();
+        let second = provider.register_service(input.clone()).unwrap();
+        input.service_uri = "/fahrenheit".to_owned();
+        let third = provider.register_service(input).unwrap();
+
+        assert!(provider.unregister_service_entry(&first).is_ok());
+        assert!(matches!(
+            provider.unregister_service_entry(&first),
+            Err(Error::ArrowheadError(_))
+        ));
+        assert!(provider
+            .unregister_service_uri("temperature", "/celsius")
+            .is_ok());
+        let remaining = core.services();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].service_uri, "/fahrenheit");
+
+        assert!(provider.delete_service_by_id(third.entry_tag.id).is_ok());
+        assert!(core.services().is_empty());
+        assert!(matches!(
+            provider.delete_service_by_id(second.entry_tag.id),
+            Err(Error::ArrowheadError(e)) if e.exception_type == "INVALID_PARAMETER"
+        ));
+    }
+
+    #[test]
+    fn dynamic_orchestration() {
+        let core = MockCore::start().unwrap();
+        let first = core.adapter(system("first", 8080)).unwrap();
+        let second = core.adapter(system("second", 8081)).unwrap();
+        let consumer = core.adapter(system("consumer", 0)).unwrap();
+        first
+            .register_service(register_input("temperature", 1))
+            .unwrap();
+        second
+            .register_service(register_input("temperature", 2))
+            .unwrap();
+
+        let response = consumer
+            .request_orchestration(orchestration_input(
+                "temperature",
+                &[OrchestrationFlagKey::OverrideStore],
+            ))
+            .unwrap();
+        assert_eq!(response.response.len(), 2);
+        assert!(response.response[0].warnings == vec![OrchestrationWarning::TtlUnknown]);
+
+        let mut input = orchestration_input(
+            "temperature",
+            &[
+                OrchestrationFlagKey::OverrideStore,
+                OrchestrationFlagKey::OnlyPreferred,
+            ],
+        );
+        input.preferred_providers = Some(vec![ArrowheadProvider {
+            provider_cloud: ArrowheadCloud {
+                operator: "operator".to_owned(),
+                name: "cloud".to_owned(),
+            },
+            provider_system: system("second", 8081),
+        }]);
+        let response = consumer.request_orchestration(input).unwrap();
+        assert!(
+            response.response.len() == 1 && response.response[0].provider.system_name == "second"
+        );
+    }
+
+    #[test]
+    fn store_orchestration() {
+        let core = MockCore::start().unwrap();
+        let provider = core.adapter(system("provider", 8080)).unwrap();
+        let consumer = core.adapter(system("consumer", 0)).unwrap();
+        provider
+            .register_service(register_input("temperature", 1))
+            .unwrap();
+
+        let response = consumer
+            .request_orchestration(orchestration_input("temperature", &[]))
+            .unwrap();
+        assert!(response.response.is_empty());
+
+        core.add_store_rule(MockStoreRule {
+            consumer_system_name: "consumer".to_owned(),
+            service_definition: "temperature".to_owned(),
+            provider_system_name: "provider".to_owned(),
+            priority: 1,
+        });
+        let response = consumer
+            .request_orchestration(orchestration_input("temperature", &[]))
+            .unwrap();
+        assert!(
+            response.response.len() == 1 && response.response[0].provider.system_name == "provider"
+        );
+
+        let consumer_id = core
+            .lock_state()
+            .systems
+            .iter()
+            .find(|s| s.system_name == "consumer")
+            .map(|s| s.entry_tag.id)
+            .unwrap();
+        assert!(matches!(
+            consumer.request_orchestration_by_id(consumer_id.into()),
+            Ok(response) if response.response.len() == 1
+        ));
+    }
+}
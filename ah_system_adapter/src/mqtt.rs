@@ -0,0 +1,651 @@
+use crate::error::{Error, Result};
+use crate::transport::{self, Transport};
+
+use reqwest::blocking::{Request, Response};
+use rumqttc::{Client, Event, MqttOptions, Packet, QoS};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Clone)]
+pub struct MqttConfig {
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+    pub credentials: Option<(String, String)>,
+    pub keep_alive: Duration,
+    pub request_timeout: Duration,
+    pub base_topic: String,
+}
+
+impl MqttConfig {
+    pub fn new(host: &str, port: u16, client_id: &str) -> Self {
+        Self {
+            host: host.to_owned(),
+            port,
+            client_id: client_id.to_owned(),
+            credentials: None,
+            keep_alive: Duration::from_secs(30),
+            request_timeout: Duration::from_secs(10),
+            base_topic: "ah".to_owned(),
+        }
+    }
+
+    pub fn with_credentials(mut self, username: &str, password: &str) -> Self {
+        self.credentials = Some((username.to_owned(), password.to_owned()));
+        self
+    }
+
+    pub fn with_request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.request_timeout = request_timeout;
+        self
+    }
+
+    pub fn with_base_topic(mut self, base_topic: &str) -> Self {
+        self.base_topic = base_topic.trim_end_matches('/').to_owned();
+        self
+    }
+}
+
+// The request published on the topic of the operation. The response is published on the
+// `replyTo` topic, which is unique for every request.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MqttRequest {
+    pub method: String,
+    pub reply_to: String,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub query_parameters: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub payload: Value,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MqttResponse {
+    pub status: u16,
+    #[serde(default)]
+    pub payload: Value,
+}
+
+type PendingRequests = Arc<Mutex<HashMap<String, Sender<Vec<u8>>>>>;
+
+// Carries the requests to the core systems through an MQTT broker. A request is published on
+// `<base topic>/<path of its URL>` (e.g. `ah/serviceregistry/register` for the address
+// `mqtt://broker/serviceregistry/`), the host of the URL being ignored, and its response is
+// received on `<base topic>/reply/<client id>/<request number>`.
+pub struct MqttTransport {
+    client: Client,
+    pending: PendingRequests,
+    stopping: Arc<AtomicBool>,
+    request_counter: AtomicU64,
+    config: MqttConfig,
+}
+
+impl MqttTransport {
+    // Connects to the broker and subscribes to the reply topics. Fails if the broker does not
+    // accept the connection within the request timeout.
+    pub fn connect(config: MqttConfig) -> Result<Self> {
+        let mut options = MqttOptions::new(&config.client_id, &config.host, config.port);
+        options.set_keep_alive(config.keep_alive);
+        if let Some((username, password)) = &config.credentials {
+            options.set_credentials(username, password);
+        }
+        let (client, mut connection) = Client::new(options, 16);
+        let pending: PendingRequests = Arc::new(Mutex::new(HashMap::new()));
+        let stopping = Arc::new(AtomicBool::new(false));
+        let (ready_sender, ready) = mpsc::channel();
+
+        let reply_filter = format!("{}/+", Self::reply_prefix(&config));
+        {
+            let client = client.clone();
+            let pending = pending.clone();
+            let stopping = stopping.clone();
+            thread::spawn(move || {
+                let mut ready_sender = Some(ready_sender);
+                for event in connection.iter() {
+                    match event {
+                        // The subscription is renewed on every reconnection.
+                        Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                            let _ = client.try_subscribe(reply_filter.as_str(), QoS::AtLeastOnce);
+                        }
+                        Ok(Event::Incoming(Packet::SubAck(_))) => {
+                            if let Some(sender) = ready_sender.take() {
+                                let _ = sender.send(Ok(()));
+                            }
+                        }
+                        Ok(Event::Incoming(Packet::Publish(publish))) => {
+                            let sender = pending
+                                .lock()
+                                .unwrap_or_else(|e| e.into_inner())
+                                .remove(&publish.topic);
+                            if let Some(sender) = sender {
+                                let _ = sender.send(publish.payload.to_vec());
+                            }
+                        }
+                        Ok(_) => {}
+                        Err(_) if stopping.load(Ordering::Relaxed) => break,
+                        Err(err) => match ready_sender.take() {
+                            Some(sender) => {
+                                let _ = sender.send(Err(err.to_string()));
+                                break;
+                            }
+                            None => {
+                                tracing::warn!(error = %err, "MQTT connection lost, reconnecting");
+                                thread::sleep(RECONNECT_DELAY);
+                            }
+                        },
+                    }
+                }
+            });
+        }
+
+        let connected = ready.recv_timeout(config.request_timeout);
+        let transport = Self {
+            client,
+            pending,
+            stopping,
+            request_counter: AtomicU64::new(0),
+            config,
+        };
+        match connected {
+            Ok(Ok(())) => Ok(transport),
+            Ok(Err(message)) => Err(Error::TransportError(format!(
+                "Could not connect to the MQTT broker: {}",
+                message
+            ))),
+            Err(_) => Err(Error::TransportError(format!(
+                "The MQTT broker did not accept the connection within {:?}",
+                transport.config.request_timeout
+            ))),
+        }
+    }
+
+    // Publishes the request and waits for its response.
+    fn exchange(&self, topic: &str, request: MqttRequest) -> Result<MqttResponse> {
+        let reply_to = request.reply_to.clone();
+        let request = serde_json::to_vec(&request)
+            .map_err(|e| Error::TransportError(format!("Invalid request: {}", e)))?;
+
+        let (sender, receiver) = mpsc::channel();
+        self.lock_pending().insert(reply_to.clone(), sender);
+        let received = self
+            .client
+            .publish(topic, QoS::AtLeastOnce, false, request)
+            .map_err(|e| Error::TransportError(format!("Could not publish on '{}': {}", topic, e)))
+            .and_then(|_| {
+                receiver
+                    .recv_timeout(self.config.request_timeout)
+                    .map_err(|_| {
+                        Error::TransportError(format!(
+                            "No response on '{}' within {:?}",
+                            topic, self.config.request_timeout
+                        ))
+                    })
+            });
+        self.lock_pending().remove(&reply_to);
+
+        serde_json::from_slice(&received?)
+            .map_err(|e| Error::TransportError(format!("Invalid response on '{}': {}", topic, e)))
+    }
+
+    fn reply_prefix(config: &MqttConfig) -> String {
+        format!("{}/reply/{}", config.base_topic, config.client_id)
+    }
+
+    fn lock_pending(&self) -> std::sync::MutexGuard<'_, HashMap<String, Sender<Vec<u8>>>> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Transport for MqttTransport {
+    // The body of the request is sent as the JSON payload, and a string payload of the response
+    // as text, e.g. the public key.
+    fn execute(&self, request: Request) -> Result<Response> {
+        let topic = format!(
+            "{}/{}",
+            self.config.base_topic,
+            request.url().path().trim_start_matches('/')
+        );
+        let body = transport::request_body(&request)?;
+        let payload = if body.is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_slice(body).map_err(|e| {
+                Error::TransportError(format!("The request on '{}' is not JSON: {}", topic, e))
+            })?
+        };
+        let response = self.exchange(
+            &topic,
+            MqttRequest {
+                method: request.method().as_str().to_owned(),
+                reply_to: format!(
+                    "{}/{}",
+                    Self::reply_prefix(&self.config),
+                    self.request_counter.fetch_add(1, Ordering::Relaxed)
+                ),
+                query_parameters: request.url().query_pairs().into_owned().collect(),
+                payload,
+            },
+        )?;
+        match response.payload {
+            Value::Null => transport::response(response.status, "application/json", Vec::new()),
+            Value::String(text) => {
+                transport::response(response.status, "text/plain", text.into_bytes())
+            }
+            payload => transport::response(
+                response.status,
+                "application/json",
+                payload.to_string().into_bytes(),
+            ),
+        }
+    }
+}
+
+impl Drop for MqttTransport {
+    fn drop(&mut self) {
+        self.stopping.store(true, Ordering::Relaxed);
+        let _ = self.client.try_disconnect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit_breaker::CircuitBreakerConfig;
+    use crate::dtos::{
+        ArrowheadSystem, InterfaceEntry, NoEntryTag, OrchestrationFlagKey, RegisterServiceInput,
+        RequestOrchestrationInput, ServiceDefinitionEntry, ServiceQueryForm, ServiceRequirements,
+    };
+    use crate::mock_core::MockCore;
+    use crate::ArrowheadSystemAdapter;
+
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+
+    type Subscriptions = Arc<Mutex<Vec<(usize, String, Arc<Mutex<TcpStream>>)>>>;
+
+    // A stand-in for an MQTT 3.1.1 broker, supporting just what the adapter and the bridge need.
+    // Messages are always delivered with QoS 0.
+    fn start_broker() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let subscriptions: Subscriptions = Arc::new(Mutex::new(Vec::new()));
+        thread::spawn(move || {
+            for (connection, stream) in listener.incoming().flatten().enumerate() {
+                let subscriptions = subscriptions.clone();
+                thread::spawn(move || {
+                    let _ = serve_connection(connection, stream, &subscriptions);
+                    subscriptions
+                        .lock()
+                        .unwrap()
+                        .retain(|(c, _, _)| *c != connection);
+                });
+            }
+        });
+        port
+    }
+
+    fn serve_connection(
+        connection: usize,
+        mut stream: TcpStream,
+        subscriptions: &Subscriptions,
+    ) -> std::io::Result<()> {
+        let writer = Arc::new(Mutex::new(stream.try_clone()?));
+        let send = |packet: &[u8]| writer.lock().unwrap().write_all(packet);
+        loop {
+            let mut header = [0u8; 1];
+            stream.read_exact(&mut header)?;
+            let mut length = 0usize;
+            for shift in (0..4).map(|i| i * 7) {
+                let mut byte = [0u8; 1];
+                stream.read_exact(&mut byte)?;
+                length |= ((byte[0] & 0x7f) as usize) << shift;
+                if byte[0] & 0x80 == 0 {
+                    break;
+                }
+            }
+            let mut body = vec![0u8; length];
+            stream.read_exact(&mut body)?;
+
+            match header[0] >> 4 {
+                // CONNECT
+                1 => send(&[0x20, 2, 0, 0])?,
+                // PUBLISH
+                3 => {
+                    let qos = (header[0] >> 1) & 3;
+                    let topic_length = u16::from_be_bytes([body[0], body[1]]) as usize;
+                    let topic = String::from_utf8_lossy(&body[2..2 + topic_length]).into_owned();
+                    let mut payload_start = 2 + topic_length;
+                    if qos > 0 {
+                        send(&[0x40, 2, body[payload_start], body[payload_start + 1]])?;
+                        payload_start += 2;
+                    }
+                    let packet = publish_packet(&topic, &body[payload_start..]);
+                    let receivers: Vec<_> = subscriptions
+                        .lock()
+                        .unwrap()
+                        .iter()
+                        .filter(|(_, filter, _)| topic_matches(filter, &topic))
+                        .map(|(_, _, receiver)| receiver.clone())
+                        .collect();
+                    for receiver in receivers {
+                        let _ = receiver.lock().unwrap().write_all(&packet);
+                    }
+                }
+                // SUBSCRIBE
+                8 => {
+                    let mut suback = vec![0x90, 2, body[0], body[1]];
+                    let mut position = 2;
+                    while position < body.len() {
+                        let filter_length =
+                            u16::from_be_bytes([body[position], body[position + 1]]) as usize;
+                        let filter = String::from_utf8_lossy(
+                            &body[position + 2..position + 2 + filter_length],
+                        )
+                        .into_owned();
+                        subscriptions
+                            .lock()
+                            .unwrap()
+                            .push((connection, filter, writer.clone()));
+                        position += 3 + filter_length;
+                        suback[1] += 1;
+                        suback.push(0);
+                    }
+                    send(&suback)?;
+                }
+                // UNSUBSCRIBE
+                10 => send(&[0xb0, 2, body[0], body[1]])?,
+                // PINGREQ
+                12 => send(&[0xd0, 0])?,
+                // DISCONNECT
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn publish_packet(topic: &str, payload: &[u8]) -> Vec<u8> {
+        let mut length = 2 + topic.len() + payload.len();
+        let mut packet = vec![0x30];
+        loop {
+            let byte = (length % 128) as u8;
+            length /= 128;
+            packet.push(if length > 0 { byte | 0x80 } else { byte });
+            if length == 0 {
+                break;
+            }
+        }
+        packet.extend_from_slice(&(topic.len() as u16).to_be_bytes());
+        packet.extend_from_slice(topic.as_bytes());
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    fn topic_matches(filter: &str, topic: &str) -> bool {
+        let mut levels = topic.split('/');
+        for level in filter.split('/') {
+            match (level, levels.next()) {
+                ("#", _) => return true,
+                ("+", Some(_)) => {}
+                (level, Some(topic_level)) if level == topic_level => {}
+                _ => return false,
+            }
+        }
+        levels.next().is_none()
+    }
+
+    // Stands in for the MQTT interface of the core systems by forwarding every request to the
+    // HTTP endpoints of the mock core.
+    fn start_bridge(broker_port: u16, core: &MockCore) {
+        let base_url = reqwest::Url::parse(core.base_url()).unwrap();
+        let options = MqttOptions::new("core-bridge", "127.0.0.1", broker_port);
+        let (client, mut connection) = Client::new(options, 16);
+        let (ready_sender, ready) = mpsc::channel();
+        thread::spawn(move || {
+            for event in connection.iter() {
+                match event {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        for system in ["serviceregistry", "authorization", "orchestrator"] {
+                            let filter = format!("ah/{}/#", system);
+                            client.try_subscribe(filter, QoS::AtMostOnce).unwrap();
+                        }
+                    }
+                    Ok(Event::Incoming(Packet::SubAck(_))) => {
+                        let _ = ready_sender.send(());
+                    }
+                    Ok(Event::Incoming(Packet::Publish(publish))) => {
+                        let client = client.clone();
+                        let base_url = base_url.clone();
+                        thread::spawn(move || {
+                            let request: MqttRequest =
+                                serde_json::from_slice(&publish.payload).unwrap();
+                            let response = forward(&base_url, &publish.topic, &request);
+                            client
+                                .publish(
+                                    request.reply_to,
+                                    QoS::AtMostOnce,
+                                    false,
+                                    serde_json::to_vec(&response).unwrap(),
+                                )
+                                .unwrap();
+                        });
+                    }
+                    Ok(_) => {}
+                    Err(_) => break,
+                }
+            }
+        });
+        for _ in 0..3 {
+            ready.recv_timeout(Duration::from_secs(5)).unwrap();
+        }
+    }
+
+    fn forward(base_url: &reqwest::Url, topic: &str, request: &MqttRequest) -> MqttResponse {
+        let mut url = base_url.join(topic.trim_start_matches("ah/")).unwrap();
+        url.query_pairs_mut()
+            .extend_pairs(&request.query_parameters);
+        let method = reqwest::Method::from_bytes(request.method.as_bytes()).unwrap();
+        let mut builder = reqwest::blocking::Client::new().request(method, url);
+        if !request.payload.is_null() {
+            builder = builder.json(&request.payload);
+        }
+        let response = builder.send().unwrap();
+        let status = response.status().as_u16();
+        let body = response.text().unwrap();
+        MqttResponse {
+            status,
+            payload: serde_json::from_str(&body).unwrap_or(Value::String(body)),
+        }
+    }
+
+    fn system(system_name: &str, port: u32) -> ArrowheadSystem<NoEntryTag> {
+        ArrowheadSystem {
+            entry_tag: NoEntryTag {},
+            system_name: system_name.to_owned(),
+            address: "127.0.0.1".to_owned(),
+            port,
+            authentication_info: None,
+            metadata: None,
+        }
+    }
+
+    fn requirements(service_definition: &str) -> ServiceRequirements {
+        ServiceRequirements {
+            service_definition_requirement: service_definition.to_owned(),
+            interface_requirements: None,
+            security_requirements: None,
+            metadata_requirements: None,
+            version_requirement: None,
+            max_version_requirement: None,
+            min_version_requirement: None,
+        }
+    }
+
+    fn adapter(
+        broker_port: u16,
+        config: MqttConfig,
+        client_system: ArrowheadSystem<NoEntryTag>,
+    ) -> Result<ArrowheadSystemAdapter> {
+        let address = |system: &str| format!("mqtt://127.0.0.1:{}/{}/", broker_port, system);
+        Ok(ArrowheadSystemAdapter::new(
+            &address("serviceregistry"),
+            &address("authorization"),
+            &address("orchestrator"),
+            client_system,
+        )?
+        .with_transport(MqttTransport::connect(config)?))
+    }
+
+    fn config(broker_port: u16, client_id: &str) -> MqttConfig {
+        MqttConfig::new("127.0.0.1", broker_port, client_id)
+            .with_request_timeout(Duration::from_secs(5))
+    }
+
+    #[test]
+    fn topic_filters() {
+        assert!(topic_matches("ah/reply/consumer/+", "ah/reply/consumer/3"));
+        assert!(!topic_matches("ah/reply/consumer/+", "ah/reply/consumer"));
+        assert!(topic_matches(
+            "ah/orchestrator/#",
+            "ah/orchestrator/orchestration/1"
+        ));
+        assert!(!topic_matches("ah/orchestrator/#", "ah/authorization/echo"));
+    }
+
+    #[test]
+    fn core_services_over_mqtt() {
+        let core = MockCore::start().unwrap();
+        core.set_public_key("mqtt-key");
+        let broker_port = start_broker();
+        start_bridge(broker_port, &core);
+        let provider = adapter(
+            broker_port,
+            config(broker_port, "provider"),
+            system("provider", 8080),
+        )
+        .unwrap();
+        let consumer = adapter(
+            broker_port,
+            config(broker_port, "consumer"),
+            system("consumer", 0),
+        )
+        .unwrap();
+
+        assert!(consumer.echo_service_registry().is_ok());
+        assert!(consumer.echo_authorization().is_ok());
+        assert!(consumer.echo_orchestrator().is_ok());
+        assert!(matches!(consumer.get_public_key(), Ok(key) if key == "mqtt-key"));
+
+        let input = RegisterServiceInput {
+            service_definition: ServiceDefinitionEntry::Value("temperature".to_owned()),
+            service_uri: "/temperature".to_owned(),
+            end_of_validity: None,
+            secure: None,
+            metadata: None,
+            version: Some(1),
+            interfaces: vec![InterfaceEntry::Value("HTTP-INSECURE-JSON".to_owned())],
+        };
+        let registered = provider.register_service(input.clone()).unwrap();
+        assert!(registered.provider_system.system_name == "provider");
+        assert!(matches!(
+            provider.register_service(input),
+            Err(Error::ArrowheadError(e)) if e.exception_type == "INVALID_PARAMETER"
+        ));
+        let form = |service_definition: &str| ServiceQueryForm {
+            service_requirements: requirements(service_definition),
+            ping_providers: None,
+        };
+        assert!(matches!(
+            consumer.query_service(&form("temperature")),
+            Ok(list) if list.service_query_data.len() == 1
+        ));
+        // The parallel queries share the connection of the transport.
+        let results = consumer
+            .query_services(&[form("temperature"), form("humidity")])
+            .unwrap();
+        assert!(results[0].1.service_query_data.len() == 1);
+        assert!(results[1].1.service_query_data.is_empty());
+
+        let response = consumer
+            .request_orchestration(RequestOrchestrationInput {
+                requested_service: requirements("temperature"),
+                preferred_providers: None,
+                orchestration_flags: Some(HashMap::from([(
+                    OrchestrationFlagKey::OverrideStore,
+                    true,
+                )])),
+            })
+            .unwrap();
+        assert!(
+            response.response.len() == 1 && response.response[0].provider.system_name == "provider"
+        );
+
+        assert!(provider.unregister_service_entry(&registered).is_ok());
+        assert!(core.services().is_empty());
+        assert!(matches!(
+            provider.unregister_service("temperature"),
+            Err(Error::ArrowheadError(_))
+        ));
+        let registered = provider
+            .register_service(RegisterServiceInput {
+                service_definition: ServiceDefinitionEntry::Value("humidity".to_owned()),
+                service_uri: "/humidity".to_owned(),
+                end_of_validity: None,
+                secure: None,
+                metadata: None,
+                version: None,
+                interfaces: vec![InterfaceEntry::Value("HTTP-INSECURE-JSON".to_owned())],
+            })
+            .unwrap();
+        assert!(provider
+            .delete_service_by_id(registered.entry_tag.id)
+            .is_ok());
+        assert!(core.services().is_empty());
+    }
+
+    #[test]
+    fn no_response() {
+        let broker_port = start_broker();
+        let consumer = adapter(
+            broker_port,
+            config(broker_port, "unanswered").with_request_timeout(Duration::from_millis(500)),
+            system("consumer", 0),
+        )
+        .unwrap()
+        .with_circuit_breaker(CircuitBreakerConfig {
+            failure_threshold: 1,
+            cool_down: Duration::from_secs(60),
+        });
+
+        // Nobody answers on this broker, so the request times out once it is published.
+        assert!(matches!(
+            consumer.echo_orchestrator(),
+            Err(Error::TransportError(message)) if message.contains("No response")
+        ));
+        assert!(matches!(
+            consumer.echo_orchestrator(),
+            Err(Error::CircuitOpenError(_))
+        ));
+    }
+
+    #[test]
+    fn connection_refused() {
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        assert!(matches!(
+            MqttTransport::connect(config(port, "refused")),
+            Err(Error::TransportError(_))
+        ));
+    }
+}
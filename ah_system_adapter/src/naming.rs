@@ -0,0 +1,310 @@
+use crate::dtos::{ArrowheadService, ArrowheadSystem, ServiceRequestForm};
+use crate::error::{Error, Result};
+
+use std::collections::HashMap;
+
+// The naming conventions enforced by the core systems from Arrowhead 4.4 on. System names and
+// service definitions are DNS labels: lowercase letters, digits and hyphens, starting with a
+// letter and not ending with a hyphen. Metadata keys are segments of letters (of any case), digits,
+// hyphens and underscores starting with a letter, separated by dots. The lengths and the
+// positions in the messages are counted in characters.
+pub const MAX_NAME_LENGTH: usize = 63;
+pub const MAX_METADATA_KEY_LENGTH: usize = 255;
+pub const MAX_SERVICE_URI_LENGTH: usize = 255;
+
+pub fn validate_system_name(system_name: &str) -> Result<()> {
+    validate_label("system name", system_name)
+}
+
+pub fn validate_service_definition(service_definition: &str) -> Result<()> {
+    validate_label("service definition", service_definition)
+}
+
+pub fn validate_metadata_key(key: &str) -> Result<()> {
+    let length = key.chars().count();
+    if length > MAX_METADATA_KEY_LENGTH {
+        return Err(invalid(
+            "metadata key",
+            key,
+            &format!(
+                "it is {} characters long, the limit is {}",
+                length, MAX_METADATA_KEY_LENGTH
+            ),
+        ));
+    }
+    let mut offset = 0;
+    key.split('.').try_for_each(|segment| {
+        validate_metadata_key_segment(key, segment, offset)?;
+        offset += segment.chars().count() + 1;
+        Ok(())
+    })
+}
+
+fn validate_metadata_key_segment(key: &str, segment: &str, offset: usize) -> Result<()> {
+    if segment.is_empty() {
+        let reason = if key.is_empty() {
+            "it is empty".to_owned()
+        } else {
+            format!("empty segment at position {}", offset + 1)
+        };
+        return Err(invalid("metadata key", key, &reason));
+    }
+    for (index, c) in segment.chars().enumerate() {
+        let reason = match c {
+            'a'..='z' | 'A'..='Z' => continue,
+            '0'..='9' | '-' | '_' if index > 0 => continue,
+            _ if index == 0 => format!(
+                "every segment must start with a letter, not {:?} at position {}",
+                c,
+                offset + index + 1
+            ),
+            _ => format!(
+                "character {:?} at position {} is not allowed, only letters, digits, hyphens, \
+                 underscores and dots are",
+                c,
+                offset + index + 1
+            ),
+        };
+        return Err(invalid("metadata key", key, &reason));
+    }
+    Ok(())
+}
+
+// The service URI is the path of the service on the provider; it may be empty.
+pub fn validate_service_uri(service_uri: &str) -> Result<()> {
+    let length = service_uri.chars().count();
+    if length > MAX_SERVICE_URI_LENGTH {
+        return Err(invalid(
+            "service URI",
+            service_uri,
+            &format!(
+                "it is {} characters long, the limit is {}",
+                length, MAX_SERVICE_URI_LENGTH
+            ),
+        ));
+    }
+    match service_uri
+        .chars()
+        .enumerate()
+        .find(|(_, c)| c.is_whitespace() || c.is_control())
+    {
+        Some((position, c)) => Err(invalid(
+            "service URI",
+            service_uri,
+            &format!(
+                "character {:?} at position {} is not allowed, whitespace and control \
+                 characters must be percent-encoded",
+                c,
+                position + 1
+            ),
+        )),
+        None => Ok(()),
+    }
+}
+
+pub(crate) fn validate_system<T>(system: &ArrowheadSystem<T>) -> Result<()> {
+    validate_system_name(&system.system_name)?;
+    validate_metadata(system.metadata.as_ref())
+}
+
+pub(crate) fn validate_service<T>(service: &ArrowheadService<T>) -> Result<()> {
+    validate_system(&service.provider_system)?;
+    validate_service_definition(service.service_definition.get_service_definition())?;
+    validate_service_uri(&service.service_uri)?;
+    validate_metadata(service.metadata.as_ref())
+}
+
+pub(crate) fn validate_service_request_form(form: &ServiceRequestForm) -> Result<()> {
+    validate_system(&form.requester_system)?;
+    validate_service_definition(&form.requested_service.service_definition_requirement)?;
+    validate_metadata(form.requested_service.metadata_requirements.as_ref())?;
+    form.preferred_providers
+        .iter()
+        .flatten()
+        .try_for_each(|provider| validate_system(&provider.provider_system))
+}
+
+fn validate_metadata(metadata: Option<&HashMap<String, String>>) -> Result<()> {
+    metadata
+        .into_iter()
+        .flat_map(HashMap::keys)
+        .try_for_each(|key| validate_metadata_key(key))
+}
+
+fn validate_label(what: &str, label: &str) -> Result<()> {
+    if label.is_empty() {
+        return Err(invalid(what, label, "it is empty"));
+    }
+    let length = label.chars().count();
+    if length > MAX_NAME_LENGTH {
+        return Err(invalid(
+            what,
+            label,
+            &format!(
+                "it is {} characters long, the limit is {}",
+                length, MAX_NAME_LENGTH
+            ),
+        ));
+    }
+    for (index, c) in label.chars().enumerate() {
+        let reason = match c {
+            'a'..='z' => continue,
+            '0'..='9' | '-' if index > 0 => continue,
+            'A'..='Z' => format!(
+                "uppercase letter '{}' at position {} is not allowed, use '{}'",
+                c,
+                index + 1,
+                c.to_ascii_lowercase()
+            ),
+            _ if index == 0 => format!(
+                "it must start with a lowercase letter, not {:?} at position 1",
+                c
+            ),
+            _ => format!(
+                "character {:?} at position {} is not allowed, only lowercase letters, digits \
+                 and hyphens are",
+                c,
+                index + 1
+            ),
+        };
+        return Err(invalid(what, label, &reason));
+    }
+    if label.ends_with('-') {
+        return Err(invalid(
+            what,
+            label,
+            &format!("it must not end with a hyphen at position {}", length),
+        ));
+    }
+    Ok(())
+}
+
+fn invalid(what: &str, name: &str, reason: &str) -> Error {
+    Error::ValidationError(format!("Invalid {} '{}': {}", what, name, reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(result: Result<()>) -> String {
+        match result {
+            Err(Error::ValidationError(message)) => message,
+            _ => panic!("validation error expected"),
+        }
+    }
+
+    #[test]
+    fn names() {
+        assert!(validate_system_name("thermometer-2").is_ok());
+        assert!(validate_service_definition(&"a".repeat(MAX_NAME_LENGTH)).is_ok());
+
+        assert_eq!(
+            message(validate_system_name("Thermometer")),
+            "Invalid system name 'Thermometer': uppercase letter 'T' at position 1 is not \
+             allowed, use 't'"
+        );
+        assert_eq!(
+            message(validate_system_name("thermo_meter")),
+            "Invalid system name 'thermo_meter': character '_' at position 7 is not allowed, \
+             only lowercase letters, digits and hyphens are"
+        );
+        assert_eq!(
+            message(validate_service_definition("2nd-temperature")),
+            "Invalid service definition '2nd-temperature': it must start with a lowercase \
+             letter, not '2' at position 1"
+        );
+        assert_eq!(
+            message(validate_service_definition("temperature-")),
+            "Invalid service definition 'temperature-': it must not end with a hyphen at \
+             position 12"
+        );
+        assert_eq!(
+            message(validate_system_name("")),
+            "Invalid system name '': it is empty"
+        );
+        assert!(
+            message(validate_system_name(&"a".repeat(MAX_NAME_LENGTH + 1)))
+                .ends_with("it is 64 characters long, the limit is 63")
+        );
+        assert!(
+            message(validate_system_name(&"\u{e9}".repeat(MAX_NAME_LENGTH)))
+                .ends_with("it must start with a lowercase letter, not '\u{e9}' at position 1")
+        );
+        assert!(
+            message(validate_system_name(&"\u{e9}".repeat(MAX_NAME_LENGTH + 1)))
+                .ends_with("it is 64 characters long, the limit is 63")
+        );
+    }
+
+    #[test]
+    fn metadata_keys() {
+        assert!(validate_metadata_key("unit").is_ok());
+        assert!(validate_metadata_key("sensor.unitType_2").is_ok());
+
+        assert_eq!(
+            message(validate_metadata_key("sensor.unit type")),
+            "Invalid metadata key 'sensor.unit type': character ' ' at position 12 is not \
+             allowed, only letters, digits, hyphens, underscores and dots are"
+        );
+        assert_eq!(
+            message(validate_metadata_key("sensor.2nd")),
+            "Invalid metadata key 'sensor.2nd': every segment must start with a letter, not '2' \
+             at position 8"
+        );
+        assert_eq!(
+            message(validate_metadata_key("sensor..unit")),
+            "Invalid metadata key 'sensor..unit': empty segment at position 8"
+        );
+        assert_eq!(
+            message(validate_metadata_key("sensor.\u{e9}t\u{e9}..unit")),
+            "Invalid metadata key 'sensor.\u{e9}t\u{e9}..unit': every segment must start with \
+             a letter, not '\u{e9}' at position 8"
+        );
+    }
+
+    #[test]
+    fn service_uris() {
+        assert!(validate_service_uri("").is_ok());
+        assert!(validate_service_uri("/temperature/celsius%20scale").is_ok());
+
+        assert_eq!(
+            message(validate_service_uri("/temperature/celsius scale")),
+            "Invalid service URI '/temperature/celsius scale': character ' ' at position 21 \
+             is not allowed, whitespace and control characters must be percent-encoded"
+        );
+        assert!(
+            message(validate_service_uri(&"/a".repeat(MAX_SERVICE_URI_LENGTH)))
+                .ends_with("it is 510 characters long, the limit is 255")
+        );
+        // Multi-byte characters count once, in the length and in the positions.
+        assert!(validate_service_uri(&"\u{e9}".repeat(MAX_SERVICE_URI_LENGTH)).is_ok());
+        assert!(message(validate_service_uri("/caf\u{e9} cr\u{e8}me"))
+            .contains("character ' ' at position 6"));
+    }
+
+    #[test]
+    fn service_request_form() {
+        let form = |provider_name: &str| -> ServiceRequestForm {
+            serde_json::from_value(serde_json::json!({
+                "requesterSystem": { "systemName": "consumer", "address": "127.0.0.1", "port": 0 },
+                "requestedService": {
+                    "serviceDefinitionRequirement": "temperature",
+                    "metadataRequirements": { "unit": "celsius" }
+                },
+                "preferredProviders": [{
+                    "providerCloud": { "operator": "aitia", "name": "testcloud" },
+                    "providerSystem": { "systemName": provider_name, "address": "127.0.0.1", "port": 0 }
+                }]
+            }))
+            .unwrap()
+        };
+
+        assert!(validate_service_request_form(&form("thermometer")).is_ok());
+        assert_eq!(
+            message(validate_service_request_form(&form("thermometer.local"))),
+            "Invalid system name 'thermometer.local': character '.' at position 12 is not \
+             allowed, only lowercase letters, digits and hyphens are"
+        );
+    }
+}
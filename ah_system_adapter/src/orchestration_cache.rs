@@ -0,0 +1,414 @@
+use crate::core_system::Orchestrator;
+use crate::dtos::{OrchestrationResponse, OrchestrationWarning, RequestOrchestrationInput};
+use crate::error::Result;
+use crate::ArrowheadSystemAdapter;
+
+use serde_json::Value;
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(PartialEq, Clone)]
+pub struct OrchestrationCacheConfig {
+    pub ttl: Duration,
+    pub expiring_ttl: Duration,
+}
+impl Default for OrchestrationCacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl: Duration::from_secs(300),
+            expiring_ttl: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Default)]
+pub struct OrchestrationCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub refreshes: u64,
+    pub refresh_failures: u64,
+    pub expirations: u64,
+    pub invalidations: u64,
+}
+
+struct CacheEntry {
+    response: OrchestrationResponse,
+    refresh_at: Instant,
+    expires_at: Instant,
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<String, CacheEntry>,
+    stats: OrchestrationCacheStats,
+}
+
+pub struct OrchestrationCache<O: Orchestrator = ArrowheadSystemAdapter> {
+    adapter: O,
+    config: OrchestrationCacheConfig,
+    state: Mutex<CacheState>,
+}
+
+impl<O: Orchestrator> OrchestrationCache<O> {
+    pub fn new(adapter: O, config: OrchestrationCacheConfig) -> Self {
+        Self {
+            adapter,
+            config,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    pub fn adapter(&self) -> &O {
+        &self.adapter
+    }
+
+    pub fn request_orchestration(
+        &self,
+        input: &RequestOrchestrationInput,
+    ) -> Result<OrchestrationResponse> {
+        let key = Self::cache_key(input);
+        let now = Instant::now();
+        let cached = {
+            let mut state = self.lock_state();
+            match state.entries.get(&key) {
+                Some(entry) if entry.expires_at <= now => {
+                    state.entries.remove(&key);
+                    state.stats.expirations += 1;
+                    state.stats.misses += 1;
+                    None
+                }
+                Some(entry) if entry.refresh_at <= now => Some(entry.response.clone()),
+                Some(entry) => {
+                    let response = entry.response.clone();
+                    state.stats.hits += 1;
+                    return Ok(response);
+                }
+                None => {
+                    state.stats.misses += 1;
+                    None
+                }
+            }
+        };
+
+        match (self.adapter.request_orchestration(input.clone()), cached) {
+            (Ok(response), cached) => {
+                let mut state = self.lock_state();
+                if cached.is_some() {
+                    state.stats.refreshes += 1;
+                }
+                match self.lifetime(&response) {
+                    Some((refresh_after, expires_after)) => {
+                        state.entries.insert(
+                            key,
+                            CacheEntry {
+                                response: response.clone(),
+                                refresh_at: now + refresh_after,
+                                expires_at: now + expires_after,
+                            },
+                        );
+                    }
+                    None => {
+                        state.entries.remove(&key);
+                    }
+                }
+                Ok(response)
+            }
+            (Err(_), Some(cached)) => {
+                let mut state = self.lock_state();
+                state.stats.refresh_failures += 1;
+                state.stats.hits += 1;
+                Ok(cached)
+            }
+            (Err(err), None) => Err(err),
+        }
+    }
+
+    pub fn invoke_with<R, F>(&self, input: &RequestOrchestrationInput, mut call: F) -> Result<R>
+    where
+        F: FnMut(&OrchestrationResponse) -> Result<R>,
+    {
+        let response = self.request_orchestration(input)?;
+        match call(&response) {
+            Ok(result) => Ok(result),
+            Err(_) => {
+                self.invalidate(input);
+                let response = self.request_orchestration(input)?;
+                call(&response)
+            }
+        }
+    }
+
+    pub fn invalidate(&self, input: &RequestOrchestrationInput) {
+        let mut state = self.lock_state();
+        if state.entries.remove(&Self::cache_key(input)).is_some() {
+            state.stats.invalidations += 1;
+        }
+    }
+
+    pub fn invalidate_all(&self) {
+        let mut state = self.lock_state();
+        state.stats.invalidations += state.entries.len() as u64;
+        state.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock_state().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn stats(&self) -> OrchestrationCacheStats {
+        self.lock_state().stats
+    }
+
+    // Results without providers or with expired providers are not worth caching, results with
+    // expiring providers are kept for a shorter time and refreshed on the next access.
+    fn lifetime(&self, response: &OrchestrationResponse) -> Option<(Duration, Duration)> {
+        let has_warning = |warning| {
+            response
+                .response
+                .iter()
+                .any(|o| o.warnings.contains(&warning))
+        };
+        if response.response.is_empty() || has_warning(OrchestrationWarning::TtlExpired) {
+            None
+        } else if has_warning(OrchestrationWarning::TtlExpiring) {
+            Some((
+                Duration::ZERO,
+                self.config.expiring_ttl.min(self.config.ttl),
+            ))
+        } else {
+            Some((self.config.ttl, self.config.ttl))
+        }
+    }
+
+    fn lock_state(&self) -> std::sync::MutexGuard<'_, CacheState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn cache_key(input: &RequestOrchestrationInput) -> String {
+        serde_json::to_value(input)
+            .map(|v| Self::canonical_json(&v))
+            .unwrap_or_default()
+    }
+
+    fn canonical_json(value: &Value) -> String {
+        match value {
+            Value::Object(map) => {
+                let mut entries: Vec<_> = map.iter().collect();
+                entries.sort_by(|a, b| a.0.cmp(b.0));
+                let entries: Vec<String> = entries
+                    .into_iter()
+                    .map(|(k, v)| {
+                        format!("{}:{}", Value::from(k.as_str()), Self::canonical_json(v))
+                    })
+                    .collect();
+                format!("{{{}}}", entries.join(","))
+            }
+            Value::Array(values) => {
+                let values: Vec<String> = values.iter().map(Self::canonical_json).collect();
+                format!("[{}]", values.join(","))
+            }
+            _ => value.to_string(),
+        }
+    }
+}
+
+impl<O: Orchestrator> Orchestrator for OrchestrationCache<O> {
+    fn echo_orchestrator(&self) -> Result<()> {
+        self.adapter.echo_orchestrator()
+    }
+
+    fn request_orchestration(
+        &self,
+        input: RequestOrchestrationInput,
+    ) -> Result<OrchestrationResponse> {
+        OrchestrationCache::request_orchestration(self, &input)
+    }
+
+    fn request_orchestration_by_id(&self, id: i64) -> Result<OrchestrationResponse> {
+        self.adapter.request_orchestration_by_id(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dtos::{ArrowheadSystem, NoEntryTag, OrchestrationFlagKey, ServiceRequirements};
+    use crate::Error;
+    use serde_json::json;
+
+    fn adapter() -> ArrowheadSystemAdapter {
+        ArrowheadSystemAdapter::new(
+            "http://dontcare",
+            "http://dontcare",
+            &mockito::server_url(),
+            ArrowheadSystem {
+                entry_tag: NoEntryTag {},
+                system_name: "string".to_owned(),
+                address: "string".to_owned(),
+                port: 0,
+                authentication_info: None,
+                metadata: None,
+            },
+        )
+        .unwrap()
+    }
+
+    fn input() -> RequestOrchestrationInput {
+        RequestOrchestrationInput {
+            requested_service: ServiceRequirements {
+                service_definition_requirement: "temperature".to_owned(),
+                interface_requirements: None,
+                security_requirements: None,
+                metadata_requirements: None,
+                version_requirement: None,
+                max_version_requirement: None,
+                min_version_requirement: None,
+            },
+            preferred_providers: None,
+            orchestration_flags: Some(HashMap::from([
+                (OrchestrationFlagKey::OverrideStore, true),
+                (OrchestrationFlagKey::MetadataSearch, true),
+                (OrchestrationFlagKey::PingProviders, false),
+            ])),
+        }
+    }
+
+    fn orchestration_response_body(warnings: Value) -> String {
+        json!({
+          "response": [
+            {
+              "provider": {
+                "id": 1,
+                "systemName": "provider",
+                "address": "127.0.0.1",
+                "port": 8080,
+                "createdAt": "string",
+                "updatedAt": "string"
+              },
+              "service": "temperature",
+              "serviceUri": "/temperature",
+              "secure": "NOT_SECURE",
+              "metadata": {},
+              "interfaces": ["HTTP-INSECURE-JSON"],
+              "version": 1,
+              "warnings": warnings
+            }
+          ]
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn serve_from_cache() {
+        let mock = mockito::mock("POST", "/orchestration")
+            .with_header("content-type", "application/json")
+            .with_body(orchestration_response_body(json!([])))
+            .expect(1)
+            .create();
+        let cache = OrchestrationCache::new(adapter(), OrchestrationCacheConfig::default());
+
+        assert!(cache.request_orchestration(&input()).is_ok());
+        assert!(cache.request_orchestration(&input()).is_ok());
+        assert!(cache.stats().hits == 1 && cache.stats().misses == 1);
+        assert_eq!(cache.len(), 1);
+        mock.assert();
+    }
+
+    #[test]
+    fn refresh_expiring_entry() {
+        let mock = mockito::mock("POST", "/orchestration")
+            .with_header("content-type", "application/json")
+            .with_body(orchestration_response_body(json!(["TTL_EXPIRING"])))
+            .expect(2)
+            .create();
+        let cache = OrchestrationCache::new(adapter(), OrchestrationCacheConfig::default());
+
+        assert!(cache.request_orchestration(&input()).is_ok());
+        assert!(cache.request_orchestration(&input()).is_ok());
+        assert!(cache.stats().refreshes == 1 && cache.stats().misses == 1);
+        mock.assert();
+    }
+
+    #[test]
+    fn serve_expiring_entry_when_refresh_fails() {
+        let mock = mockito::mock("POST", "/orchestration")
+            .with_header("content-type", "application/json")
+            .with_body(orchestration_response_body(json!(["TTL_EXPIRING"])))
+            .expect(1)
+            .create();
+        let cache = OrchestrationCache::new(adapter(), OrchestrationCacheConfig::default());
+        assert!(cache.request_orchestration(&input()).is_ok());
+        mock.assert();
+        drop(mock);
+
+        let failing_mock = mockito::mock("POST", "/orchestration")
+            .with_status(503)
+            .create();
+        assert!(cache.request_orchestration(&input()).is_ok());
+        assert_eq!(cache.stats().refresh_failures, 1);
+        failing_mock.assert();
+    }
+
+    #[test]
+    fn do_not_cache_expired_or_outdated_entries() {
+        let mock = mockito::mock("POST", "/orchestration")
+            .with_header("content-type", "application/json")
+            .with_body(orchestration_response_body(json!(["TTL_EXPIRED"])))
+            .expect(2)
+            .create();
+        let cache = OrchestrationCache::new(adapter(), OrchestrationCacheConfig::default());
+
+        assert!(cache.request_orchestration(&input()).is_ok());
+        assert!(cache.request_orchestration(&input()).is_ok());
+        assert!(cache.is_empty());
+        mock.assert();
+        drop(mock);
+
+        let mock = mockito::mock("POST", "/orchestration")
+            .with_header("content-type", "application/json")
+            .with_body(orchestration_response_body(json!([])))
+            .expect(2)
+            .create();
+        let cache = OrchestrationCache::new(
+            adapter(),
+            OrchestrationCacheConfig {
+                ttl: Duration::ZERO,
+                expiring_ttl: Duration::ZERO,
+            },
+        );
+
+        assert!(cache.request_orchestration(&input()).is_ok());
+        assert!(cache.request_orchestration(&input()).is_ok());
+        assert_eq!(cache.stats().expirations, 1);
+        mock.assert();
+    }
+
+    #[test]
+    fn invalidate_on_invocation_failure() {
+        let mock = mockito::mock("POST", "/orchestration")
+            .with_header("content-type", "application/json")
+            .with_body(orchestration_response_body(json!([])))
+            .expect(2)
+            .create();
+        let cache = OrchestrationCache::new(adapter(), OrchestrationCacheConfig::default());
+        let mut calls = 0;
+        let result = cache.invoke_with(&input(), |_| {
+            calls += 1;
+            if calls == 1 {
+                Err(Error::InvocationError("string".to_owned()))
+            } else {
+                Ok(calls)
+            }
+        });
+
+        assert!(matches!(result, Ok(2)));
+        assert_eq!(cache.stats().invalidations, 1);
+        mock.assert();
+    }
+}
@@ -0,0 +1,594 @@
+use crate::dtos::{ArrowheadService, EntryTag, NoEntryTag, PageRequest};
+use crate::error::{Error, Result};
+use crate::instrumentation::{self, SendObserved, PLANT_DESCRIPTION_ENGINE};
+
+use reqwest::blocking::Client;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PlantDescription<T> {
+    #[serde(flatten)]
+    pub entry_tag: T,
+    pub plant_description: String,
+    pub active: bool,
+    #[serde(default)]
+    pub include: Vec<u32>,
+    pub systems: Vec<PlantSystem>,
+    pub connections: Vec<PlantConnection>,
+}
+impl<T> PlantDescription<T> {
+    pub fn compare_with_registrations(
+        &self,
+        registrations: &[ArrowheadService<EntryTag>],
+    ) -> Vec<PlantDescriptionMismatch> {
+        let mut mismatches = Vec::new();
+
+        for system in &self.systems {
+            for port in system.ports.iter().filter(|p| !p.consumer.unwrap_or(false)) {
+                let registered = registrations.iter().any(|r| {
+                    system.matches_provider(r)
+                        && r.service_definition.get_service_definition() == port.service_definition
+                });
+                if !registered {
+                    mismatches.push(PlantDescriptionMismatch::MissingRegistration {
+                        system_id: system.system_id.clone(),
+                        port_name: port.port_name.clone(),
+                        service_definition: port.service_definition.clone(),
+                    });
+                }
+            }
+        }
+
+        for registration in registrations {
+            let service_definition = registration.service_definition.get_service_definition();
+            let system = self
+                .systems
+                .iter()
+                .find(|s| s.matches_provider(registration));
+            if let Some(system) = system {
+                let declared = system.ports.iter().any(|p| {
+                    !p.consumer.unwrap_or(false) && p.service_definition == service_definition
+                });
+                if !declared {
+                    mismatches.push(PlantDescriptionMismatch::UnexpectedRegistration {
+                        system_id: system.system_id.clone(),
+                        service_definition: service_definition.to_owned(),
+                        service_uri: registration.service_uri.clone(),
+                    });
+                }
+            }
+        }
+
+        mismatches
+    }
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PlantSystem {
+    pub system_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<HashMap<String, String>>,
+    #[serde(default)]
+    pub ports: Vec<PlantSystemPort>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inventory_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub monitor_info: Option<Vec<PlantMonitorInfo>>,
+}
+impl PlantSystem {
+    pub fn arrowhead_system_name(&self) -> &str {
+        self.system_name.as_deref().unwrap_or(&self.system_id)
+    }
+
+    fn matches_provider(&self, registration: &ArrowheadService<EntryTag>) -> bool {
+        registration.provider_system.system_name == self.arrowhead_system_name()
+    }
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PlantSystemPort {
+    pub port_name: String,
+    pub service_definition: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_interface: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub consumer: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PlantConnection {
+    pub consumer: PlantConnectionEndpoint,
+    pub producer: PlantConnectionEndpoint,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u32>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PlantConnectionEndpoint {
+    pub system_id: String,
+    pub port_name: String,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PlantMonitorInfo {
+    pub service_definition: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_data: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inventory_id: Option<String>,
+}
+
+#[derive(Deserialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PlantDescriptionList {
+    pub data: Vec<PlantDescription<EntryTag>>,
+    pub count: u32,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum PlantAlarmSeverity {
+    Indeterminate,
+    Critical,
+    Major,
+    Minor,
+    Warning,
+    Cleared,
+}
+
+#[derive(Deserialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PlantAlarm {
+    pub id: u32,
+    pub system_id: Option<String>,
+    pub system_name: Option<String>,
+    pub acknowledged: bool,
+    pub severity: PlantAlarmSeverity,
+    pub description: String,
+    pub raised_at: String,
+    pub updated_at: String,
+    pub cleared_at: Option<String>,
+    pub acknowledged_at: Option<String>,
+}
+
+#[derive(Deserialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PlantAlarmList {
+    pub data: Vec<PlantAlarm>,
+    pub count: u32,
+}
+
+#[derive(PartialEq, Clone)]
+pub enum PlantDescriptionMismatch {
+    MissingRegistration {
+        system_id: String,
+        port_name: String,
+        service_definition: String,
+    },
+    UnexpectedRegistration {
+        system_id: String,
+        service_definition: String,
+        service_uri: String,
+    },
+}
+
+pub struct PlantDescriptionEngineAdapter {
+    pub plant_description_engine_address: Url,
+}
+
+impl PlantDescriptionEngineAdapter {
+    pub fn new(plant_description_engine_address: &str) -> Result<Self> {
+        Ok(PlantDescriptionEngineAdapter {
+            plant_description_engine_address: plant_description_engine_address.try_into()?,
+        })
+    }
+
+    pub fn get_plant_descriptions(
+        &self,
+        page_request: &PageRequest,
+    ) -> Result<PlantDescriptionList> {
+        instrumentation::observe(PLANT_DESCRIPTION_ENGINE, "get_plant_descriptions", || {
+            let client = Client::new();
+            let mut url = self.plant_description_engine_address.join("mgmt/pd")?;
+            page_request.append_query_pairs(&mut url);
+            let response = client.get(url).send_observed()?;
+            if response.status().is_client_error() {
+                Err(Error::ArrowheadError(response.json()?))
+            } else {
+                Ok(response.json()?)
+            }
+        })
+    }
+
+    pub fn get_plant_description(&self, id: u32) -> Result<PlantDescription<EntryTag>> {
+        instrumentation::observe(PLANT_DESCRIPTION_ENGINE, "get_plant_description", || {
+            let client = Client::new();
+            let response = client
+                .get(
+                    self.plant_description_engine_address
+                        .join(&format!("mgmt/pd/{}", id))?,
+                )
+                .send_observed()?;
+            if response.status().is_client_error() {
+                Err(Error::ArrowheadError(response.json()?))
+            } else {
+                Ok(response.json()?)
+            }
+        })
+    }
+
+    pub fn create_plant_description(
+        &self,
+        plant_description: &PlantDescription<NoEntryTag>,
+    ) -> Result<PlantDescription<EntryTag>> {
+        instrumentation::observe(PLANT_DESCRIPTION_ENGINE, "create_plant_description", || {
+            let client = Client::new();
+            let response = client
+                .post(self.plant_description_engine_address.join("mgmt/pd")?)
+                .json(plant_description)
+                .send_observed()?;
+            if response.status().is_client_error() {
+                Err(Error::ArrowheadError(response.json()?))
+            } else {
+                Ok(response.json()?)
+            }
+        })
+    }
+
+    pub fn replace_plant_description(
+        &self,
+        id: u32,
+        plant_description: &PlantDescription<NoEntryTag>,
+    ) -> Result<PlantDescription<EntryTag>> {
+        instrumentation::observe(
+            PLANT_DESCRIPTION_ENGINE,
+            "replace_plant_description",
+            || {
+                let client = Client::new();
+                let response = client
+                    .put(
+                        self.plant_description_engine_address
+                            .join(&format!("mgmt/pd/{}", id))?,
+                    )
+                    .json(plant_description)
+                    .send_observed()?;
+                if response.status().is_client_error() {
+                    Err(Error::ArrowheadError(response.json()?))
+                } else {
+                    Ok(response.json()?)
+                }
+            },
+        )
+    }
+
+    pub fn delete_plant_description(&self, id: u32) -> Result<()> {
+        instrumentation::observe(PLANT_DESCRIPTION_ENGINE, "delete_plant_description", || {
+            let client = Client::new();
+            let response = client
+                .delete(
+                    self.plant_description_engine_address
+                        .join(&format!("mgmt/pd/{}", id))?,
+                )
+                .send_observed()?;
+            if response.status().is_client_error() {
+                Err(Error::ArrowheadError(response.json()?))
+            } else {
+                response.error_for_status()?;
+                Ok(())
+            }
+        })
+    }
+
+    pub fn get_monitored_plant_descriptions(
+        &self,
+        page_request: &PageRequest,
+    ) -> Result<PlantDescriptionList> {
+        instrumentation::observe(
+            PLANT_DESCRIPTION_ENGINE,
+            "get_monitored_plant_descriptions",
+            || {
+                let client = Client::new();
+                let mut url = self.plant_description_engine_address.join("monitor/pd")?;
+                page_request.append_query_pairs(&mut url);
+                let response = client.get(url).send_observed()?;
+                if response.status().is_client_error() {
+                    Err(Error::ArrowheadError(response.json()?))
+                } else {
+                    Ok(response.json()?)
+                }
+            },
+        )
+    }
+
+    pub fn get_monitored_plant_description(&self, id: u32) -> Result<PlantDescription<EntryTag>> {
+        instrumentation::observe(
+            PLANT_DESCRIPTION_ENGINE,
+            "get_monitored_plant_description",
+            || {
+                let client = Client::new();
+                let response = client
+                    .get(
+                        self.plant_description_engine_address
+                            .join(&format!("monitor/pd/{}", id))?,
+                    )
+                    .send_observed()?;
+                if response.status().is_client_error() {
+                    Err(Error::ArrowheadError(response.json()?))
+                } else {
+                    Ok(response.json()?)
+                }
+            },
+        )
+    }
+
+    pub fn get_alarms(&self, page_request: &PageRequest) -> Result<PlantAlarmList> {
+        instrumentation::observe(PLANT_DESCRIPTION_ENGINE, "get_alarms", || {
+            let client = Client::new();
+            let mut url = self.plant_description_engine_address.join("mgmt/alarm")?;
+            page_request.append_query_pairs(&mut url);
+            let response = client.get(url).send_observed()?;
+            if response.status().is_client_error() {
+                Err(Error::ArrowheadError(response.json()?))
+            } else {
+                Ok(response.json()?)
+            }
+        })
+    }
+
+    pub fn get_alarm(&self, id: u32) -> Result<PlantAlarm> {
+        instrumentation::observe(PLANT_DESCRIPTION_ENGINE, "get_alarm", || {
+            let client = Client::new();
+            let response = client
+                .get(
+                    self.plant_description_engine_address
+                        .join(&format!("mgmt/alarm/{}", id))?,
+                )
+                .send_observed()?;
+            if response.status().is_client_error() {
+                Err(Error::ArrowheadError(response.json()?))
+            } else {
+                Ok(response.json()?)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dtos::{ArrowheadSystem, InterfaceEntry, ServiceDefinitionEntry};
+    use mockito::Matcher;
+    use serde_json::json;
+
+    fn plant_description() -> PlantDescription<NoEntryTag> {
+        PlantDescription {
+            entry_tag: NoEntryTag {},
+            plant_description: "plant".to_owned(),
+            active: true,
+            include: vec![],
+            systems: vec![
+                PlantSystem {
+                    system_id: "conveyor".to_owned(),
+                    system_name: Some("conveyor-a".to_owned()),
+                    metadata: None,
+                    ports: vec![PlantSystemPort {
+                        port_name: "speed".to_owned(),
+                        service_definition: "conveyor-speed".to_owned(),
+                        service_interface: Some("HTTP-INSECURE-JSON".to_owned()),
+                        consumer: None,
+                        metadata: None,
+                    }],
+                    inventory_id: None,
+                    monitor_info: None,
+                },
+                PlantSystem {
+                    system_id: "controller".to_owned(),
+                    system_name: None,
+                    metadata: None,
+                    ports: vec![PlantSystemPort {
+                        port_name: "speed".to_owned(),
+                        service_definition: "conveyor-speed".to_owned(),
+                        service_interface: None,
+                        consumer: Some(true),
+                        metadata: None,
+                    }],
+                    inventory_id: None,
+                    monitor_info: None,
+                },
+            ],
+            connections: vec![PlantConnection {
+                consumer: PlantConnectionEndpoint {
+                    system_id: "controller".to_owned(),
+                    port_name: "speed".to_owned(),
+                },
+                producer: PlantConnectionEndpoint {
+                    system_id: "conveyor".to_owned(),
+                    port_name: "speed".to_owned(),
+                },
+                priority: None,
+            }],
+        }
+    }
+
+    fn registration(system_name: &str, service_definition: &str) -> ArrowheadService<EntryTag> {
+        let entry_tag = EntryTag {
+            id: 0,
+            created_at: "string".to_owned(),
+            updated_at: "string".to_owned(),
+        };
+        ArrowheadService {
+            entry_tag: entry_tag.clone(),
+            service_definition: ServiceDefinitionEntry::Value(service_definition.to_owned()),
+            provider_system: ArrowheadSystem {
+                entry_tag,
+                system_name: system_name.to_owned(),
+                address: "string".to_owned(),
+                port: 0,
+                authentication_info: None,
+                metadata: None,
+            },
+            service_uri: "/uri".to_owned(),
+            end_of_validity: None,
+            secure: None,
+            metadata: None,
+            version: None,
+            interfaces: vec![InterfaceEntry::Value("HTTP-INSECURE-JSON".to_owned())],
+        }
+    }
+
+    #[test]
+    fn create_plant_description() {
+        let mock = mockito::mock("POST", "/mgmt/pd")
+            .match_body(Matcher::Json(json!({
+                "plantDescription": "plant",
+                "active": true,
+                "include": [],
+                "systems": [
+                  {
+                    "systemId": "conveyor",
+                    "systemName": "conveyor-a",
+                    "ports": [
+                      {
+                        "portName": "speed",
+                        "serviceDefinition": "conveyor-speed",
+                        "serviceInterface": "HTTP-INSECURE-JSON"
+                      }
+                    ]
+                  },
+                  {
+                    "systemId": "controller",
+                    "ports": [
+                      {
+                        "portName": "speed",
+                        "serviceDefinition": "conveyor-speed",
+                        "consumer": true
+                      }
+                    ]
+                  }
+                ],
+                "connections": [
+                  {
+                    "consumer": { "systemId": "controller", "portName": "speed" },
+                    "producer": { "systemId": "conveyor", "portName": "speed" }
+                  }
+                ]
+            })))
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                  "id": 1,
+                  "plantDescription": "plant",
+                  "active": true,
+                  "include": [],
+                  "systems": [],
+                  "connections": [],
+                  "createdAt": "string",
+                  "updatedAt": "string"
+                })
+                .to_string(),
+            )
+            .create();
+        let adapter = PlantDescriptionEngineAdapter::new(&mockito::server_url()).unwrap();
+        let result = adapter.create_plant_description(&plant_description());
+
+        assert!(matches!(result, Ok(entry) if entry.entry_tag.id == 1));
+        mock.assert();
+    }
+
+    #[test]
+    fn delete_plant_description_arrowhead_error() {
+        let mock = mockito::mock("DELETE", "/mgmt/pd/3")
+            .with_status(404)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                  "errorMessage": "string",
+                  "errorCode": 404,
+                  "exceptionType": "DATA_NOT_FOUND",
+                  "origin": "string"
+                })
+                .to_string(),
+            )
+            .create();
+        let adapter = PlantDescriptionEngineAdapter::new(&mockito::server_url()).unwrap();
+        let result = adapter.delete_plant_description(3);
+
+        assert!(matches!(result, Err(Error::ArrowheadError(_))));
+        mock.assert();
+    }
+
+    #[test]
+    fn get_alarms() {
+        let mock = mockito::mock("GET", "/mgmt/alarm")
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                  "data": [
+                    {
+                      "id": 1,
+                      "systemName": "conveyor-a",
+                      "acknowledged": false,
+                      "severity": "warning",
+                      "description": "System not registered",
+                      "raisedAt": "string",
+                      "updatedAt": "string"
+                    }
+                  ],
+                  "count": 1
+                })
+                .to_string(),
+            )
+            .create();
+        let adapter = PlantDescriptionEngineAdapter::new(&mockito::server_url()).unwrap();
+        let result = adapter.get_alarms(&PageRequest::default());
+
+        assert!(
+            matches!(result, Ok(alarms) if alarms.data[0].severity == PlantAlarmSeverity::Warning)
+        );
+        mock.assert();
+    }
+
+    #[test]
+    fn compare_with_registrations() {
+        let mismatches = plant_description().compare_with_registrations(&[
+            registration("conveyor-a", "conveyor-stop"),
+            registration("unrelated", "conveyor-speed"),
+        ]);
+
+        assert!(
+            mismatches
+                == vec![
+                    PlantDescriptionMismatch::MissingRegistration {
+                        system_id: "conveyor".to_owned(),
+                        port_name: "speed".to_owned(),
+                        service_definition: "conveyor-speed".to_owned(),
+                    },
+                    PlantDescriptionMismatch::UnexpectedRegistration {
+                        system_id: "conveyor".to_owned(),
+                        service_definition: "conveyor-stop".to_owned(),
+                        service_uri: "/uri".to_owned(),
+                    },
+                ]
+        );
+        assert!(plant_description()
+            .compare_with_registrations(&[registration("conveyor-a", "conveyor-speed")])
+            .is_empty());
+    }
+}
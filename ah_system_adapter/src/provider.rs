@@ -0,0 +1,1165 @@
+use crate::access_token::TokenVerifier;
+use crate::core_system::{Authorization, ServiceRegistry};
+use crate::dtos::{
+    ArrowheadService, EntryTag, InterfaceEntry, RegisterServiceInput, SecurityType,
+    ServiceDefinitionEntry,
+};
+use crate::error::{Error, Result};
+use crate::ArrowheadSystemAdapter;
+
+use openssl::nid::Nid;
+use openssl::pkey::PKey;
+use openssl::ssl::{SslAcceptor, SslMethod, SslVerifyMode};
+use openssl::x509::verify::X509VerifyFlags;
+use openssl::x509::{X509Ref, X509};
+use serde::Serialize;
+use tiny_http::{Header, Request, Response, Server};
+
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+const MAX_HEAD_LENGTH: usize = 16 * 1024;
+const TLS_READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub trait ServiceHandler: Send + Sync + 'static {
+    fn handle(&self, request: ProviderRequest) -> ProviderResponse;
+}
+
+impl<F> ServiceHandler for F
+where
+    F: Fn(ProviderRequest) -> ProviderResponse + Send + Sync + 'static,
+{
+    fn handle(&self, request: ProviderRequest) -> ProviderResponse {
+        self(request)
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct ProviderRequest {
+    pub method: String,
+    pub path: String,
+    pub query: Option<String>,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    // The name of the consumer system, if it is known from the token or the client certificate
+    pub consumer: Option<String>,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct ProviderResponse {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+impl ProviderResponse {
+    pub fn status(status: u16) -> Self {
+        Self {
+            status,
+            content_type: None,
+            body: Vec::new(),
+        }
+    }
+
+    pub fn text(body: &str) -> Self {
+        Self {
+            status: 200,
+            content_type: Some("text/plain".to_owned()),
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    pub fn json<T: Serialize>(body: &T) -> Self {
+        match serde_json::to_vec(body) {
+            Ok(body) => Self {
+                status: 200,
+                content_type: Some("application/json".to_owned()),
+                body,
+            },
+            Err(_) => Self::status(500),
+        }
+    }
+}
+
+pub struct ProvidedService {
+    pub service_definition: String,
+    pub service_uri: String,
+    pub interfaces: Vec<String>,
+    pub metadata: Option<HashMap<String, String>>,
+    pub version: Option<u32>,
+    handler: Arc<dyn ServiceHandler>,
+}
+impl ProvidedService {
+    pub fn new<H: ServiceHandler>(
+        service_definition: &str,
+        service_uri: &str,
+        interfaces: &[&str],
+        handler: H,
+    ) -> Self {
+        Self {
+            service_definition: service_definition.to_owned(),
+            service_uri: service_uri.to_owned(),
+            interfaces: interfaces.iter().map(|i| (*i).to_owned()).collect(),
+            metadata: None,
+            version: None,
+            handler: Arc::new(handler),
+        }
+    }
+
+    pub fn with_metadata(mut self, metadata: HashMap<String, String>) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    pub fn with_version(mut self, version: u32) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    fn register_input(&self, secure: SecurityType) -> RegisterServiceInput {
+        RegisterServiceInput {
+            service_definition: ServiceDefinitionEntry::Value(self.service_definition.clone()),
+            service_uri: self.service_uri.clone(),
+            end_of_validity: None,
+            secure: Some(secure),
+            metadata: self.metadata.clone(),
+            version: self.version,
+            interfaces: self
+                .interfaces
+                .iter()
+                .map(|i| InterfaceEntry::Value(i.clone()))
+                .collect(),
+        }
+    }
+
+    // The service URI matches the path itself and the paths below it.
+    fn matches(&self, path: &str) -> bool {
+        let service_uri = self.service_uri.trim_end_matches('/');
+        path.strip_prefix(service_uri)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    }
+}
+
+#[derive(PartialEq, Clone)]
+pub enum ProviderSecurity {
+    NotSecure,
+    // The consumers pass the token generated by the Authorization in the `token` query parameter.
+    // The server terminates TLS as in the certificate mode, so the token is never sent in
+    // cleartext. The token is decrypted with the private key of the provider (PKCS#8 PEM, the
+    // RSA key of its certificate), and its signature is checked with the public key of the
+    // Authorization.
+    Token {
+        certificate_chain: String,
+        private_key: String,
+        trusted_certificates: String,
+    },
+    // The server terminates TLS with the certificate chain and the private key of the provider
+    // (PEM), and requires a client certificate issued by one of the trusted certificates (PEM,
+    // e.g. the certificate of the cloud). The consumer is named by the common name of the
+    // verified client certificate, and must belong to the given cloud (e.g.
+    // `testcloud.aitia.arrowhead.eu`).
+    Certificate {
+        certificate_chain: String,
+        private_key: String,
+        trusted_certificates: String,
+        cloud_common_name: String,
+    },
+    // The TLS connection is terminated by a reverse proxy in front of the provider, which
+    // verifies the client certificate and passes its common name in the given header. As the
+    // header is trusted, the server only binds to the loopback interface.
+    ReverseProxy {
+        client_cn_header: String,
+        cloud_common_name: String,
+    },
+}
+impl ProviderSecurity {
+    fn security_type(&self) -> SecurityType {
+        match self {
+            Self::NotSecure => SecurityType::NotSecure,
+            Self::Token { .. } => SecurityType::Token,
+            Self::Certificate { .. } | Self::ReverseProxy { .. } => SecurityType::Certificate,
+        }
+    }
+}
+
+enum AccessCheck {
+    NotSecure,
+    Token(Box<TokenVerifier>),
+    Certificate {
+        cloud_common_name: String,
+    },
+    ReverseProxy {
+        client_cn_header: String,
+        cloud_common_name: String,
+    },
+}
+
+struct ServedServices {
+    services: Vec<ProvidedService>,
+    access_check: AccessCheck,
+}
+
+enum Listener {
+    Http(Arc<Server>),
+    Tls {
+        listener: Arc<TcpListener>,
+        acceptor: Arc<SslAcceptor>,
+        stopped: Arc<AtomicBool>,
+    },
+}
+impl Listener {
+    fn bind(bind_address: &str, security: &ProviderSecurity) -> Result<Self> {
+        match security {
+            ProviderSecurity::Token {
+                certificate_chain,
+                private_key,
+                trusted_certificates,
+            }
+            | ProviderSecurity::Certificate {
+                certificate_chain,
+                private_key,
+                trusted_certificates,
+                ..
+            } => {
+                let acceptor = tls_acceptor(certificate_chain, private_key, trusted_certificates)?;
+                let listener = TcpListener::bind(bind_address)
+                    .map_err(|e| Error::ServerError(format!("{}", e)))?;
+                Ok(Self::Tls {
+                    listener: Arc::new(listener),
+                    acceptor: Arc::new(acceptor),
+                    stopped: Arc::new(AtomicBool::new(false)),
+                })
+            }
+            ProviderSecurity::NotSecure | ProviderSecurity::ReverseProxy { .. } => {
+                Ok(Self::Http(Arc::new(
+                    Server::http(bind_address).map_err(|e| Error::ServerError(format!("{}", e)))?,
+                )))
+            }
+        }
+    }
+
+    fn port(&self) -> Option<u16> {
+        match self {
+            Self::Http(server) => server.server_addr().to_ip().map(|a| a.port()),
+            Self::Tls { listener, .. } => listener.local_addr().ok().map(|a| a.port()),
+        }
+    }
+
+    fn serve(&self, served_services: Arc<ServedServices>) -> JoinHandle<()> {
+        match self {
+            Self::Http(server) => {
+                let server = server.clone();
+                thread::spawn(move || {
+                    for request in server.incoming_requests() {
+                        let served_services = served_services.clone();
+                        thread::spawn(move || handle_request(request, &served_services));
+                    }
+                })
+            }
+            Self::Tls {
+                listener,
+                acceptor,
+                stopped,
+            } => {
+                let (listener, acceptor, stopped) =
+                    (listener.clone(), acceptor.clone(), stopped.clone());
+                thread::spawn(move || {
+                    for stream in listener.incoming() {
+                        if stopped.load(Ordering::SeqCst) {
+                            break;
+                        }
+                        let Ok(stream) = stream else {
+                            continue;
+                        };
+                        let (acceptor, served_services) =
+                            (acceptor.clone(), served_services.clone());
+                        thread::spawn(move || {
+                            handle_tls_connection(&acceptor, stream, &served_services)
+                        });
+                    }
+                })
+            }
+        }
+    }
+
+    // The TLS listener is woken up by a connection after setting the flag.
+    fn unblock(&self) {
+        match self {
+            Self::Http(server) => server.unblock(),
+            Self::Tls {
+                listener, stopped, ..
+            } => {
+                stopped.store(true, Ordering::SeqCst);
+                if let Ok(mut address) = listener.local_addr() {
+                    if address.ip().is_unspecified() {
+                        address.set_ip(match address.ip() {
+                            IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+                            IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+                        });
+                    }
+                    let _ = TcpStream::connect(address);
+                }
+            }
+        }
+    }
+}
+
+pub struct ProviderServer<A: ServiceRegistry + Authorization = ArrowheadSystemAdapter> {
+    registered_services: Vec<ArrowheadService<EntryTag>>,
+    adapter: A,
+    listener: Listener,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl<A: ServiceRegistry + Authorization> ProviderServer<A> {
+    // Binds the server and registers the services in the Service Registry, then starts serving
+    // them. If a registration fails, the already registered services are unregistered.
+    pub fn start(
+        adapter: A,
+        bind_address: &str,
+        security: ProviderSecurity,
+        services: Vec<ProvidedService>,
+    ) -> Result<Self> {
+        let access_check = match &security {
+            ProviderSecurity::NotSecure => AccessCheck::NotSecure,
+            ProviderSecurity::Token { private_key, .. } => AccessCheck::Token(Box::new(
+                TokenVerifier::new(private_key, &adapter.get_public_key()?).ok_or_else(|| {
+                    Error::ServerError(
+                        "The provider private key or the Authorization public key is invalid"
+                            .to_owned(),
+                    )
+                })?,
+            )),
+            ProviderSecurity::Certificate {
+                cloud_common_name, ..
+            } => AccessCheck::Certificate {
+                cloud_common_name: cloud_common_name.clone(),
+            },
+            ProviderSecurity::ReverseProxy {
+                client_cn_header,
+                cloud_common_name,
+            } => {
+                if !is_loopback(bind_address) {
+                    return Err(Error::ConfigError(format!(
+                        "The reverse proxy mode only binds to the loopback interface, not to '{}'",
+                        bind_address
+                    )));
+                }
+                AccessCheck::ReverseProxy {
+                    client_cn_header: client_cn_header.clone(),
+                    cloud_common_name: cloud_common_name.clone(),
+                }
+            }
+        };
+        let listener = Listener::bind(bind_address, &security)?;
+
+        let mut provider = Self {
+            registered_services: Vec::new(),
+            adapter,
+            listener,
+            worker: None,
+        };
+        // On error, dropping the provider unregisters the already registered services.
+        for service in &services {
+            let registered_service = provider
+                .adapter
+                .register_service(service.register_input(security.security_type()))?;
+            provider.registered_services.push(registered_service);
+        }
+
+        provider.worker = Some(provider.listener.serve(Arc::new(ServedServices {
+            services,
+            access_check,
+        })));
+        Ok(provider)
+    }
+
+    pub fn registered_services(&self) -> &[ArrowheadService<EntryTag>] {
+        &self.registered_services
+    }
+
+    pub fn port(&self) -> Option<u16> {
+        self.listener.port()
+    }
+
+    // Shuts down the server and unregisters the services. Dropping the server does the same,
+    // but the errors of the unregistration are only logged.
+    pub fn stop(mut self) -> Result<()> {
+        self.shutdown();
+        self.unregister_services()
+    }
+
+    fn shutdown(&mut self) {
+        if let Some(worker) = self.worker.take() {
+            self.listener.unblock();
+            let _ = worker.join();
+        }
+    }
+
+    // Every service is unregistered even if some of them fail, the first error is returned.
+    fn unregister_services(&mut self) -> Result<()> {
+        let mut result = Ok(());
+        for service in self.registered_services.drain(..) {
+            let unregistered = self.adapter.unregister_service_entry(&service);
+            if result.is_ok() {
+                result = unregistered;
+            }
+        }
+        result
+    }
+}
+
+impl<A: ServiceRegistry + Authorization> Drop for ProviderServer<A> {
+    fn drop(&mut self) {
+        self.shutdown();
+        if let Err(err) = self.unregister_services() {
+            tracing::warn!(error = %err, "unregistration of the provided services failed");
+        }
+    }
+}
+
+fn is_loopback(bind_address: &str) -> bool {
+    bind_address
+        .to_socket_addrs()
+        .is_ok_and(|mut addresses| addresses.all(|address| address.ip().is_loopback()))
+}
+
+fn tls_acceptor(
+    certificate_chain: &str,
+    private_key: &str,
+    trusted_certificates: &str,
+) -> Result<SslAcceptor> {
+    let invalid = |e| Error::ServerError(format!("The TLS configuration is invalid: {}", e));
+    let mut builder =
+        SslAcceptor::mozilla_intermediate_v5(SslMethod::tls_server()).map_err(invalid)?;
+
+    let mut chain = X509::stack_from_pem(certificate_chain.as_bytes())
+        .map_err(invalid)?
+        .into_iter();
+    let certificate = chain.next().ok_or_else(|| {
+        Error::ServerError("The certificate chain of the provider is empty".to_owned())
+    })?;
+    builder.set_certificate(&certificate).map_err(invalid)?;
+    for certificate in chain {
+        builder.add_extra_chain_cert(certificate).map_err(invalid)?;
+    }
+    let private_key = PKey::private_key_from_pem(private_key.as_bytes()).map_err(invalid)?;
+    builder.set_private_key(&private_key).map_err(invalid)?;
+    builder.check_private_key().map_err(invalid)?;
+
+    let trusted_certificates =
+        X509::stack_from_pem(trusted_certificates.as_bytes()).map_err(invalid)?;
+    if trusted_certificates.is_empty() {
+        return Err(Error::ServerError(
+            "No trusted certificate is given for the client certificates".to_owned(),
+        ));
+    }
+    for certificate in trusted_certificates {
+        builder
+            .cert_store_mut()
+            .add_cert(certificate)
+            .map_err(invalid)?;
+    }
+    // A trusted certificate (e.g. the certificate of the cloud) is enough to verify the client
+    // certificates issued by it, its own issuers are not needed.
+    builder
+        .verify_param_mut()
+        .set_flags(X509VerifyFlags::PARTIAL_CHAIN)
+        .map_err(invalid)?;
+    builder.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+    Ok(builder.build())
+}
+
+// A request read by one of the servers, the body is only read after the access check.
+struct IncomingRequest {
+    method: String,
+    url: String,
+    headers: Vec<(String, String)>,
+    // The common name of the verified client certificate
+    peer_common_name: Option<String>,
+}
+
+fn dispatch(
+    served_services: &ServedServices,
+    incoming: IncomingRequest,
+    read_body: impl FnOnce() -> io::Result<Vec<u8>>,
+) -> ProviderResponse {
+    let (path, query) = match incoming.url.split_once('?') {
+        Some((path, query)) => (path, Some(query.to_owned())),
+        None => (incoming.url.as_str(), None),
+    };
+    let Some(service) = served_services
+        .services
+        .iter()
+        .filter(|service| service.matches(path))
+        .max_by_key(|service| service.service_uri.len())
+    else {
+        return ProviderResponse::status(404);
+    };
+
+    let consumer = match check_access(
+        &served_services.access_check,
+        service,
+        query.as_deref(),
+        &incoming.headers,
+        incoming.peer_common_name.as_deref(),
+    ) {
+        Ok(consumer) => consumer,
+        Err(status_code) => return ProviderResponse::status(status_code),
+    };
+
+    let Ok(body) = read_body() else {
+        return ProviderResponse::status(400);
+    };
+    service.handler.handle(ProviderRequest {
+        method: incoming.method,
+        path: path.to_owned(),
+        query,
+        headers: incoming.headers,
+        body,
+        consumer,
+    })
+}
+
+fn handle_request(mut request: Request, served_services: &ServedServices) {
+    let incoming = IncomingRequest {
+        method: request.method().to_string(),
+        url: request.url().to_owned(),
+        headers: request
+            .headers()
+            .iter()
+            .map(|h| (h.field.to_string(), h.value.to_string()))
+            .collect(),
+        peer_common_name: None,
+    };
+    let response = dispatch(served_services, incoming, || {
+        let mut body = Vec::new();
+        request.as_reader().read_to_end(&mut body).map(|_| body)
+    });
+
+    let mut http_response = Response::from_data(response.body).with_status_code(response.status);
+    if let Some(header) = response
+        .content_type
+        .and_then(|c| Header::from_bytes(&b"Content-Type"[..], c.as_bytes()).ok())
+    {
+        http_response = http_response.with_header(header);
+    }
+    let _ = request.respond(http_response);
+}
+
+// Serves a single request on the connection, which is closed afterwards.
+fn handle_tls_connection(
+    acceptor: &SslAcceptor,
+    stream: TcpStream,
+    served_services: &ServedServices,
+) {
+    let _ = stream.set_read_timeout(Some(TLS_READ_TIMEOUT));
+    // The handshake fails without a client certificate issued by a trusted certificate.
+    let mut stream = match acceptor.accept(stream) {
+        Ok(stream) => stream,
+        Err(err) => {
+            tracing::debug!(error = %err, "TLS handshake with a consumer failed");
+            return;
+        }
+    };
+    let peer_common_name = stream
+        .ssl()
+        .peer_certificate()
+        .and_then(|certificate| common_name(&certificate));
+
+    let response = match read_request_head(&mut stream) {
+        Ok((mut incoming, body_start)) => {
+            incoming.peer_common_name = peer_common_name;
+            let content_length = content_length(&incoming.headers);
+            dispatch(served_services, incoming, || {
+                read_request_body(&mut stream, body_start, content_length?)
+            })
+        }
+        Err(_) => ProviderResponse::status(400),
+    };
+    if write_response(&mut stream, &response).is_ok() {
+        let _ = stream.shutdown();
+    }
+}
+
+fn common_name(certificate: &X509Ref) -> Option<String> {
+    certificate
+        .subject_name()
+        .entries_by_nid(Nid::COMMONNAME)
+        .next()
+        .and_then(|entry| entry.data().to_string().ok())
+}
+
+// Reads the request line and the headers, and returns the bytes read after them.
+fn read_request_head(stream: &mut impl Read) -> io::Result<(IncomingRequest, Vec<u8>)> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    loop {
+        let read = stream.read(&mut chunk)?;
+        if read == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+
+        let mut headers = [httparse::EMPTY_HEADER; 64];
+        let mut request = httparse::Request::new(&mut headers);
+        match request.parse(&buffer) {
+            Ok(httparse::Status::Complete(head_length)) => {
+                let incoming = IncomingRequest {
+                    method: request.method.unwrap_or_default().to_owned(),
+                    url: request.path.unwrap_or_default().to_owned(),
+                    headers: request
+                        .headers
+                        .iter()
+                        .map(|h| {
+                            (
+                                h.name.to_owned(),
+                                String::from_utf8_lossy(h.value).into_owned(),
+                            )
+                        })
+                        .collect(),
+                    peer_common_name: None,
+                };
+                return Ok((incoming, buffer.split_off(head_length)));
+            }
+            Ok(httparse::Status::Partial) if buffer.len() < MAX_HEAD_LENGTH => {}
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "The request head is invalid",
+                ))
+            }
+        }
+    }
+}
+
+// Only bodies with a `Content-Length` are supported, chunked bodies are rejected.
+fn content_length(headers: &[(String, String)]) -> io::Result<usize> {
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(field, _)| field.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.trim())
+    };
+    if header("Transfer-Encoding").is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Chunked request bodies are not supported",
+        ));
+    }
+    header("Content-Length").map_or(Ok(0), |value| {
+        value
+            .parse()
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Invalid Content-Length"))
+    })
+}
+
+fn read_request_body(
+    stream: &mut impl Read,
+    mut body: Vec<u8>,
+    content_length: usize,
+) -> io::Result<Vec<u8>> {
+    if body.len() < content_length {
+        stream
+            .take((content_length - body.len()) as u64)
+            .read_to_end(&mut body)?;
+    }
+    if body.len() != content_length {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "The body does not match the Content-Length",
+        ));
+    }
+    Ok(body)
+}
+
+fn write_response(stream: &mut impl Write, response: &ProviderResponse) -> io::Result<()> {
+    let reason = http::StatusCode::from_u16(response.status)
+        .ok()
+        .and_then(|status| status.canonical_reason())
+        .unwrap_or_default();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        reason,
+        response.body.len()
+    )?;
+    if let Some(content_type) = response
+        .content_type
+        .as_ref()
+        .filter(|c| !c.contains(['\r', '\n']))
+    {
+        write!(stream, "Content-Type: {}\r\n", content_type)?;
+    }
+    stream.write_all(b"\r\n")?;
+    stream.write_all(&response.body)?;
+    stream.flush()
+}
+
+// Returns the name of the consumer, or the status code of the rejection.
+fn check_access(
+    access_check: &AccessCheck,
+    service: &ProvidedService,
+    query: Option<&str>,
+    headers: &[(String, String)],
+    peer_common_name: Option<&str>,
+) -> std::result::Result<Option<String>, u16> {
+    match access_check {
+        AccessCheck::NotSecure => Ok(None),
+        AccessCheck::Token(verifier) => {
+            let token = query
+                .and_then(|query| {
+                    url::form_urlencoded::parse(query.as_bytes())
+                        .find(|(key, _)| key == "token")
+                        .map(|(_, value)| value.into_owned())
+                })
+                .ok_or(401u16)?;
+            let claims = verifier.verify(&token).map_err(|_| 401u16)?;
+            let interface_allowed = claims.iid.as_ref().is_none_or(|iid| {
+                service
+                    .interfaces
+                    .iter()
+                    .any(|i| i.eq_ignore_ascii_case(iid))
+            });
+            if claims.sid.eq_ignore_ascii_case(&service.service_definition) && interface_allowed {
+                Ok(Some(claims.cid))
+            } else {
+                Err(403)
+            }
+        }
+        AccessCheck::Certificate { cloud_common_name } => {
+            consumer_in_cloud(peer_common_name.ok_or(401u16)?, cloud_common_name)
+        }
+        AccessCheck::ReverseProxy {
+            client_cn_header,
+            cloud_common_name,
+        } => {
+            let common_name = headers
+                .iter()
+                .find(|(field, _)| field.eq_ignore_ascii_case(client_cn_header))
+                .map(|(_, value)| value.as_str())
+                .ok_or(401u16)?;
+            consumer_in_cloud(common_name, cloud_common_name)
+        }
+    }
+}
+
+// The common name of a system certificate is `<system name>.<cloud common name>`.
+fn consumer_in_cloud(
+    common_name: &str,
+    cloud_common_name: &str,
+) -> std::result::Result<Option<String>, u16> {
+    match common_name.split_once('.') {
+        Some((system_name, cloud)) if cloud.eq_ignore_ascii_case(cloud_common_name) => {
+            Ok(Some(system_name.to_owned()))
+        }
+        _ => Err(403),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::access_token::tests::{key, public_key, token};
+    use crate::dtos::{ArrowheadSystem, NoEntryTag, Orchestration};
+    use crate::invoker::ServiceInvoker;
+    use crate::test_certificates::{issue, issue_with_key, Issued};
+    use mockito::{Matcher, Mock};
+    use reqwest::blocking::Client;
+    use reqwest::{Certificate, Identity};
+    use serde_json::json;
+
+    fn adapter(prefix: &str) -> ArrowheadSystemAdapter {
+        ArrowheadSystemAdapter::new(
+            &format!("{}/{}/serviceregistry/", mockito::server_url(), prefix),
+            &format!("{}/{}/authorization/", mockito::server_url(), prefix),
+            "http://dontcare",
+            ArrowheadSystem {
+                entry_tag: NoEntryTag {},
+                system_name: "thermometer".to_owned(),
+                address: "127.0.0.1".to_owned(),
+                port: 0,
+                authentication_info: None,
+                metadata: None,
+            },
+        )
+        .unwrap()
+    }
+
+    fn register_mock(prefix: &str, service_definition: &str, secure: &str) -> Mock {
+        let response = std::fs::read_to_string(format!(
+            "{}/fixtures/4.4/service_registry/register/response.json",
+            env!("CARGO_MANIFEST_DIR")
+        ))
+        .unwrap();
+        mockito::mock(
+            "POST",
+            format!("/{}/serviceregistry/register", prefix).as_str(),
+        )
+        .match_body(Matcher::PartialJson(json!({
+            "serviceDefinition": service_definition,
+            "secure": secure
+        })))
+        .with_status(201)
+        .with_header("content-type", "application/json")
+        .with_body(response)
+        .create()
+    }
+
+    fn unregister_mock(prefix: &str, expected: usize) -> Mock {
+        mockito::mock(
+            "DELETE",
+            format!("/{}/serviceregistry/unregister", prefix).as_str(),
+        )
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("service_definition".into(), "temperature".into()),
+            Matcher::UrlEncoded("service_uri".into(), "/temperature".into()),
+        ]))
+        .expect(expected)
+        .create()
+    }
+
+    fn temperature_service() -> ProvidedService {
+        ProvidedService::new(
+            "temperature",
+            "/temperature",
+            &["HTTP-SECURE-JSON"],
+            |request: ProviderRequest| {
+                ProviderResponse::text(request.consumer.as_deref().unwrap_or("21.5"))
+            },
+        )
+    }
+
+    fn get(provider: &ProviderServer, path: &str) -> (u16, String) {
+        let response = Client::new()
+            .get(format!(
+                "http://127.0.0.1:{}{}",
+                provider.port().unwrap(),
+                path
+            ))
+            .header("X-Client-CN", "consumer.testcloud.aitia.arrowhead.eu")
+            .send()
+            .unwrap();
+        (response.status().as_u16(), response.text().unwrap())
+    }
+
+    #[test]
+    fn provider_lifecycle() {
+        let temperature_mock = register_mock("lifecycle", "temperature", "NOT_SECURE");
+        let history_mock = register_mock("lifecycle", "temperature-history", "NOT_SECURE");
+        let unregister_mock = unregister_mock("lifecycle", 2);
+        let provider = ProviderServer::start(
+            adapter("lifecycle"),
+            "127.0.0.1:0",
+            ProviderSecurity::NotSecure,
+            vec![
+                temperature_service(),
+                ProvidedService::new(
+                    "temperature-history",
+                    "/temperature/history",
+                    &["HTTP-INSECURE-JSON"],
+                    |request: ProviderRequest| ProviderResponse::json(&json!([request.path])),
+                )
+                .with_version(2),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(provider.registered_services().len(), 2);
+        assert_eq!(get(&provider, "/temperature"), (200, "21.5".to_owned()));
+        assert_eq!(
+            get(&provider, "/temperature/history/today?unit=celsius"),
+            (200, r#"["/temperature/history/today"]"#.to_owned())
+        );
+        assert_eq!(get(&provider, "/temperatures").0, 404);
+        assert!(provider.stop().is_ok());
+        temperature_mock.assert();
+        history_mock.assert();
+        unregister_mock.assert();
+    }
+
+    #[test]
+    fn unregister_on_failed_registration() {
+        let temperature_mock = register_mock("rollback", "temperature", "NOT_SECURE");
+        let failing_mock = mockito::mock("POST", "/rollback/serviceregistry/register")
+            .match_body(Matcher::PartialJson(
+                json!({ "serviceDefinition": "humidity" }),
+            ))
+            .with_status(400)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "errorMessage": "Service definition is invalid",
+                    "errorCode": 400,
+                    "exceptionType": "INVALID_PARAMETER",
+                    "origin": "serviceregistry/register"
+                })
+                .to_string(),
+            )
+            .create();
+        let unregister_mock = unregister_mock("rollback", 1);
+
+        let result = ProviderServer::start(
+            adapter("rollback"),
+            "127.0.0.1:0",
+            ProviderSecurity::NotSecure,
+            vec![
+                temperature_service(),
+                ProvidedService::new("humidity", "/humidity", &["HTTP-INSECURE-JSON"], |_| {
+                    ProviderResponse::status(204)
+                }),
+            ],
+        );
+
+        assert!(matches!(result, Err(Error::ArrowheadError(_))));
+        temperature_mock.assert();
+        failing_mock.assert();
+        unregister_mock.assert();
+    }
+
+    #[test]
+    fn check_reverse_proxy_header() {
+        let _register_mock = register_mock("proxy", "temperature", "CERTIFICATE");
+        let _unregister_mock = unregister_mock("proxy", 2);
+        let start = |bind_address: &str, cloud_common_name: &str| {
+            ProviderServer::start(
+                adapter("proxy"),
+                bind_address,
+                ProviderSecurity::ReverseProxy {
+                    client_cn_header: "X-Client-CN".to_owned(),
+                    cloud_common_name: cloud_common_name.to_owned(),
+                },
+                vec![temperature_service()],
+            )
+        };
+
+        assert!(matches!(
+            start("0.0.0.0:0", "testcloud.aitia.arrowhead.eu"),
+            Err(Error::ConfigError(_))
+        ));
+
+        let provider = start("127.0.0.1:0", "testcloud.aitia.arrowhead.eu").unwrap();
+        assert_eq!(get(&provider, "/temperature"), (200, "consumer".to_owned()));
+        drop(provider);
+
+        let provider = start("127.0.0.1:0", "othercloud.aitia.arrowhead.eu").unwrap();
+        assert_eq!(get(&provider, "/temperature").0, 403);
+    }
+
+    fn pem(certificate: &X509) -> String {
+        String::from_utf8(certificate.to_pem().unwrap()).unwrap()
+    }
+
+    fn private_key_pem(issued: &Issued) -> String {
+        String::from_utf8(issued.key.private_key_to_pem_pkcs8().unwrap()).unwrap()
+    }
+
+    fn tls_client(cloud: &Issued, client: Option<&Issued>) -> reqwest::Result<Client> {
+        let mut builder = Client::builder()
+            .add_root_certificate(Certificate::from_pem(pem(&cloud.certificate).as_bytes())?);
+        if let Some(client) = client {
+            builder = builder.identity(Identity::from_pkcs8_pem(
+                pem(&client.certificate).as_bytes(),
+                private_key_pem(client).as_bytes(),
+            )?);
+        }
+        builder.build()
+    }
+
+    fn get_tls(
+        provider: &ProviderServer,
+        cloud: &Issued,
+        client: Option<&Issued>,
+    ) -> reqwest::Result<(u16, String)> {
+        get_tls_path(provider, cloud, client, "/temperature")
+    }
+
+    fn get_tls_path(
+        provider: &ProviderServer,
+        cloud: &Issued,
+        client: Option<&Issued>,
+        path: &str,
+    ) -> reqwest::Result<(u16, String)> {
+        let response = tls_client(cloud, client)?
+            .get(format!(
+                "https://127.0.0.1:{}{}",
+                provider.port().unwrap(),
+                path
+            ))
+            .header("X-Client-CN", "admin.testcloud.aitia.arrowhead.eu")
+            .send()?;
+        Ok((response.status().as_u16(), response.text()?))
+    }
+
+    #[test]
+    fn check_client_certificate() {
+        let _register_mock = register_mock("certificate", "temperature", "CERTIFICATE");
+        let _unregister_mock = unregister_mock("certificate", 2);
+        let cloud = issue("testcloud.aitia.arrowhead.eu", None);
+        let provider_certificate = issue("thermometer.testcloud.aitia.arrowhead.eu", Some(&cloud));
+        let start = |cloud_common_name: &str| {
+            ProviderServer::start(
+                adapter("certificate"),
+                "127.0.0.1:0",
+                ProviderSecurity::Certificate {
+                    certificate_chain: pem(&provider_certificate.certificate)
+                        + &pem(&cloud.certificate),
+                    private_key: private_key_pem(&provider_certificate),
+                    trusted_certificates: pem(&cloud.certificate),
+                    cloud_common_name: cloud_common_name.to_owned(),
+                },
+                vec![temperature_service()],
+            )
+            .unwrap()
+        };
+        let consumer = issue("consumer.testcloud.aitia.arrowhead.eu", Some(&cloud));
+        let other_cloud = issue("testcloud.aitia.arrowhead.eu", None);
+        let impostor = issue("consumer.testcloud.aitia.arrowhead.eu", Some(&other_cloud));
+
+        let provider = start("testcloud.aitia.arrowhead.eu");
+        // The consumer is named by the verified certificate, not by the header.
+        assert_eq!(
+            get_tls(&provider, &cloud, Some(&consumer)).unwrap(),
+            (200, "consumer".to_owned())
+        );
+        assert!(get_tls(&provider, &cloud, None).is_err());
+        assert!(get_tls(&provider, &cloud, Some(&impostor)).is_err());
+        drop(provider);
+
+        let provider = start("othercloud.aitia.arrowhead.eu");
+        assert_eq!(get_tls(&provider, &cloud, Some(&consumer)).unwrap().0, 403);
+    }
+
+    fn token_for(service_definition: &str) -> String {
+        token(
+            json!({
+                "iss": "Authorization",
+                "cid": "consumer",
+                "sid": service_definition,
+                "iid": "HTTP-SECURE-JSON"
+            }),
+            "authorization",
+            "provider",
+        )
+    }
+
+    // The token is decrypted with the RSA key of the provider certificate.
+    fn start_with_token(
+        prefix: &str,
+        cloud: &Issued,
+        services: Vec<ProvidedService>,
+    ) -> ProviderServer {
+        let provider_certificate = issue_with_key(
+            "thermometer.testcloud.aitia.arrowhead.eu",
+            Some(cloud),
+            PKey::private_key_from_pem(key("provider").as_bytes()).unwrap(),
+        );
+        ProviderServer::start(
+            adapter(prefix),
+            "127.0.0.1:0",
+            ProviderSecurity::Token {
+                certificate_chain: pem(&provider_certificate.certificate)
+                    + &pem(&cloud.certificate),
+                private_key: key("provider"),
+                trusted_certificates: pem(&cloud.certificate),
+            },
+            services,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn check_token() {
+        let public_key_mock = mockito::mock("GET", "/token/authorization/publickey")
+            .with_body(format!("\"{}\"", public_key("authorization")))
+            .create();
+        let _register_mock = register_mock("token", "temperature", "TOKEN");
+        let _unregister_mock = unregister_mock("token", 1);
+        let cloud = issue("testcloud.aitia.arrowhead.eu", None);
+        let consumer = issue("consumer.testcloud.aitia.arrowhead.eu", Some(&cloud));
+        let provider = start_with_token("token", &cloud, vec![temperature_service()]);
+        let get = |path: &str| get_tls_path(&provider, &cloud, Some(&consumer), path);
+
+        assert_eq!(get("/temperature").unwrap().0, 401);
+        assert_eq!(
+            get(&format!("/temperature?token={}", token_for("temperature"))).unwrap(),
+            (200, "consumer".to_owned())
+        );
+        assert_eq!(
+            get(&format!("/temperature?token={}", token_for("humidity")))
+                .unwrap()
+                .0,
+            403
+        );
+        // The token is not accepted in cleartext.
+        assert!(Client::new()
+            .get(format!(
+                "http://127.0.0.1:{}/temperature?token={}",
+                provider.port().unwrap(),
+                token_for("temperature")
+            ))
+            .send()
+            .is_err());
+        public_key_mock.assert();
+    }
+
+    #[test]
+    fn invoke_with_token() {
+        let _public_key_mock = mockito::mock("GET", "/invoker/authorization/publickey")
+            .with_body(format!("\"{}\"", public_key("authorization")))
+            .create();
+        let _register_mock = register_mock("invoker", "temperature", "TOKEN");
+        let _unregister_mock = unregister_mock("invoker", 1);
+        let cloud = issue("testcloud.aitia.arrowhead.eu", None);
+        let consumer = issue("consumer.testcloud.aitia.arrowhead.eu", Some(&cloud));
+        let provider = start_with_token(
+            "invoker",
+            &cloud,
+            vec![ProvidedService::new(
+                "temperature",
+                "/temperature",
+                &["HTTP-SECURE-JSON"],
+                |request: ProviderRequest| {
+                    ProviderResponse::json(&json!({ "consumer": request.consumer, "value": 21.5 }))
+                },
+            )],
+        );
+        let orchestration = Orchestration {
+            provider: ArrowheadSystem {
+                entry_tag: EntryTag {
+                    id: 1,
+                    created_at: "string".to_owned(),
+                    updated_at: "string".to_owned(),
+                },
+                system_name: "thermometer".to_owned(),
+                address: "127.0.0.1".to_owned(),
+                port: provider.port().unwrap().into(),
+                authentication_info: None,
+                metadata: None,
+            },
+            service: ServiceDefinitionEntry::Value("temperature".to_owned()),
+            service_uri: "/temperature".to_owned(),
+            secure: SecurityType::Token,
+            metadata: HashMap::new(),
+            interfaces: vec![InterfaceEntry::Value("HTTP-SECURE-JSON".to_owned())],
+            version: 1,
+            authorization_tokens: Some(HashMap::from([(
+                "HTTP-SECURE-JSON".to_owned(),
+                token_for("temperature"),
+            )])),
+            warnings: vec![],
+        };
+        let invoker = ServiceInvoker::new(vec![orchestration])
+            .with_client(tls_client(&cloud, Some(&consumer)).unwrap());
+
+        assert_eq!(
+            invoker.get::<serde_json::Value>().unwrap(),
+            json!({ "consumer": "consumer", "value": 21.5 })
+        );
+    }
+}
@@ -0,0 +1,410 @@
+use crate::dtos::{Orchestration, OrchestrationResponse};
+use crate::error::{Error, Result};
+use crate::qos_monitor::QosIntraPingMeasurementList;
+use crate::random;
+
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+#[derive(PartialEq, Clone)]
+pub enum SelectionStrategy {
+    PriorityOrder,
+    RoundRobin,
+    Random,
+    LeastRecentlyFailed,
+    PreferredInterface(String),
+    LowestLatency(HashMap<u32, u32>),
+}
+impl SelectionStrategy {
+    pub fn lowest_latency(measurements: &QosIntraPingMeasurementList) -> Self {
+        Self::LowestLatency(
+            measurements
+                .data
+                .iter()
+                .filter(|m| m.statistics.available != Some(false))
+                .map(|m| {
+                    (
+                        m.system_id(),
+                        m.statistics.mean_response_time_without_timeout,
+                    )
+                })
+                .collect(),
+        )
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub struct HealthPolicy {
+    pub max_failures: u32,
+    pub ejection_time: Duration,
+}
+impl Default for HealthPolicy {
+    fn default() -> Self {
+        Self {
+            max_failures: 3,
+            ejection_time: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Default)]
+pub struct ProviderHealth {
+    pub consecutive_failures: u32,
+    pub last_failure: Option<Instant>,
+    pub ejected_until: Option<Instant>,
+}
+impl ProviderHealth {
+    fn is_ejected(&self, now: Instant) -> bool {
+        matches!(self.ejected_until, Some(until) if until > now)
+    }
+}
+
+type ProviderKey = (u32, String);
+
+#[derive(Default)]
+struct SelectorState {
+    orchestrations: Vec<Orchestration>,
+    health: HashMap<ProviderKey, ProviderHealth>,
+    round_robin_counter: usize,
+}
+
+pub struct ProviderSelector {
+    strategy: SelectionStrategy,
+    health_policy: HealthPolicy,
+    state: Mutex<SelectorState>,
+}
+
+impl ProviderSelector {
+    pub fn new(orchestrations: Vec<Orchestration>, strategy: SelectionStrategy) -> Self {
+        Self {
+            strategy,
+            health_policy: HealthPolicy::default(),
+            state: Mutex::new(SelectorState {
+                orchestrations,
+                ..Default::default()
+            }),
+        }
+    }
+
+    pub fn from_response(
+        orchestration_response: OrchestrationResponse,
+        strategy: SelectionStrategy,
+    ) -> Self {
+        Self::new(orchestration_response.response, strategy)
+    }
+
+    pub fn with_health_policy(mut self, health_policy: HealthPolicy) -> Self {
+        self.health_policy = health_policy;
+        self
+    }
+
+    pub fn update_orchestrations(&self, orchestrations: Vec<Orchestration>) {
+        let mut state = self.lock_state();
+        let keys: Vec<ProviderKey> = orchestrations.iter().map(Self::provider_key).collect();
+        state.health.retain(|key, _| keys.contains(key));
+        state.orchestrations = orchestrations;
+    }
+
+    pub fn next(&self) -> Option<Orchestration> {
+        self.next_excluding(&[])
+    }
+
+    pub fn report_success(&self, orchestration: &Orchestration) {
+        let mut state = self.lock_state();
+        state.health.remove(&Self::provider_key(orchestration));
+    }
+
+    pub fn report_failure(&self, orchestration: &Orchestration) {
+        let now = Instant::now();
+        let health_policy = self.health_policy;
+        let mut state = self.lock_state();
+        let health = state
+            .health
+            .entry(Self::provider_key(orchestration))
+            .or_default();
+        health.consecutive_failures += 1;
+        health.last_failure = Some(now);
+        if health.consecutive_failures >= health_policy.max_failures {
+            health.consecutive_failures = 0;
+            health.ejected_until = Some(now + health_policy.ejection_time);
+        }
+    }
+
+    pub fn health(&self, orchestration: &Orchestration) -> ProviderHealth {
+        self.lock_state()
+            .health
+            .get(&Self::provider_key(orchestration))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn is_ejected(&self, orchestration: &Orchestration) -> bool {
+        self.health(orchestration).is_ejected(Instant::now())
+    }
+
+    pub fn invoke_with<R, F>(&self, mut call: F) -> Result<R>
+    where
+        F: FnMut(&Orchestration) -> Result<R>,
+    {
+        let mut tried = Vec::new();
+        let mut last_error = None;
+        while let Some(orchestration) = self.next_excluding(&tried) {
+            match call(&orchestration) {
+                Ok(result) => {
+                    self.report_success(&orchestration);
+                    return Ok(result);
+                }
+                Err(err) => {
+                    self.report_failure(&orchestration);
+                    tried.push(Self::provider_key(&orchestration));
+                    last_error = Some(err);
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            Error::InvocationError("There is no healthy provider to invoke".to_owned())
+        }))
+    }
+
+    fn next_excluding(&self, excluded: &[ProviderKey]) -> Option<Orchestration> {
+        let now = Instant::now();
+        let mut state = self.lock_state();
+        let candidates: Vec<&Orchestration> = state
+            .orchestrations
+            .iter()
+            .filter(|o| {
+                let key = Self::provider_key(o);
+                !excluded.contains(&key)
+                    && !state.health.get(&key).is_some_and(|h| h.is_ejected(now))
+            })
+            .collect();
+        if candidates.is_empty() {
+            return None;
+        }
+
+        let selected = match &self.strategy {
+            SelectionStrategy::PriorityOrder => candidates[0],
+            SelectionStrategy::RoundRobin => {
+                candidates[state.round_robin_counter % candidates.len()]
+            }
+            SelectionStrategy::Random => candidates[random::random_index(candidates.len())],
+            SelectionStrategy::LeastRecentlyFailed => candidates
+                .iter()
+                .min_by_key(|o| {
+                    state
+                        .health
+                        .get(&Self::provider_key(o))
+                        .and_then(|h| h.last_failure)
+                })
+                .copied()
+                .unwrap_or(candidates[0]),
+            SelectionStrategy::PreferredInterface(interface_name) => candidates
+                .iter()
+                .find(|o| o.select_interface(Some(interface_name)).is_some())
+                .copied()
+                .unwrap_or(candidates[0]),
+            SelectionStrategy::LowestLatency(latencies) => candidates
+                .iter()
+                .min_by_key(|o| {
+                    latencies
+                        .get(&o.provider.entry_tag.id)
+                        .copied()
+                        .unwrap_or(u32::MAX)
+                })
+                .copied()
+                .unwrap_or(candidates[0]),
+        }
+        .clone();
+
+        state.round_robin_counter = state.round_robin_counter.wrapping_add(1);
+        Some(selected)
+    }
+
+    fn lock_state(&self) -> MutexGuard<'_, SelectorState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn provider_key(orchestration: &Orchestration) -> ProviderKey {
+        (
+            orchestration.provider.entry_tag.id,
+            orchestration.service_uri.clone(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dtos::{
+        ArrowheadSystem, EntryTag, InterfaceEntry, SecurityType, ServiceDefinitionEntry,
+    };
+    use crate::qos_monitor::{QosIntraMeasurement, QosIntraPingMeasurement, QosPingStatistics};
+
+    fn orchestration(provider_id: u32, interface_name: &str) -> Orchestration {
+        let entry_tag = EntryTag {
+            id: provider_id,
+            created_at: "string".to_owned(),
+            updated_at: "string".to_owned(),
+        };
+        Orchestration {
+            provider: ArrowheadSystem {
+                entry_tag,
+                system_name: format!("provider{}", provider_id),
+                address: "127.0.0.1".to_owned(),
+                port: 8080,
+                authentication_info: None,
+                metadata: None,
+            },
+            service: ServiceDefinitionEntry::Value("temperature".to_owned()),
+            service_uri: "/temperature".to_owned(),
+            secure: SecurityType::NotSecure,
+            metadata: HashMap::new(),
+            interfaces: vec![InterfaceEntry::Value(interface_name.to_owned())],
+            version: 1,
+            authorization_tokens: None,
+            warnings: vec![],
+        }
+    }
+
+    fn orchestrations() -> Vec<Orchestration> {
+        vec![
+            orchestration(1, "HTTP-INSECURE-JSON"),
+            orchestration(2, "HTTP-SECURE-JSON"),
+            orchestration(3, "HTTP-INSECURE-JSON"),
+        ]
+    }
+
+    fn next_ids(selector: &ProviderSelector, count: usize) -> Vec<u32> {
+        (0..count)
+            .map(|_| selector.next().unwrap().provider.entry_tag.id)
+            .collect()
+    }
+
+    #[test]
+    fn priority_order() {
+        let selector = ProviderSelector::new(orchestrations(), SelectionStrategy::PriorityOrder);
+
+        assert_eq!(next_ids(&selector, 3), vec![1, 1, 1]);
+    }
+
+    #[test]
+    fn round_robin() {
+        let selector = ProviderSelector::new(orchestrations(), SelectionStrategy::RoundRobin);
+
+        assert_eq!(next_ids(&selector, 4), vec![1, 2, 3, 1]);
+    }
+
+    #[test]
+    fn random() {
+        let selector = ProviderSelector::new(orchestrations(), SelectionStrategy::Random);
+
+        assert!(next_ids(&selector, 10)
+            .iter()
+            .all(|id| (1..=3).contains(id)));
+    }
+
+    #[test]
+    fn preferred_interface() {
+        let selector = ProviderSelector::new(
+            orchestrations(),
+            SelectionStrategy::PreferredInterface("HTTP-SECURE-JSON".to_owned()),
+        );
+
+        assert_eq!(next_ids(&selector, 2), vec![2, 2]);
+    }
+
+    #[test]
+    fn lowest_latency() {
+        let statistics = |mean_response_time| QosPingStatistics {
+            available: Some(true),
+            last_access_at: None,
+            min_response_time: 0,
+            max_response_time_with_timeout: 0,
+            max_response_time_without_timeout: 0,
+            mean_response_time_with_timeout: 0,
+            mean_response_time_without_timeout: mean_response_time,
+            jitter_with_timeout: 0,
+            jitter_without_timeout: 0,
+            lost_per_measurement_percent: 0,
+            sent: 0,
+            received: 0,
+            count_started_at: None,
+            sent_all: 0,
+            received_all: 0,
+        };
+        let measurement = |system_id, mean_response_time| QosIntraPingMeasurement {
+            entry_tag: orchestration(system_id, "").provider.entry_tag,
+            measurement: QosIntraMeasurement {
+                entry_tag: orchestration(system_id, "").provider.entry_tag,
+                system: orchestration(system_id, "").provider,
+                measurement_type: crate::qos_monitor::QosMeasurementType::Ping,
+                last_measurement_at: "string".to_owned(),
+            },
+            statistics: statistics(mean_response_time),
+        };
+        let selector = ProviderSelector::new(
+            orchestrations(),
+            SelectionStrategy::lowest_latency(&QosIntraPingMeasurementList {
+                data: vec![measurement(1, 40), measurement(3, 15)],
+                count: 2,
+            }),
+        );
+
+        assert_eq!(next_ids(&selector, 1), vec![3]);
+    }
+
+    #[test]
+    fn least_recently_failed() {
+        let orchestrations = orchestrations();
+        let selector = ProviderSelector::new(
+            orchestrations.clone(),
+            SelectionStrategy::LeastRecentlyFailed,
+        );
+        selector.report_failure(&orchestrations[0]);
+        selector.report_failure(&orchestrations[1]);
+
+        assert_eq!(next_ids(&selector, 1), vec![3]);
+        selector.report_failure(&orchestrations[2]);
+        assert_eq!(next_ids(&selector, 1), vec![1]);
+    }
+
+    #[test]
+    fn eject_failing_provider() {
+        let orchestrations = orchestrations();
+        let selector =
+            ProviderSelector::new(orchestrations.clone(), SelectionStrategy::PriorityOrder)
+                .with_health_policy(HealthPolicy {
+                    max_failures: 2,
+                    ejection_time: Duration::from_secs(60),
+                });
+        selector.report_failure(&orchestrations[0]);
+        assert!(!selector.is_ejected(&orchestrations[0]));
+        selector.report_failure(&orchestrations[0]);
+
+        assert!(selector.is_ejected(&orchestrations[0]));
+        assert_eq!(next_ids(&selector, 1), vec![2]);
+
+        selector.update_orchestrations(vec![orchestrations[0].clone()]);
+        assert!(selector.next().is_none());
+    }
+
+    #[test]
+    fn invoke_with_fallback() {
+        let selector = ProviderSelector::new(orchestrations(), SelectionStrategy::PriorityOrder);
+        let result = selector.invoke_with(|o| match o.provider.entry_tag.id {
+            1 => Err(Error::InvocationError("string".to_owned())),
+            id => Ok(id),
+        });
+
+        assert!(matches!(result, Ok(2)));
+        assert_eq!(
+            selector.health(&orchestrations()[0]).consecutive_failures,
+            1
+        );
+
+        let result: Result<()> =
+            selector.invoke_with(|_| Err(Error::InvocationError("string".to_owned())));
+        assert!(matches!(result, Err(Error::InvocationError(_))));
+    }
+}
@@ -0,0 +1,639 @@
+use crate::dtos::{
+    ArrowheadCloud, ArrowheadCloudEntry, ArrowheadRelay, ArrowheadSystem, EntryTag, NoEntryTag,
+    Orchestration, PageRequest,
+};
+use crate::error::{Error, Result};
+use crate::instrumentation::{self, SendObserved, QOS_MONITOR};
+
+use reqwest::blocking::Client;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum QosMeasurementType {
+    Ping,
+    RelayEcho,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum QosMeasurementStatus {
+    New,
+    Pending,
+    Finished,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct QosPingStatistics {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub available: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_access_at: Option<String>,
+    pub min_response_time: u32,
+    pub max_response_time_with_timeout: u32,
+    pub max_response_time_without_timeout: u32,
+    pub mean_response_time_with_timeout: u32,
+    pub mean_response_time_without_timeout: u32,
+    pub jitter_with_timeout: u32,
+    pub jitter_without_timeout: u32,
+    pub lost_per_measurement_percent: u32,
+    pub sent: u64,
+    pub received: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count_started_at: Option<String>,
+    pub sent_all: u64,
+    pub received_all: u64,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct QosIntraMeasurement {
+    #[serde(flatten)]
+    pub entry_tag: EntryTag,
+    pub system: ArrowheadSystem<EntryTag>,
+    pub measurement_type: QosMeasurementType,
+    pub last_measurement_at: String,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct QosIntraPingMeasurement {
+    #[serde(flatten)]
+    pub entry_tag: EntryTag,
+    pub measurement: QosIntraMeasurement,
+    #[serde(flatten)]
+    pub statistics: QosPingStatistics,
+}
+impl QosIntraPingMeasurement {
+    pub fn system_id(&self) -> u32 {
+        self.measurement.system.entry_tag.id
+    }
+
+    // Results from other clouds are never measured by the local QoS Monitor, their provider ids
+    // belong to the Service Registry of the other cloud.
+    pub fn is_measurement_of(&self, orchestration: &Orchestration) -> bool {
+        !orchestration.is_from_other_cloud()
+            && self.system_id() == orchestration.provider.entry_tag.id
+    }
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct QosIntraPingMeasurementList {
+    pub data: Vec<QosIntraPingMeasurement>,
+    pub count: u32,
+}
+impl QosIntraPingMeasurementList {
+    pub fn find_by_system_id(&self, system_id: u32) -> Option<&QosIntraPingMeasurement> {
+        self.data.iter().find(|m| m.system_id() == system_id)
+    }
+
+    pub fn find_for_orchestration(
+        &self,
+        orchestration: &Orchestration,
+    ) -> Option<&QosIntraPingMeasurement> {
+        self.data
+            .iter()
+            .find(|m| m.is_measurement_of(orchestration))
+    }
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct QosInterDirectMeasurement {
+    #[serde(flatten)]
+    pub entry_tag: EntryTag,
+    pub cloud: ArrowheadCloudEntry,
+    pub address: String,
+    pub port: u32,
+    pub measurement_type: QosMeasurementType,
+    pub last_measurement_at: String,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct QosInterDirectPingMeasurement {
+    #[serde(flatten)]
+    pub entry_tag: EntryTag,
+    pub measurement: QosInterDirectMeasurement,
+    #[serde(flatten)]
+    pub statistics: QosPingStatistics,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct QosInterDirectPingMeasurementList {
+    pub data: Vec<QosInterDirectPingMeasurement>,
+    pub count: u32,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct QosInterRelayMeasurement {
+    #[serde(flatten)]
+    pub entry_tag: EntryTag,
+    pub cloud: ArrowheadCloudEntry,
+    pub relay: ArrowheadRelay<EntryTag>,
+    pub measurement_type: QosMeasurementType,
+    pub status: QosMeasurementStatus,
+    pub last_measurement_at: String,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct QosInterRelayEchoMeasurement {
+    #[serde(flatten)]
+    pub entry_tag: EntryTag,
+    pub measurement: QosInterRelayMeasurement,
+    #[serde(flatten)]
+    pub statistics: QosPingStatistics,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct QosInterRelayEchoMeasurementList {
+    pub data: Vec<QosInterRelayEchoMeasurement>,
+    pub count: u32,
+}
+
+#[derive(Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CloudSystemForm {
+    pub cloud: ArrowheadCloud,
+    pub system: ArrowheadSystem<NoEntryTag>,
+}
+
+#[derive(Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CloudRelayForm {
+    pub cloud: ArrowheadCloud,
+    pub relay: ArrowheadRelay<NoEntryTag>,
+}
+
+pub struct QosMonitorAdapter {
+    pub qos_monitor_address: Url,
+}
+
+impl QosMonitorAdapter {
+    pub fn new(qos_monitor_address: &str) -> Result<Self> {
+        Ok(QosMonitorAdapter {
+            qos_monitor_address: qos_monitor_address.try_into()?,
+        })
+    }
+
+    pub fn echo_qos_monitor(&self) -> Result<()> {
+        instrumentation::observe(QOS_MONITOR, "echo_qos_monitor", || {
+            let client = Client::new();
+            client
+                .get(self.qos_monitor_address.join("echo")?)
+                .send_observed()?
+                .error_for_status()?;
+            Ok(())
+        })
+    }
+
+    pub fn get_ping_measurements(
+        &self,
+        page_request: &PageRequest,
+    ) -> Result<QosIntraPingMeasurementList> {
+        instrumentation::observe(QOS_MONITOR, "get_ping_measurements", || {
+            let client = Client::new();
+            let mut url = self.qos_monitor_address.join("mgmt/ping/measurements")?;
+            page_request.append_query_pairs(&mut url);
+            let response = client.get(url).send_observed()?;
+            if response.status().is_client_error() {
+                Err(Error::ArrowheadError(response.json()?))
+            } else {
+                Ok(response.json()?)
+            }
+        })
+    }
+
+    pub fn get_ping_measurement_by_system_id(
+        &self,
+        system_id: u32,
+    ) -> Result<QosIntraPingMeasurement> {
+        instrumentation::observe(QOS_MONITOR, "get_ping_measurement_by_system_id", || {
+            let client = Client::new();
+            let response = client
+                .get(
+                    self.qos_monitor_address
+                        .join(&format!("mgmt/ping/measurements/{}", system_id))?,
+                )
+                .send_observed()?;
+            if response.status().is_client_error() {
+                Err(Error::ArrowheadError(response.json()?))
+            } else {
+                Ok(response.json()?)
+            }
+        })
+    }
+
+    pub fn get_ping_measurement_for_orchestration(
+        &self,
+        orchestration: &Orchestration,
+    ) -> Result<QosIntraPingMeasurement> {
+        if orchestration.is_from_other_cloud() {
+            return Err(Error::UnsupportedError(
+                "The intra-cloud measurements do not cover providers of other clouds, use \
+                 get_inter_direct_ping_measurement_for_orchestration"
+                    .to_owned(),
+            ));
+        }
+        self.get_ping_measurement_by_system_id(orchestration.provider.entry_tag.id)
+    }
+
+    // The direct ping measurement of the provider of an inter-cloud result. The orchestration
+    // results do not name the cloud of the provider, so it is given by the caller (e.g. from the
+    // Gatekeeper's global service discovery).
+    pub fn get_inter_direct_ping_measurement_for_orchestration(
+        &self,
+        orchestration: &Orchestration,
+        provider_cloud: &ArrowheadCloud,
+    ) -> Result<QosInterDirectPingMeasurement> {
+        if !orchestration.is_from_other_cloud() {
+            return Err(Error::UnsupportedError(
+                "The orchestration result is not from another cloud, use \
+                 get_ping_measurement_for_orchestration"
+                    .to_owned(),
+            ));
+        }
+        let provider = &orchestration.provider;
+        self.get_inter_direct_ping_measurement(&CloudSystemForm {
+            cloud: provider_cloud.clone(),
+            system: ArrowheadSystem {
+                entry_tag: NoEntryTag {},
+                system_name: provider.system_name.clone(),
+                address: provider.address.clone(),
+                port: provider.port,
+                authentication_info: provider.authentication_info.clone(),
+                metadata: provider.metadata.clone(),
+            },
+        })
+    }
+
+    pub fn get_inter_direct_ping_measurements(
+        &self,
+        page_request: &PageRequest,
+    ) -> Result<QosInterDirectPingMeasurementList> {
+        instrumentation::observe(QOS_MONITOR, "get_inter_direct_ping_measurements", || {
+            let client = Client::new();
+            let mut url = self.qos_monitor_address.join("mgmt/direct/measurements")?;
+            page_request.append_query_pairs(&mut url);
+            let response = client.get(url).send_observed()?;
+            if response.status().is_client_error() {
+                Err(Error::ArrowheadError(response.json()?))
+            } else {
+                Ok(response.json()?)
+            }
+        })
+    }
+
+    pub fn get_inter_direct_ping_measurement(
+        &self,
+        cloud_system_form: &CloudSystemForm,
+    ) -> Result<QosInterDirectPingMeasurement> {
+        instrumentation::observe(QOS_MONITOR, "get_inter_direct_ping_measurement", || {
+            let client = Client::new();
+            let response = client
+                .post(
+                    self.qos_monitor_address
+                        .join("mgmt/direct/measurement/pair_results")?,
+                )
+                .json(cloud_system_form)
+                .send_observed()?;
+            if response.status().is_client_error() {
+                Err(Error::ArrowheadError(response.json()?))
+            } else {
+                Ok(response.json()?)
+            }
+        })
+    }
+
+    pub fn get_inter_relay_echo_measurements(
+        &self,
+        page_request: &PageRequest,
+    ) -> Result<QosInterRelayEchoMeasurementList> {
+        instrumentation::observe(QOS_MONITOR, "get_inter_relay_echo_measurements", || {
+            let client = Client::new();
+            let mut url = self.qos_monitor_address.join("mgmt/relay/measurements")?;
+            page_request.append_query_pairs(&mut url);
+            let response = client.get(url).send_observed()?;
+            if response.status().is_client_error() {
+                Err(Error::ArrowheadError(response.json()?))
+            } else {
+                Ok(response.json()?)
+            }
+        })
+    }
+
+    pub fn get_inter_relay_echo_measurement(
+        &self,
+        cloud_relay_form: &CloudRelayForm,
+    ) -> Result<QosInterRelayEchoMeasurement> {
+        instrumentation::observe(QOS_MONITOR, "get_inter_relay_echo_measurement", || {
+            let client = Client::new();
+            let response = client
+                .post(
+                    self.qos_monitor_address
+                        .join("mgmt/relay/measurement/pair_results")?,
+                )
+                .json(cloud_relay_form)
+                .send_observed()?;
+            if response.status().is_client_error() {
+                Err(Error::ArrowheadError(response.json()?))
+            } else {
+                Ok(response.json()?)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dtos::{RelayType, SecurityType, ServiceDefinitionEntry};
+    use mockito::Matcher;
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+
+    fn ping_statistics_json() -> Value {
+        json!({
+          "available": true,
+          "lastAccessAt": "string",
+          "minResponseTime": 1,
+          "maxResponseTimeWithTimeout": 5,
+          "maxResponseTimeWithoutTimeout": 4,
+          "meanResponseTimeWithTimeout": 3,
+          "meanResponseTimeWithoutTimeout": 2,
+          "jitterWithTimeout": 1,
+          "jitterWithoutTimeout": 1,
+          "lostPerMeasurementPercent": 0,
+          "sent": 10,
+          "received": 10,
+          "countStartedAt": "string",
+          "sentAll": 100,
+          "receivedAll": 99,
+          "createdAt": "string",
+          "updatedAt": "string"
+        })
+    }
+
+    fn ping_measurement_json(id: u32, system_id: u32) -> Value {
+        let mut measurement = ping_statistics_json();
+        measurement["id"] = json!(id);
+        measurement["measurement"] = json!({
+          "id": id,
+          "system": {
+            "id": system_id,
+            "systemName": "string",
+            "address": "string",
+            "port": 0,
+            "createdAt": "string",
+            "updatedAt": "string"
+          },
+          "measurementType": "PING",
+          "lastMeasurementAt": "string",
+          "createdAt": "string",
+          "updatedAt": "string"
+        });
+        measurement
+    }
+
+    fn orchestration(provider_id: u32) -> Orchestration {
+        Orchestration {
+            provider: ArrowheadSystem {
+                entry_tag: EntryTag {
+                    id: provider_id,
+                    created_at: "string".to_owned(),
+                    updated_at: "string".to_owned(),
+                },
+                system_name: "string".to_owned(),
+                address: "string".to_owned(),
+                port: 0,
+                authentication_info: None,
+                metadata: None,
+            },
+            service: ServiceDefinitionEntry::Value("string".to_owned()),
+            service_uri: "string".to_owned(),
+            secure: SecurityType::NotSecure,
+            metadata: HashMap::new(),
+            interfaces: vec![],
+            version: 0,
+            authorization_tokens: None,
+            warnings: vec![],
+        }
+    }
+
+    #[test]
+    fn get_ping_measurements() {
+        let mock = mockito::mock("GET", "/mgmt/ping/measurements")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("page".into(), "1".into()),
+                Matcher::UrlEncoded("item_per_page".into(), "2".into()),
+                Matcher::UrlEncoded("direction".into(), "DESC".into()),
+            ]))
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                  "data": [ping_measurement_json(1, 11), ping_measurement_json(2, 12)],
+                  "count": 2
+                })
+                .to_string(),
+            )
+            .create();
+        let adapter = QosMonitorAdapter::new(&mockito::server_url()).unwrap();
+        let result = adapter
+            .get_ping_measurements(&PageRequest {
+                page: Some(1),
+                item_per_page: Some(2),
+                direction: Some(crate::dtos::SortDirection::Desc),
+                sort_field: None,
+            })
+            .unwrap();
+        let measurement = result.find_for_orchestration(&orchestration(12)).unwrap();
+
+        assert_eq!(result.count, 2);
+        assert_eq!(measurement.entry_tag.id, 2);
+        assert_eq!(measurement.statistics.received_all, 99);
+        assert!(result.find_by_system_id(13).is_none());
+        mock.assert();
+    }
+
+    #[test]
+    fn get_ping_measurement_for_orchestration() {
+        let mock = mockito::mock("GET", "/mgmt/ping/measurements/11")
+            .with_header("content-type", "application/json")
+            .with_body(ping_measurement_json(1, 11).to_string())
+            .create();
+        let adapter = QosMonitorAdapter::new(&mockito::server_url()).unwrap();
+        let orchestration = orchestration(11);
+        let result = adapter.get_ping_measurement_for_orchestration(&orchestration);
+
+        assert!(matches!(result, Ok(measurement) if measurement.is_measurement_of(&orchestration)));
+        mock.assert();
+    }
+
+    #[test]
+    fn get_inter_direct_ping_measurement_for_orchestration() {
+        let mut body = ping_statistics_json();
+        body["id"] = json!(4);
+        body["measurement"] = json!({
+          "id": 4,
+          "cloud": {
+            "id": 2,
+            "operator": "operator",
+            "name": "cloud",
+            "secure": false,
+            "neighbor": true,
+            "ownCloud": false,
+            "createdAt": "string",
+            "updatedAt": "string"
+          },
+          "address": "string",
+          "port": 0,
+          "measurementType": "PING",
+          "lastMeasurementAt": "string",
+          "createdAt": "string",
+          "updatedAt": "string"
+        });
+        let mock = mockito::mock("POST", "/inter/mgmt/direct/measurement/pair_results")
+            .match_body(Matcher::Json(json!({
+                "cloud": { "operator": "operator", "name": "cloud" },
+                "system": { "systemName": "string", "address": "string", "port": 0 }
+            })))
+            .with_header("content-type", "application/json")
+            .with_body(body.to_string())
+            .create();
+        let adapter = QosMonitorAdapter::new(&format!("{}/inter/", mockito::server_url())).unwrap();
+        let cloud = ArrowheadCloud {
+            operator: "operator".to_owned(),
+            name: "cloud".to_owned(),
+        };
+        let mut orchestration = orchestration(11);
+
+        assert!(matches!(
+            adapter.get_inter_direct_ping_measurement_for_orchestration(&orchestration, &cloud),
+            Err(Error::UnsupportedError(_))
+        ));
+        orchestration
+            .warnings
+            .push(crate::dtos::OrchestrationWarning::FromOtherCloud);
+        assert!(matches!(
+            adapter.get_ping_measurement_for_orchestration(&orchestration),
+            Err(Error::UnsupportedError(_))
+        ));
+        let result =
+            adapter.get_inter_direct_ping_measurement_for_orchestration(&orchestration, &cloud);
+
+        assert!(
+            matches!(result, Ok(measurement) if measurement.measurement.cloud.entry_tag.id == 2)
+        );
+        assert!(QosIntraPingMeasurementList {
+            data: vec![serde_json::from_value(ping_measurement_json(1, 11)).unwrap()],
+            count: 1,
+        }
+        .find_for_orchestration(&orchestration)
+        .is_none());
+        mock.assert();
+    }
+
+    #[test]
+    fn get_ping_measurement_arrowhead_error() {
+        let mock = mockito::mock("GET", "/mgmt/ping/measurements/11")
+            .with_status(400)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                  "errorMessage": "string",
+                  "errorCode": 400,
+                  "exceptionType": "INVALID_PARAMETER",
+                  "origin": "string"
+                })
+                .to_string(),
+            )
+            .create();
+        let adapter = QosMonitorAdapter::new(&mockito::server_url()).unwrap();
+        let result = adapter.get_ping_measurement_by_system_id(11);
+
+        assert!(matches!(result, Err(Error::ArrowheadError(_))));
+        mock.assert();
+    }
+
+    #[test]
+    fn get_inter_relay_echo_measurement() {
+        let mut body = ping_statistics_json();
+        body["id"] = json!(3);
+        body["measurement"] = json!({
+          "id": 3,
+          "cloud": {
+            "id": 1,
+            "operator": "operator",
+            "name": "cloud",
+            "secure": false,
+            "neighbor": true,
+            "ownCloud": false,
+            "createdAt": "string",
+            "updatedAt": "string"
+          },
+          "relay": {
+            "id": 2,
+            "address": "relay",
+            "port": 1883,
+            "secure": false,
+            "exclusive": false,
+            "type": "GENERAL_RELAY",
+            "createdAt": "string",
+            "updatedAt": "string"
+          },
+          "measurementType": "RELAY_ECHO",
+          "status": "FINISHED",
+          "lastMeasurementAt": "string",
+          "createdAt": "string",
+          "updatedAt": "string"
+        });
+        let mock = mockito::mock("POST", "/mgmt/relay/measurement/pair_results")
+            .match_body(Matcher::Json(json!({
+                "cloud": { "operator": "operator", "name": "cloud" },
+                "relay": {
+                  "address": "relay",
+                  "port": 1883,
+                  "secure": false,
+                  "exclusive": false,
+                  "type": "GENERAL_RELAY"
+                }
+            })))
+            .with_header("content-type", "application/json")
+            .with_body(body.to_string())
+            .create();
+        let adapter = QosMonitorAdapter::new(&mockito::server_url()).unwrap();
+        let result = adapter.get_inter_relay_echo_measurement(&CloudRelayForm {
+            cloud: ArrowheadCloud {
+                operator: "operator".to_owned(),
+                name: "cloud".to_owned(),
+            },
+            relay: ArrowheadRelay {
+                entry_tag: NoEntryTag {},
+                address: "relay".to_owned(),
+                port: 1883,
+                secure: false,
+                exclusive: false,
+                relay_type: RelayType::GeneralRelay,
+            },
+        });
+
+        assert!(matches!(
+            result,
+            Ok(QosInterRelayEchoMeasurement {
+                measurement: QosInterRelayMeasurement {
+                    status: QosMeasurementStatus::Finished,
+                    ..
+                },
+                ..
+            })
+        ));
+        mock.assert();
+    }
+}
@@ -0,0 +1,9 @@
+use rand::Rng;
+
+pub(crate) fn random_index(len: usize) -> usize {
+    rand::thread_rng().gen_range(0..len)
+}
+
+pub(crate) fn random_u64() -> u64 {
+    rand::thread_rng().gen()
+}
@@ -0,0 +1,42 @@
+#[cfg(any(feature = "mqtt", feature = "coap"))]
+use crate::error::Error;
+use crate::error::Result;
+
+use reqwest::blocking::{Client, Request, Response};
+
+// Carries the requests of the adapter to the core systems and returns their responses. The
+// requests keep their HTTP form (method, URL with the query parameters and JSON body) whatever
+// the transport, so the operations, the failover, the circuit breaker and the middleware are the
+// same over HTTP, MQTT and CoAP. A client is the HTTP transport.
+pub trait Transport: Send + Sync {
+    fn execute(&self, request: Request) -> Result<Response>;
+}
+
+impl Transport for Client {
+    fn execute(&self, request: Request) -> Result<Response> {
+        Ok(Client::execute(self, request)?)
+    }
+}
+
+// The body of the request, empty if it has none. Streamed bodies are not supported.
+#[cfg(any(feature = "mqtt", feature = "coap"))]
+pub(crate) fn request_body(request: &Request) -> Result<&[u8]> {
+    match request.body() {
+        None => Ok(&[]),
+        Some(body) => body.as_bytes().ok_or_else(|| {
+            Error::TransportError("Streamed request bodies are not supported".to_owned())
+        }),
+    }
+}
+
+// The response received by a transport other than HTTP, with the status of the equivalent HTTP
+// response.
+#[cfg(any(feature = "mqtt", feature = "coap"))]
+pub(crate) fn response(status: u16, content_type: &str, body: Vec<u8>) -> Result<Response> {
+    let response = http::Response::builder()
+        .status(status)
+        .header(http::header::CONTENT_TYPE, content_type)
+        .body(body)
+        .map_err(|e| Error::TransportError(format!("Invalid response status {}: {}", status, e)))?;
+    Ok(response.into())
+}
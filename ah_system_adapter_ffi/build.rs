@@ -0,0 +1,197 @@
+// Generates the C header ah_system_adapter.h in OUT_DIR from the exported items of src/lib.rs:
+// the public structs become opaque types, the `#[repr(C)]` enums become C enums and the
+// `#[no_mangle] extern "C"` functions become prototypes. The doc comments are kept. The
+// checked-in include/ah_system_adapter.h is compared to it by tests/header.rs.
+
+use syn::{
+    Attribute, Expr, Fields, FnArg, Item, ItemEnum, ItemFn, Lit, PathArguments, ReturnType, Type,
+    Visibility,
+};
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+const SOURCE: &str = "src/lib.rs";
+const HEADER: &str = "ah_system_adapter.h";
+const GUARD: &str = "AH_SYSTEM_ADAPTER_H";
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", SOURCE);
+    let source = fs::read_to_string(SOURCE).expect("the source is readable");
+    let file = syn::parse_file(&source).expect("the source is valid Rust");
+
+    let mut header = format!(
+        "/* Generated by build.rs from {}, do not edit. */\n\n#ifndef {guard}\n#define {guard}\n\n\
+         #include <stdbool.h>\n#include <stdint.h>\n\n#ifdef __cplusplus\nextern \"C\" {{\n#endif\n",
+        SOURCE,
+        guard = GUARD
+    );
+    for item in &file.items {
+        match item {
+            Item::Struct(item) if is_public(&item.vis) => {
+                header.push_str(&doc(&item.attrs, ""));
+                header.push_str(&format!("typedef struct {0} {0};\n", item.ident));
+            }
+            Item::Enum(item) if is_public(&item.vis) && has_attribute(&item.attrs, "repr") => {
+                header.push_str(&c_enum(item));
+            }
+            Item::Fn(item) if is_public(&item.vis) && has_attribute(&item.attrs, "no_mangle") => {
+                header.push_str(&prototype(item));
+            }
+            _ => continue,
+        }
+    }
+    header.push_str(&format!(
+        "\n#ifdef __cplusplus\n}}\n#endif\n\n#endif /* {} */\n",
+        GUARD
+    ));
+
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR is set by cargo");
+    fs::write(Path::new(&out_dir).join(HEADER), header).expect("the header is writable");
+}
+
+fn is_public(vis: &Visibility) -> bool {
+    matches!(vis, Visibility::Public(_))
+}
+
+fn has_attribute(attrs: &[Attribute], name: &str) -> bool {
+    attrs.iter().any(|attr| attr.path().is_ident(name))
+}
+
+// The doc comments as a C comment, without the Rust specific sections (e.g. `# Safety`).
+fn doc(attrs: &[Attribute], indent: &str) -> String {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta.require_name_value().ok()?.value {
+            Expr::Lit(expr) => match &expr.lit {
+                Lit::Str(line) => Some(line.value().trim().to_owned()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .take_while(|line| !line.starts_with('#'))
+        .collect();
+    let lines: Vec<&String> = lines
+        .iter()
+        .rev()
+        .skip_while(|line| line.is_empty())
+        .collect();
+    if lines.is_empty() {
+        return String::new();
+    }
+    let mut comment = format!("\n{}/*\n", indent);
+    for line in lines.into_iter().rev() {
+        comment.push_str(&format!("{} * {}\n", indent, line).replace(" * \n", " *\n"));
+    }
+    comment.push_str(&format!("{} */\n", indent));
+    comment
+}
+
+fn c_enum(item: &ItemEnum) -> String {
+    let prefix = screaming_snake_case(&item.ident.to_string());
+    let mut c_enum = doc(&item.attrs, "");
+    c_enum.push_str(&format!("typedef enum {} {{\n", item.ident));
+    for variant in &item.variants {
+        assert!(
+            matches!(variant.fields, Fields::Unit),
+            "C enums have no fields"
+        );
+        c_enum.push_str(doc(&variant.attrs, "    ").trim_start_matches('\n'));
+        let value = match &variant.discriminant {
+            Some((_, Expr::Lit(expr))) => match &expr.lit {
+                Lit::Int(value) => format!(" = {}", value.base10_digits()),
+                _ => panic!("unsupported discriminant"),
+            },
+            _ => String::new(),
+        };
+        c_enum.push_str(&format!(
+            "    {}_{}{},\n",
+            prefix,
+            screaming_snake_case(&variant.ident.to_string()),
+            value
+        ));
+    }
+    c_enum.push_str(&format!("}} {};\n", item.ident));
+    c_enum
+}
+
+fn prototype(item: &ItemFn) -> String {
+    let output = match &item.sig.output {
+        ReturnType::Default => "void".to_owned(),
+        ReturnType::Type(_, ty) => c_type(ty),
+    };
+    let inputs: Vec<String> = item
+        .sig
+        .inputs
+        .iter()
+        .map(|input| match input {
+            FnArg::Typed(input) => match &*input.pat {
+                syn::Pat::Ident(name) => declaration(&c_type(&input.ty), &name.ident.to_string()),
+                _ => panic!("unsupported argument pattern"),
+            },
+            FnArg::Receiver(_) => panic!("exported functions have no receiver"),
+        })
+        .collect();
+    format!(
+        "{}{}({});\n",
+        doc(&item.attrs, ""),
+        declaration(&output, &item.sig.ident.to_string()),
+        if inputs.is_empty() {
+            "void".to_owned()
+        } else {
+            inputs.join(", ")
+        }
+    )
+}
+
+// Declares the name with the type, in the `char **name` style.
+fn declaration(ty: &str, name: &str) -> String {
+    if ty.ends_with('*') {
+        format!("{}{}", ty, name)
+    } else {
+        format!("{} {}", ty, name)
+    }
+}
+
+fn c_type(ty: &Type) -> String {
+    match ty {
+        Type::Ptr(ptr) => {
+            let pointee = c_type(&ptr.elem);
+            let pointee = match ptr.const_token {
+                Some(_) if !pointee.ends_with('*') => format!("const {}", pointee),
+                _ => pointee,
+            };
+            declaration(&pointee, "*")
+        }
+        Type::Path(path) => {
+            let segment = path.path.segments.last().expect("the type has a name");
+            assert!(
+                matches!(segment.arguments, PathArguments::None),
+                "generic types are not supported"
+            );
+            match segment.ident.to_string().as_str() {
+                "c_char" => "char".to_owned(),
+                "c_int" => "int".to_owned(),
+                "i8" | "i16" | "i32" | "i64" => format!("int{}_t", &segment.ident.to_string()[1..]),
+                "u8" | "u16" | "u32" | "u64" => {
+                    format!("uint{}_t", &segment.ident.to_string()[1..])
+                }
+                name => name.to_owned(),
+            }
+        }
+        _ => panic!("unsupported type"),
+    }
+}
+
+fn screaming_snake_case(name: &str) -> String {
+    let mut snake_case = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            snake_case.push('_');
+        }
+        snake_case.push(c.to_ascii_uppercase());
+    }
+    snake_case
+}
@@ -0,0 +1,133 @@
+/* Generated by build.rs from src/lib.rs, do not edit. */
+
+#ifndef AH_SYSTEM_ADAPTER_H
+#define AH_SYSTEM_ADAPTER_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/*
+ * Opaque handle of an adapter.
+ */
+typedef struct AhAdapter AhAdapter;
+
+/*
+ * Result codes of the functions, mapping the variants of `Error`.
+ */
+typedef enum AhErrorCode {
+    AH_ERROR_CODE_OK = 0,
+    /*
+     * A null pointer, a string which is not UTF-8, or JSON not matching the expected input
+     */
+    AH_ERROR_CODE_INVALID_ARGUMENT = 1,
+    AH_ERROR_CODE_HTTP_ERROR = 2,
+    /*
+     * The output JSON holds the exception returned by the core system
+     */
+    AH_ERROR_CODE_ARROWHEAD_ERROR = 3,
+    AH_ERROR_CODE_SERVER_ERROR = 4,
+    AH_ERROR_CODE_INVOCATION_ERROR = 5,
+    AH_ERROR_CODE_CIRCUIT_OPEN_ERROR = 6,
+    AH_ERROR_CODE_UNSUPPORTED_ERROR = 7,
+    /*
+     * The library panicked, the adapter should not be used anymore
+     */
+    AH_ERROR_CODE_PANIC = 8,
+    AH_ERROR_CODE_CONFIG_ERROR = 9,
+    /*
+     * A name breaks the naming conventions of the core systems, the request was not sent
+     */
+    AH_ERROR_CODE_VALIDATION_ERROR = 10,
+    /*
+     * A core system answered with a response not matching the request
+     */
+    AH_ERROR_CODE_UNEXPECTED_RESPONSE_ERROR = 11,
+    /*
+     * A transport other than HTTP (MQTT, CoAP) failed, e.g. no response in time
+     */
+    AH_ERROR_CODE_TRANSPORT_ERROR = 12,
+} AhErrorCode;
+
+/*
+ * Creates an adapter of the core systems at the given base URLs. The client system is the JSON
+ * of an Arrowhead system (`systemName`, `address`, `port`, optional `authenticationInfo` and
+ * `metadata`).
+ */
+AhErrorCode ah_adapter_new(const char *service_registry_address, const char *authorization_address, const char *orchestrator_address, const char *client_system_json, AhAdapter **adapter);
+
+/*
+ * Selects the Arrowhead version of the core systems (`"4.4.0"` or `"4.6.0"`).
+ */
+AhErrorCode ah_adapter_set_version(AhAdapter *adapter, const char *version);
+
+/*
+ * Enables (the default) or disables the validation of the names in the registered services and
+ * in the orchestration requests against the naming conventions of the core systems.
+ */
+AhErrorCode ah_adapter_set_naming_validation(AhAdapter *adapter, bool enabled);
+
+/*
+ * Releases the adapter. Null is accepted.
+ */
+void ah_adapter_free(AhAdapter *adapter);
+
+/*
+ * Registers a service of the client system. The input is the JSON of the service without the
+ * provider system, the output is the JSON of the registered service entry.
+ */
+AhErrorCode ah_register_service(const AhAdapter *adapter, const char *input_json, char **output_json);
+
+/*
+ * Unregisters the service of the client system with the given service definition.
+ */
+AhErrorCode ah_unregister_service(const AhAdapter *adapter, const char *service_definition);
+
+/*
+ * Unregisters exactly the given service entry (the JSON returned by `ah_register_service` or an
+ * element of the query result), keeping the other entries of the same service definition.
+ */
+AhErrorCode ah_unregister_service_entry(const AhAdapter *adapter, const char *service_json);
+
+/*
+ * Queries the Service Registry. The input is the JSON of a service query form, the output is the
+ * JSON of the service query list.
+ */
+AhErrorCode ah_query_service(const AhAdapter *adapter, const char *query_form_json, char **output_json);
+
+/*
+ * Requests orchestration for the client system. The input is the JSON of the service request
+ * form without the requester system, the output is the JSON of the orchestration response.
+ */
+AhErrorCode ah_request_orchestration(const AhAdapter *adapter, const char *input_json, char **output_json);
+
+/*
+ * Requests the stored orchestration of the consumer with the given id. The output is the JSON
+ * of the orchestration response.
+ */
+AhErrorCode ah_request_orchestration_by_id(const AhAdapter *adapter, int64_t id, char **output_json);
+
+/*
+ * Returns the public key of the Authorization.
+ */
+AhErrorCode ah_get_public_key(const AhAdapter *adapter, char **public_key);
+
+/*
+ * Returns the message of the last error on the calling thread, or null. The message is valid
+ * until the next call of the library on the same thread.
+ */
+const char *ah_last_error_message(void);
+
+/*
+ * Releases a string returned by the library. Null is accepted.
+ */
+void ah_string_free(char *string);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* AH_SYSTEM_ADAPTER_H */
@@ -0,0 +1,390 @@
+//! C API of ah_system_adapter. The structured inputs and outputs are JSON strings in the format
+//! of the Arrowhead core systems. The strings returned by the library must be released by
+//! `ah_string_free`, the adapters by `ah_adapter_free`.
+
+use ah_system_adapter::{ArrowheadSystem, ArrowheadSystemAdapter, ArrowheadVersion, Error};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+/// Opaque handle of an adapter.
+pub struct AhAdapter {
+    adapter: ArrowheadSystemAdapter,
+}
+
+/// Result codes of the functions, mapping the variants of `Error`.
+#[repr(C)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum AhErrorCode {
+    Ok = 0,
+    /// A null pointer, a string which is not UTF-8, or JSON not matching the expected input
+    InvalidArgument = 1,
+    HttpError = 2,
+    /// The output JSON holds the exception returned by the core system
+    ArrowheadError = 3,
+    ServerError = 4,
+    InvocationError = 5,
+    CircuitOpenError = 6,
+    UnsupportedError = 7,
+    /// The library panicked, the adapter should not be used anymore
+    Panic = 8,
+    ConfigError = 9,
+    /// A name breaks the naming conventions of the core systems, the request was not sent
+    ValidationError = 10,
+    /// A core system answered with a response not matching the request
+    UnexpectedResponseError = 11,
+    /// A transport other than HTTP (MQTT, CoAP) failed, e.g. no response in time
+    TransportError = 12,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+enum FfiError {
+    InvalidArgument(String),
+    Adapter(Error),
+}
+
+impl From<Error> for FfiError {
+    fn from(err: Error) -> Self {
+        Self::Adapter(err)
+    }
+}
+
+type FfiResult<T> = Result<T, FfiError>;
+
+/// Creates an adapter of the core systems at the given base URLs. The client system is the JSON
+/// of an Arrowhead system (`systemName`, `address`, `port`, optional `authenticationInfo` and
+/// `metadata`).
+///
+/// # Safety
+/// The strings must be null-terminated, and `adapter` must point to a writable pointer.
+#[no_mangle]
+pub unsafe extern "C" fn ah_adapter_new(
+    service_registry_address: *const c_char,
+    authorization_address: *const c_char,
+    orchestrator_address: *const c_char,
+    client_system_json: *const c_char,
+    adapter: *mut *mut AhAdapter,
+) -> AhErrorCode {
+    call(|| {
+        let new_adapter = ArrowheadSystemAdapter::new(
+            read_str(service_registry_address)?,
+            read_str(authorization_address)?,
+            read_str(orchestrator_address)?,
+            read_json::<ArrowheadSystem<_>>(client_system_json)?,
+        )?;
+        let adapter = write_target(adapter)?;
+        *adapter = Box::into_raw(Box::new(AhAdapter {
+            adapter: new_adapter,
+        }));
+        Ok(())
+    })
+}
+
+/// Selects the Arrowhead version of the core systems (`"4.4.0"` or `"4.6.0"`).
+///
+/// # Safety
+/// `adapter` must be created by `ah_adapter_new`, `version` must be null-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn ah_adapter_set_version(
+    adapter: *mut AhAdapter,
+    version: *const c_char,
+) -> AhErrorCode {
+    call(|| {
+        let version: ArrowheadVersion =
+            serde_json::from_value(serde_json::Value::from(read_str(version)?))
+                .map_err(|e| FfiError::InvalidArgument(format!("Unknown version: {}", e)))?;
+        let adapter = adapter
+            .as_mut()
+            .ok_or_else(|| FfiError::InvalidArgument("The adapter is null".to_owned()))?;
+        adapter.adapter.version = version;
+        Ok(())
+    })
+}
+
+/// Enables (the default) or disables the validation of the names in the registered services and
+/// in the orchestration requests against the naming conventions of the core systems.
+///
+/// # Safety
+/// `adapter` must be created by `ah_adapter_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ah_adapter_set_naming_validation(
+    adapter: *mut AhAdapter,
+    enabled: bool,
+) -> AhErrorCode {
+    call(|| {
+        let adapter = adapter
+            .as_mut()
+            .ok_or_else(|| FfiError::InvalidArgument("The adapter is null".to_owned()))?;
+        adapter.adapter.naming_validation = enabled;
+        Ok(())
+    })
+}
+
+/// Releases the adapter. Null is accepted.
+///
+/// # Safety
+/// `adapter` must be created by `ah_adapter_new`, and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn ah_adapter_free(adapter: *mut AhAdapter) {
+    if !adapter.is_null() {
+        drop(Box::from_raw(adapter));
+    }
+}
+
+/// Registers a service of the client system. The input is the JSON of the service without the
+/// provider system, the output is the JSON of the registered service entry.
+///
+/// # Safety
+/// `adapter` must be created by `ah_adapter_new`, `input_json` must be null-terminated, and
+/// `output_json` must point to a writable pointer.
+#[no_mangle]
+pub unsafe extern "C" fn ah_register_service(
+    adapter: *const AhAdapter,
+    input_json: *const c_char,
+    output_json: *mut *mut c_char,
+) -> AhErrorCode {
+    call_with_output(output_json, || {
+        Ok(read_adapter(adapter)?.register_service(read_json(input_json)?)?)
+    })
+}
+
+/// Unregisters the service of the client system with the given service definition.
+///
+/// # Safety
+/// `adapter` must be created by `ah_adapter_new`, `service_definition` must be null-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn ah_unregister_service(
+    adapter: *const AhAdapter,
+    service_definition: *const c_char,
+) -> AhErrorCode {
+    call(|| {
+        read_adapter(adapter)?.unregister_service(read_str(service_definition)?)?;
+        Ok(())
+    })
+}
+
+/// Unregisters exactly the given service entry (the JSON returned by `ah_register_service` or an
+/// element of the query result), keeping the other entries of the same service definition.
+///
+/// # Safety
+/// `adapter` must be created by `ah_adapter_new`, `service_json` must be null-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn ah_unregister_service_entry(
+    adapter: *const AhAdapter,
+    service_json: *const c_char,
+) -> AhErrorCode {
+    call(|| {
+        read_adapter(adapter)?.unregister_service_entry(&read_json(service_json)?)?;
+        Ok(())
+    })
+}
+
+/// Queries the Service Registry. The input is the JSON of a service query form, the output is the
+/// JSON of the service query list.
+///
+/// # Safety
+/// `adapter` must be created by `ah_adapter_new`, `query_form_json` must be null-terminated, and
+/// `output_json` must point to a writable pointer.
+#[no_mangle]
+pub unsafe extern "C" fn ah_query_service(
+    adapter: *const AhAdapter,
+    query_form_json: *const c_char,
+    output_json: *mut *mut c_char,
+) -> AhErrorCode {
+    call_with_output(output_json, || {
+        Ok(read_adapter(adapter)?.query_service(&read_json(query_form_json)?)?)
+    })
+}
+
+/// Requests orchestration for the client system. The input is the JSON of the service request
+/// form without the requester system, the output is the JSON of the orchestration response.
+///
+/// # Safety
+/// `adapter` must be created by `ah_adapter_new`, `input_json` must be null-terminated, and
+/// `output_json` must point to a writable pointer.
+#[no_mangle]
+pub unsafe extern "C" fn ah_request_orchestration(
+    adapter: *const AhAdapter,
+    input_json: *const c_char,
+    output_json: *mut *mut c_char,
+) -> AhErrorCode {
+    call_with_output(output_json, || {
+        Ok(read_adapter(adapter)?.request_orchestration(read_json(input_json)?)?)
+    })
+}
+
+/// Requests the stored orchestration of the consumer with the given id. The output is the JSON
+/// of the orchestration response.
+///
+/// # Safety
+/// `adapter` must be created by `ah_adapter_new`, and `output_json` must point to a writable
+/// pointer.
+#[no_mangle]
+pub unsafe extern "C" fn ah_request_orchestration_by_id(
+    adapter: *const AhAdapter,
+    id: i64,
+    output_json: *mut *mut c_char,
+) -> AhErrorCode {
+    call_with_output(output_json, || {
+        Ok(read_adapter(adapter)?.request_orchestration_by_id(id)?)
+    })
+}
+
+/// Returns the public key of the Authorization.
+///
+/// # Safety
+/// `adapter` must be created by `ah_adapter_new`, and `public_key` must point to a writable
+/// pointer.
+#[no_mangle]
+pub unsafe extern "C" fn ah_get_public_key(
+    adapter: *const AhAdapter,
+    public_key: *mut *mut c_char,
+) -> AhErrorCode {
+    let public_key = match write_target(public_key) {
+        Ok(public_key) => public_key,
+        Err(err) => return fail(err),
+    };
+    *public_key = ptr::null_mut();
+    call(|| {
+        let key = read_adapter(adapter)?.get_public_key()?;
+        *public_key = to_c_string(key)?;
+        Ok(())
+    })
+}
+
+/// Returns the message of the last error on the calling thread, or null. The message is valid
+/// until the next call of the library on the same thread.
+#[no_mangle]
+pub extern "C" fn ah_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last_error| {
+        last_error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Releases a string returned by the library. Null is accepted.
+///
+/// # Safety
+/// `string` must be returned by the library, and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn ah_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+// Runs the call, converting the errors and the panics to error codes.
+fn call<F>(call: F) -> AhErrorCode
+where
+    F: FnOnce() -> FfiResult<()>,
+{
+    LAST_ERROR.with(|last_error| last_error.borrow_mut().take());
+    match panic::catch_unwind(AssertUnwindSafe(call)) {
+        Ok(Ok(_)) => AhErrorCode::Ok,
+        Ok(Err(err)) => fail(err),
+        Err(_) => {
+            set_last_error("The library panicked".to_owned());
+            AhErrorCode::Panic
+        }
+    }
+}
+
+// Runs the call and writes its result as JSON to the output. On ArrowheadError the exception
+// returned by the core system is written to the output instead.
+unsafe fn call_with_output<T, F>(output_json: *mut *mut c_char, call_adapter: F) -> AhErrorCode
+where
+    T: Serialize,
+    F: FnOnce() -> FfiResult<T>,
+{
+    let output_json = match write_target(output_json) {
+        Ok(output_json) => output_json,
+        Err(err) => return fail(err),
+    };
+    *output_json = ptr::null_mut();
+    call(|| {
+        let output = match call_adapter() {
+            Ok(output) => output,
+            Err(FfiError::Adapter(Error::ArrowheadError(exception))) => {
+                *output_json = to_c_string(serialize(&exception)?)?;
+                return Err(Error::ArrowheadError(exception).into());
+            }
+            Err(err) => return Err(err),
+        };
+        *output_json = to_c_string(serialize(&output)?)?;
+        Ok(())
+    })
+}
+
+fn fail(err: FfiError) -> AhErrorCode {
+    let (code, message) = match err {
+        FfiError::InvalidArgument(message) => (AhErrorCode::InvalidArgument, message),
+        FfiError::Adapter(err) => (
+            match &err {
+                Error::HttpError(_) => AhErrorCode::HttpError,
+                Error::ArrowheadError(_) => AhErrorCode::ArrowheadError,
+                Error::ServerError(_) => AhErrorCode::ServerError,
+                Error::InvocationError(_) => AhErrorCode::InvocationError,
+                Error::CircuitOpenError(_) => AhErrorCode::CircuitOpenError,
+                Error::UnsupportedError(_) => AhErrorCode::UnsupportedError,
+                Error::ConfigError(_) => AhErrorCode::ConfigError,
+                Error::ValidationError(_) => AhErrorCode::ValidationError,
+                Error::UnexpectedResponseError(_) => AhErrorCode::UnexpectedResponseError,
+                Error::TransportError(_) => AhErrorCode::TransportError,
+            },
+            format!("{}", err),
+        ),
+    };
+    set_last_error(message);
+    code
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+}
+
+unsafe fn read_str<'a>(string: *const c_char) -> FfiResult<&'a str> {
+    if string.is_null() {
+        return Err(FfiError::InvalidArgument("A string is null".to_owned()));
+    }
+    CStr::from_ptr(string)
+        .to_str()
+        .map_err(|e| FfiError::InvalidArgument(format!("{}", e)))
+}
+
+unsafe fn read_json<T: DeserializeOwned>(json: *const c_char) -> FfiResult<T> {
+    serde_json::from_str(read_str(json)?).map_err(|e| FfiError::InvalidArgument(format!("{}", e)))
+}
+
+unsafe fn read_adapter<'a>(adapter: *const AhAdapter) -> FfiResult<&'a ArrowheadSystemAdapter> {
+    adapter
+        .as_ref()
+        .map(|adapter| &adapter.adapter)
+        .ok_or_else(|| FfiError::InvalidArgument("The adapter is null".to_owned()))
+}
+
+unsafe fn write_target<'a, T>(target: *mut *mut T) -> FfiResult<&'a mut *mut T> {
+    target
+        .as_mut()
+        .ok_or_else(|| FfiError::InvalidArgument("The output pointer is null".to_owned()))
+}
+
+fn serialize<T: Serialize>(value: &T) -> FfiResult<String> {
+    serde_json::to_string(value).map_err(|e| FfiError::InvalidArgument(format!("{}", e)))
+}
+
+fn to_c_string(string: String) -> FfiResult<*mut c_char> {
+    CString::new(string)
+        .map(CString::into_raw)
+        .map_err(|e| FfiError::InvalidArgument(format!("{}", e)))
+}
@@ -0,0 +1,150 @@
+/*
+ * Exercises the C API against the mock core systems whose base URLs are given as arguments:
+ * <service registry> <authorization> <orchestrator>
+ */
+
+#include "ah_system_adapter.h"
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+static int failures = 0;
+
+#define CHECK(condition)                                                  \
+    do {                                                                  \
+        if (!(condition)) {                                               \
+            const char *message = ah_last_error_message();                \
+            fprintf(stderr, "%s:%d: check failed: %s (last error: %s)\n", \
+                    __FILE__, __LINE__, #condition,                       \
+                    message != NULL ? message : "none");                  \
+            failures++;                                                   \
+        }                                                                 \
+    } while (0)
+
+static int contains(const char *string, const char *part) {
+    return string != NULL && strstr(string, part) != NULL;
+}
+
+static const char *CLIENT_SYSTEM =
+    "{\"systemName\": \"thermometer\", \"address\": \"127.0.0.1\", \"port\": 8080}";
+
+static const char *SERVICE =
+    "{\"serviceDefinition\": \"temperature\", \"serviceUri\": \"/temperature\","
+    " \"interfaces\": [\"HTTP-INSECURE-JSON\"]}";
+
+static const char *OTHER_SERVICE =
+    "{\"serviceDefinition\": \"temperature\", \"serviceUri\": \"/celsius\","
+    " \"interfaces\": [\"HTTP-INSECURE-JSON\"]}";
+
+static const char *INVALID_SERVICE =
+    "{\"serviceDefinition\": \"Temperature\", \"serviceUri\": \"/temperature\","
+    " \"interfaces\": [\"HTTP-INSECURE-JSON\"]}";
+
+static const char *QUERY_FORM =
+    "{\"serviceDefinitionRequirement\": \"temperature\"}";
+
+static const char *ORCHESTRATION =
+    "{\"requestedService\": {\"serviceDefinitionRequirement\": \"temperature\"},"
+    " \"orchestrationFlags\": {\"overrideStore\": true}}";
+
+static void test_services(AhAdapter *adapter) {
+    char *output = NULL;
+    char *other_entry = NULL;
+
+    CHECK(ah_register_service(adapter, SERVICE, &output) == AH_ERROR_CODE_OK);
+    CHECK(contains(output, "\"serviceDefinition\":\"temperature\""));
+    ah_string_free(output);
+
+    /* An entry with another URI, unregistered exactly */
+    CHECK(ah_register_service(adapter, OTHER_SERVICE, &other_entry) == AH_ERROR_CODE_OK);
+    CHECK(ah_unregister_service_entry(adapter, other_entry) == AH_ERROR_CODE_OK);
+    CHECK(ah_unregister_service_entry(adapter, other_entry) == AH_ERROR_CODE_ARROWHEAD_ERROR);
+    ah_string_free(other_entry);
+
+    /* The exception of the Service Registry is returned in the output */
+    CHECK(ah_register_service(adapter, SERVICE, &output) == AH_ERROR_CODE_ARROWHEAD_ERROR);
+    CHECK(contains(output, "INVALID_PARAMETER"));
+    CHECK(ah_last_error_message() != NULL);
+    ah_string_free(output);
+
+    CHECK(ah_query_service(adapter, QUERY_FORM, &output) == AH_ERROR_CODE_OK);
+    CHECK(contains(output, "\"unfilteredHits\":1"));
+    ah_string_free(output);
+
+    CHECK(ah_request_orchestration(adapter, ORCHESTRATION, &output) == AH_ERROR_CODE_OK);
+    CHECK(contains(output, "\"systemName\":\"thermometer\""));
+    ah_string_free(output);
+
+    CHECK(ah_request_orchestration_by_id(adapter, 9999, &output) ==
+          AH_ERROR_CODE_ARROWHEAD_ERROR);
+    CHECK(contains(output, "INVALID_PARAMETER"));
+    ah_string_free(output);
+
+    CHECK(ah_unregister_service(adapter, "temperature") == AH_ERROR_CODE_OK);
+    CHECK(ah_query_service(adapter, QUERY_FORM, &output) == AH_ERROR_CODE_OK);
+    CHECK(contains(output, "\"unfilteredHits\":0"));
+    ah_string_free(output);
+}
+
+static void test_public_key(AhAdapter *adapter) {
+    char *public_key = NULL;
+
+    CHECK(ah_get_public_key(adapter, &public_key) == AH_ERROR_CODE_OK);
+    CHECK(public_key != NULL && strcmp(public_key, "mock-public-key") == 0);
+    ah_string_free(public_key);
+}
+
+static void test_invalid_arguments(AhAdapter *adapter) {
+    char *output = NULL;
+
+    CHECK(ah_register_service(adapter, "{}", &output) == AH_ERROR_CODE_INVALID_ARGUMENT);
+    CHECK(output == NULL);
+    CHECK(ah_query_service(NULL, QUERY_FORM, &output) == AH_ERROR_CODE_INVALID_ARGUMENT);
+    CHECK(ah_query_service(adapter, NULL, &output) == AH_ERROR_CODE_INVALID_ARGUMENT);
+    CHECK(ah_query_service(adapter, QUERY_FORM, NULL) == AH_ERROR_CODE_INVALID_ARGUMENT);
+    CHECK(ah_register_service(adapter, INVALID_SERVICE, &output) ==
+          AH_ERROR_CODE_VALIDATION_ERROR);
+    CHECK(output == NULL);
+    CHECK(contains(ah_last_error_message(), "Invalid service definition 'Temperature'"));
+    CHECK(ah_adapter_set_version(adapter, "4.5.0") == AH_ERROR_CODE_INVALID_ARGUMENT);
+    CHECK(ah_adapter_set_version(adapter, "4.6.0") == AH_ERROR_CODE_OK);
+    CHECK(ah_last_error_message() == NULL);
+}
+
+static void test_unreachable_core(void) {
+    AhAdapter *adapter = NULL;
+    char *output = NULL;
+
+    CHECK(ah_adapter_new("http://127.0.0.1:1/serviceregistry/",
+                         "http://127.0.0.1:1/authorization/",
+                         "http://127.0.0.1:1/orchestrator/", CLIENT_SYSTEM,
+                         &adapter) == AH_ERROR_CODE_OK);
+    CHECK(ah_query_service(adapter, QUERY_FORM, &output) == AH_ERROR_CODE_HTTP_ERROR);
+    CHECK(ah_adapter_set_naming_validation(adapter, false) == AH_ERROR_CODE_OK);
+    CHECK(ah_register_service(adapter, INVALID_SERVICE, &output) == AH_ERROR_CODE_HTTP_ERROR);
+    ah_adapter_free(adapter);
+}
+
+int main(int argc, char **argv) {
+    AhAdapter *adapter = NULL;
+
+    if (argc != 4) {
+        fprintf(stderr, "usage: %s <service registry> <authorization> <orchestrator>\n", argv[0]);
+        return 2;
+    }
+    CHECK(ah_adapter_new(argv[1], argv[2], argv[3], CLIENT_SYSTEM, &adapter) == AH_ERROR_CODE_OK);
+    if (adapter == NULL) {
+        return 1;
+    }
+
+    test_services(adapter);
+    test_public_key(adapter);
+    test_invalid_arguments(adapter);
+    test_unreachable_core();
+
+    ah_adapter_free(adapter);
+    ah_adapter_free(NULL);
+    ah_string_free(NULL);
+    return failures == 0 ? 0 : 1;
+}
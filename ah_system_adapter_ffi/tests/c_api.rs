@@ -0,0 +1,55 @@
+// Compiles tests/c/test_adapter.c against the generated header and the shared library, and runs
+// it against a mock core.
+
+use ah_system_adapter::MockCore;
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// The shared library is built by `cargo test` in the directory of the test executable
+// (target/debug/deps); it is copied to target/debug only by `cargo build`.
+fn library_dir() -> PathBuf {
+    env::current_exe().unwrap().parent().unwrap().to_path_buf()
+}
+
+#[test]
+fn c_api() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let executable = Path::new(env!("CARGO_TARGET_TMPDIR")).join("test_adapter");
+    let library_dir = library_dir();
+
+    let compiled = Command::new(env::var("CC").unwrap_or_else(|_| "cc".to_owned()))
+        .arg("-Wall")
+        .arg("-Werror")
+        .arg("-I")
+        .arg(manifest_dir.join("include"))
+        .arg(manifest_dir.join("tests/c/test_adapter.c"))
+        .arg("-o")
+        .arg(&executable)
+        .arg("-L")
+        .arg(&library_dir)
+        .arg("-lah_system_adapter_ffi")
+        .status()
+        .expect("a C compiler is installed");
+    assert!(compiled.success(), "the C test does not compile");
+
+    let core = MockCore::start().unwrap();
+    let output = Command::new(&executable)
+        .args([
+            core.service_registry_address(),
+            core.authorization_address(),
+            core.orchestrator_address(),
+        ])
+        .env("LD_LIBRARY_PATH", &library_dir)
+        .env("DYLD_LIBRARY_PATH", &library_dir)
+        .output()
+        .unwrap();
+    core.stop();
+
+    assert!(
+        output.status.success(),
+        "the C test failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
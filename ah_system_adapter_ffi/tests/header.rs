@@ -0,0 +1,20 @@
+// Checks that the checked-in C header matches the one generated by build.rs from src/lib.rs.
+// Running the test with AH_UPDATE_HEADER=1 overwrites the checked-in header.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+const GENERATED: &str = include_str!(concat!(env!("OUT_DIR"), "/ah_system_adapter.h"));
+
+#[test]
+fn header_is_up_to_date() {
+    let header = Path::new(env!("CARGO_MANIFEST_DIR")).join("include/ah_system_adapter.h");
+    if env::var_os("AH_UPDATE_HEADER").is_some() {
+        fs::write(&header, GENERATED).unwrap();
+    }
+    assert!(
+        fs::read_to_string(&header).unwrap() == GENERATED,
+        "include/ah_system_adapter.h is out of date, run `AH_UPDATE_HEADER=1 cargo test --test header`"
+    );
+}
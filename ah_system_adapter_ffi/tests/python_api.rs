@@ -0,0 +1,39 @@
+// Runs the tests of the Python bindings (ah_system_adapter_py) with the shared library against a
+// mock core.
+
+use ah_system_adapter::MockCore;
+
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn python_api() {
+    let package_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../ah_system_adapter_py");
+    // The shared library is built by `cargo test` in the directory of the test executable
+    let library = env::current_exe().unwrap().with_file_name(format!(
+        "{}ah_system_adapter_ffi{}",
+        env::consts::DLL_PREFIX,
+        env::consts::DLL_SUFFIX
+    ));
+
+    let core = MockCore::start().unwrap();
+    let output = Command::new(env::var("PYTHON").unwrap_or_else(|_| "python3".to_owned()))
+        .args(["-m", "unittest", "discover", "-v", "-s", "tests"])
+        .current_dir(&package_dir)
+        .env("PYTHONPATH", &package_dir)
+        .env("PYTHONDONTWRITEBYTECODE", "1")
+        .env("AH_SYSTEM_ADAPTER_LIB", &library)
+        .env("AH_MOCK_CORE_URL", core.base_url())
+        .output()
+        .expect("Python 3 is installed");
+    core.stop();
+
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "the Python tests failed:\n{}", log);
+    assert!(
+        !log.contains("skipped"),
+        "the Python tests are skipped:\n{}",
+        log
+    );
+}
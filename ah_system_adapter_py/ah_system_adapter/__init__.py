@@ -0,0 +1,41 @@
+"""Python bindings of ah_system_adapter.
+
+The adapter calls the shared library of ah_system_adapter_ffi. The data types of the core
+services are dicts in the JSON format of the core systems.
+"""
+
+from .adapter import V4_4, V4_6, ArrowheadSystemAdapter
+from .errors import (
+    AdapterError,
+    ArrowheadError,
+    CircuitOpenError,
+    ConfigError,
+    HttpError,
+    InvalidArgumentError,
+    InvocationError,
+    PanicError,
+    ServerError,
+    TransportError,
+    UnexpectedResponseError,
+    UnsupportedError,
+    ValidationError,
+)
+
+__all__ = [
+    "V4_4",
+    "V4_6",
+    "ArrowheadSystemAdapter",
+    "AdapterError",
+    "ArrowheadError",
+    "CircuitOpenError",
+    "ConfigError",
+    "HttpError",
+    "InvalidArgumentError",
+    "InvocationError",
+    "PanicError",
+    "ServerError",
+    "TransportError",
+    "UnexpectedResponseError",
+    "UnsupportedError",
+    "ValidationError",
+]
@@ -0,0 +1,74 @@
+"""Loads the shared library of ah_system_adapter_ffi and declares its functions."""
+
+import ctypes
+import ctypes.util
+import os
+import sys
+
+LIBRARY_ENV = "AH_SYSTEM_ADAPTER_LIB"
+_NAME = "ah_system_adapter_ffi"
+
+
+class AhAdapter(ctypes.Structure):
+    pass
+
+
+_ADAPTER = ctypes.POINTER(AhAdapter)
+_STRING = ctypes.c_void_p  # strings returned by the library are released by ah_string_free
+_CODE = ctypes.c_int
+
+_PROTOTYPES = {
+    "ah_adapter_new": (
+        _CODE,
+        [ctypes.c_char_p] * 4 + [ctypes.POINTER(_ADAPTER)],
+    ),
+    "ah_adapter_set_version": (_CODE, [_ADAPTER, ctypes.c_char_p]),
+    "ah_adapter_set_naming_validation": (_CODE, [_ADAPTER, ctypes.c_bool]),
+    "ah_adapter_free": (None, [_ADAPTER]),
+    "ah_register_service": (_CODE, [_ADAPTER, ctypes.c_char_p, ctypes.POINTER(_STRING)]),
+    "ah_unregister_service": (_CODE, [_ADAPTER, ctypes.c_char_p]),
+    "ah_unregister_service_entry": (_CODE, [_ADAPTER, ctypes.c_char_p]),
+    "ah_query_service": (_CODE, [_ADAPTER, ctypes.c_char_p, ctypes.POINTER(_STRING)]),
+    "ah_request_orchestration": (_CODE, [_ADAPTER, ctypes.c_char_p, ctypes.POINTER(_STRING)]),
+    "ah_request_orchestration_by_id": (
+        _CODE,
+        [_ADAPTER, ctypes.c_int64, ctypes.POINTER(_STRING)],
+    ),
+    "ah_get_public_key": (_CODE, [_ADAPTER, ctypes.POINTER(_STRING)]),
+    "ah_last_error_message": (ctypes.c_char_p, []),
+    "ah_string_free": (None, [_STRING]),
+}
+
+
+def _library_path():
+    # The library is searched in the path of the environment variable, next to the package,
+    # then in the paths of the system.
+    if os.environ.get(LIBRARY_ENV):
+        return os.environ[LIBRARY_ENV]
+    if sys.platform == "win32":
+        file_name = _NAME + ".dll"
+    elif sys.platform == "darwin":
+        file_name = "lib" + _NAME + ".dylib"
+    else:
+        file_name = "lib" + _NAME + ".so"
+    bundled = os.path.join(os.path.dirname(os.path.abspath(__file__)), file_name)
+    if os.path.exists(bundled):
+        return bundled
+    found = ctypes.util.find_library(_NAME)
+    if found is None:
+        raise ImportError(
+            "The library {} is not found, set {} to its path".format(file_name, LIBRARY_ENV)
+        )
+    return found
+
+
+def load():
+    library = ctypes.CDLL(_library_path())
+    for name, (restype, argtypes) in _PROTOTYPES.items():
+        function = getattr(library, name)
+        function.restype = restype
+        function.argtypes = argtypes
+    return library
+
+
+library = load()
@@ -0,0 +1,125 @@
+"""The adapter of the Arrowhead core systems."""
+
+import ctypes
+import json
+from typing import Any, Optional
+
+from . import errors
+from ._native import AhAdapter, library
+
+# Arrowhead versions of the core systems
+V4_4 = "4.4.0"
+V4_6 = "4.6.0"
+
+_OK = 0
+
+
+def _encode(value: Any) -> bytes:
+    return (value if isinstance(value, str) else json.dumps(value)).encode("utf-8")
+
+
+def _take_string(pointer: ctypes.c_void_p) -> Optional[str]:
+    if not pointer.value:
+        return None
+    try:
+        return ctypes.string_at(pointer.value).decode("utf-8")
+    finally:
+        library.ah_string_free(pointer)
+
+
+def _check(code: int, output: Optional[str] = None) -> None:
+    if code != _OK:
+        message = library.ah_last_error_message()
+        raise errors.from_code(code, message.decode("utf-8") if message else None, output)
+
+
+class ArrowheadSystemAdapter:
+    """Client of the Service Registry, Authorization and Orchestrator core systems.
+
+    The inputs and outputs are dicts (or JSON strings as inputs) in the JSON format of the core
+    systems, e.g. the client system is
+    `{"systemName": "thermometer", "address": "127.0.0.1", "port": 8080}`.
+    """
+
+    def __init__(
+        self,
+        service_registry_address: str,
+        authorization_address: str,
+        orchestrator_address: str,
+        client_system: Any,
+        version: Optional[str] = None,
+    ):
+        self._adapter = ctypes.POINTER(AhAdapter)()
+        _check(
+            library.ah_adapter_new(
+                _encode(service_registry_address),
+                _encode(authorization_address),
+                _encode(orchestrator_address),
+                _encode(client_system),
+                ctypes.byref(self._adapter),
+            )
+        )
+        if version is not None:
+            self.set_version(version)
+
+    def set_version(self, version: str) -> None:
+        """Selects the Arrowhead version of the core systems (`V4_4` or `V4_6`)."""
+        _check(library.ah_adapter_set_version(self._handle(), _encode(version)))
+
+    def set_naming_validation(self, enabled: bool) -> None:
+        """Enables (the default) or disables checking the names against the naming conventions."""
+        _check(library.ah_adapter_set_naming_validation(self._handle(), enabled))
+
+    def register_service(self, service: Any) -> dict:
+        """Registers a service of the client system, returns the registered service entry."""
+        return self._call_json(library.ah_register_service, _encode(service))
+
+    def unregister_service(self, service_definition: str) -> None:
+        _check(library.ah_unregister_service(self._handle(), _encode(service_definition)))
+
+    def unregister_service_entry(self, service: Any) -> None:
+        """Unregisters exactly the given entry, e.g. the one returned by `register_service`."""
+        _check(library.ah_unregister_service_entry(self._handle(), _encode(service)))
+
+    def query_service(self, query_form: Any) -> dict:
+        """Queries the Service Registry, returns the service query list."""
+        return self._call_json(library.ah_query_service, _encode(query_form))
+
+    def request_orchestration(self, request_form: Any) -> dict:
+        """Requests orchestration for the client system, returns the orchestration response."""
+        return self._call_json(library.ah_request_orchestration, _encode(request_form))
+
+    def request_orchestration_by_id(self, consumer_id: int) -> dict:
+        return self._call_json(library.ah_request_orchestration_by_id, consumer_id)
+
+    def get_public_key(self) -> str:
+        output = ctypes.c_void_p()
+        _check(library.ah_get_public_key(self._handle(), ctypes.byref(output)))
+        return _take_string(output) or ""
+
+    def close(self) -> None:
+        """Releases the adapter, it can not be used afterwards."""
+        if getattr(self, "_adapter", None):
+            library.ah_adapter_free(self._adapter)
+        self._adapter = None
+
+    def __enter__(self) -> "ArrowheadSystemAdapter":
+        return self
+
+    def __exit__(self, *exc_info: Any) -> None:
+        self.close()
+
+    def __del__(self) -> None:
+        self.close()
+
+    def _handle(self):
+        if not self._adapter:
+            raise errors.InvalidArgumentError("The adapter is closed")
+        return self._adapter
+
+    def _call_json(self, function, argument) -> dict:
+        output = ctypes.c_void_p()
+        code = function(self._handle(), argument, ctypes.byref(output))
+        output_json = _take_string(output)
+        _check(code, output_json)
+        return json.loads(output_json)
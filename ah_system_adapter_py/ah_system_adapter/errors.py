@@ -0,0 +1,112 @@
+"""Exceptions mapping the error codes of the C API (and the variants of `Error`)."""
+
+import json
+from typing import Optional
+
+
+class AdapterError(Exception):
+    """Base of the errors of the adapter, `code` is the `AhErrorCode` of the C API."""
+
+    code = -1
+
+    def __init__(self, message: str):
+        super().__init__(message)
+        self.message = message
+
+
+class InvalidArgumentError(AdapterError, ValueError):
+    """The input is not valid JSON of the expected data type."""
+
+    code = 1
+
+
+class HttpError(AdapterError):
+    code = 2
+
+
+class ArrowheadError(AdapterError):
+    """The exception returned by a core system."""
+
+    code = 3
+
+    def __init__(self, message: str, exception: Optional[dict] = None):
+        super().__init__(message)
+        exception = exception or {}
+        self.error_message = exception.get("errorMessage", message)
+        self.error_code = exception.get("errorCode")
+        self.exception_type = exception.get("exceptionType")
+        self.origin = exception.get("origin")
+
+
+class ServerError(AdapterError):
+    code = 4
+
+
+class InvocationError(AdapterError):
+    code = 5
+
+
+class CircuitOpenError(AdapterError):
+    code = 6
+
+
+class UnsupportedError(AdapterError):
+    code = 7
+
+
+class PanicError(AdapterError):
+    """The library panicked, the adapter should not be used anymore."""
+
+    code = 8
+
+
+class ConfigError(AdapterError):
+    code = 9
+
+
+class ValidationError(AdapterError, ValueError):
+    """A name breaks the naming conventions of the core systems, the request was not sent."""
+
+    code = 10
+
+
+class UnexpectedResponseError(AdapterError):
+    """A core system answered with a response not matching the request."""
+
+    code = 11
+
+
+class TransportError(AdapterError):
+    """A transport other than HTTP (MQTT, CoAP) failed, e.g. no response in time."""
+
+    code = 12
+
+
+_ERRORS = {
+    error.code: error
+    for error in (
+        InvalidArgumentError,
+        HttpError,
+        ArrowheadError,
+        ServerError,
+        InvocationError,
+        CircuitOpenError,
+        UnsupportedError,
+        PanicError,
+        ConfigError,
+        ValidationError,
+        UnexpectedResponseError,
+        TransportError,
+    )
+}
+
+
+def from_code(code: int, message: Optional[str], output: Optional[str]) -> AdapterError:
+    """Creates the exception of an error code, with the output of the call (if any)."""
+    message = message or "Unknown error"
+    error = _ERRORS.get(code)
+    if error is None:
+        return AdapterError("Unknown error code {}: {}".format(code, message))
+    if error is ArrowheadError:
+        return ArrowheadError(message, json.loads(output) if output else None)
+    return error(message)
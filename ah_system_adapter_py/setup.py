@@ -0,0 +1,72 @@
+"""Builds the shared library of ah_system_adapter_ffi with cargo and bundles it in the package.
+
+The library is built by `cargo build --release` in ../ah_system_adapter_ffi, or taken from the
+path in the AH_SYSTEM_ADAPTER_LIB environment variable. As the package holds a native library,
+the wheel is tagged with the platform.
+"""
+
+import os
+import subprocess
+import sys
+
+from setuptools import setup
+from setuptools.command.build_py import build_py
+
+try:
+    from setuptools.command.bdist_wheel import bdist_wheel
+except ImportError:
+    try:
+        from wheel.bdist_wheel import bdist_wheel
+    except ImportError:
+        bdist_wheel = None
+
+LIBRARY_ENV = "AH_SYSTEM_ADAPTER_LIB"
+PACKAGE = "ah_system_adapter"
+FFI_DIR = os.path.join(os.path.dirname(os.path.abspath(__file__)), "..", "ah_system_adapter_ffi")
+
+
+def library_file_name():
+    if sys.platform == "win32":
+        return "ah_system_adapter_ffi.dll"
+    if sys.platform == "darwin":
+        return "libah_system_adapter_ffi.dylib"
+    return "libah_system_adapter_ffi.so"
+
+
+def build_library():
+    if not os.path.exists(os.path.join(FFI_DIR, "Cargo.toml")):
+        raise RuntimeError(
+            "{} is not found, set {} to the path of the library".format(FFI_DIR, LIBRARY_ENV)
+        )
+    subprocess.run(
+        [os.environ.get("CARGO", "cargo"), "build", "--release", "--lib"],
+        cwd=FFI_DIR,
+        check=True,
+    )
+    target_dir = os.environ.get("CARGO_TARGET_DIR", os.path.join(FFI_DIR, "target"))
+    return os.path.join(target_dir, "release", library_file_name())
+
+
+class BuildPyWithLibrary(build_py):
+    def run(self):
+        super().run()
+        library = os.environ.get(LIBRARY_ENV) or build_library()
+        self.copy_file(library, os.path.join(self.build_lib, PACKAGE, library_file_name()))
+
+
+cmdclass = {"build_py": BuildPyWithLibrary}
+
+if bdist_wheel is not None:
+
+    class PlatformWheel(bdist_wheel):
+        # The library is loaded by ctypes, so the wheel fits any Python 3 of the platform.
+        def finalize_options(self):
+            super().finalize_options()
+            self.root_is_pure = False
+
+        def get_tag(self):
+            return ("py3", "none", super().get_tag()[2])
+
+    cmdclass["bdist_wheel"] = PlatformWheel
+
+setup(cmdclass=cmdclass)
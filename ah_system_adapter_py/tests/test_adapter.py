@@ -0,0 +1,115 @@
+"""Tests of the bindings against a mock core, whose base URL is given by AH_MOCK_CORE_URL.
+
+They are run by `cargo test` of ah_system_adapter_ffi, which starts the mock core.
+"""
+
+import os
+import unittest
+
+import ah_system_adapter
+from ah_system_adapter import (
+    ArrowheadError,
+    ArrowheadSystemAdapter,
+    HttpError,
+    InvalidArgumentError,
+    ValidationError,
+)
+
+MOCK_CORE_URL = os.environ.get("AH_MOCK_CORE_URL")
+
+CLIENT_SYSTEM = {"systemName": "thermometer", "address": "127.0.0.1", "port": 8080}
+
+SERVICE = {
+    "serviceDefinition": "temperature",
+    "serviceUri": "/temperature",
+    "interfaces": ["HTTP-INSECURE-JSON"],
+    "metadata": {"unit": "celsius"},
+}
+
+QUERY_FORM = {"serviceDefinitionRequirement": "temperature"}
+
+
+def adapter(base_url: str) -> ArrowheadSystemAdapter:
+    return ArrowheadSystemAdapter(
+        base_url + "serviceregistry/",
+        base_url + "authorization/",
+        base_url + "orchestrator/",
+        CLIENT_SYSTEM,
+    )
+
+
+@unittest.skipUnless(MOCK_CORE_URL, "AH_MOCK_CORE_URL is not set")
+class AdapterTest(unittest.TestCase):
+    def setUp(self):
+        self.adapter = adapter(MOCK_CORE_URL)
+
+    def tearDown(self):
+        self.adapter.close()
+
+    def test_register_query_unregister(self):
+        registered = self.adapter.register_service(SERVICE)
+        self.assertEqual(registered["serviceDefinition"]["serviceDefinition"], "temperature")
+        self.assertEqual(registered["providerSystem"]["systemName"], "thermometer")
+
+        query_list = self.adapter.query_service(QUERY_FORM)
+        self.assertEqual(query_list["unfilteredHits"], 1)
+        self.assertEqual(query_list["serviceQueryData"][0]["metadata"], {"unit": "celsius"})
+
+        orchestration = self.adapter.request_orchestration(
+            {
+                "requestedService": QUERY_FORM,
+                "orchestrationFlags": {"overrideStore": True},
+            }
+        )
+        self.assertEqual(
+            [o["provider"]["systemName"] for o in orchestration["response"]], ["thermometer"]
+        )
+
+        other = self.adapter.register_service(dict(SERVICE, serviceUri="/celsius"))
+        self.assertEqual(self.adapter.query_service(QUERY_FORM)["unfilteredHits"], 2)
+        self.adapter.unregister_service_entry(other)
+        self.assertEqual(self.adapter.query_service(QUERY_FORM)["unfilteredHits"], 1)
+
+        self.adapter.unregister_service("temperature")
+        self.assertEqual(self.adapter.query_service(QUERY_FORM)["unfilteredHits"], 0)
+
+    def test_arrowhead_error(self):
+        with self.assertRaises(ArrowheadError) as raised:
+            self.adapter.request_orchestration_by_id(9999)
+        self.assertEqual(raised.exception.error_code, 400)
+        self.assertEqual(raised.exception.exception_type, "INVALID_PARAMETER")
+        self.assertEqual(raised.exception.origin, "/orchestrator/orchestration/9999")
+
+    def test_public_key(self):
+        self.assertEqual(self.adapter.get_public_key(), "mock-public-key")
+
+    def test_invalid_arguments(self):
+        with self.assertRaises(InvalidArgumentError):
+            self.adapter.register_service({"serviceUri": "/temperature"})
+        with self.assertRaises(ValueError):
+            self.adapter.set_version("4.5.0")
+        self.adapter.set_version(ah_system_adapter.V4_6)
+
+        self.adapter.close()
+        with self.assertRaises(InvalidArgumentError):
+            self.adapter.query_service(QUERY_FORM)
+
+    def test_http_error(self):
+        with adapter("http://127.0.0.1:1/") as unreachable:
+            with self.assertRaises(HttpError):
+                unreachable.query_service(QUERY_FORM)
+
+    def test_naming_validation(self):
+        invalid_service = dict(SERVICE, serviceDefinition="Temperature")
+        with self.assertRaises(ValidationError) as raised:
+            self.adapter.register_service(invalid_service)
+        self.assertIn("Invalid service definition 'Temperature'", raised.exception.message)
+
+        with adapter("http://127.0.0.1:1/") as unreachable:
+            unreachable.set_naming_validation(False)
+            with self.assertRaises(HttpError):
+                unreachable.register_service(invalid_service)
+
+
+if __name__ == "__main__":
+    unittest.main()